@@ -1,7 +1,7 @@
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tokio::fs;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,6 +10,8 @@ pub struct Config {
     pub default_network: String,
     pub security: SecurityConfig,
     pub server: ServerConfig,
+    #[serde(default)]
+    pub abi: AbiConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +19,21 @@ pub struct NetworkConfig {
     pub rpc_url: String,
     pub chain_id: u64,
     pub explorer_url: Option<String>,
+    /// Etherscan-compatible API for this network; defaults to the Etherscan V2 endpoint
+    #[serde(default)]
+    pub explorer_api_url: Option<String>,
+    /// API key for `explorer_api_url`, instead of `ETHERSCAN_API_KEY`
+    #[serde(default)]
+    pub explorer_api_key: Option<String>,
+    /// Blockscout instance used by the `blockscout` ABI provider
+    #[serde(default)]
+    pub blockscout_url: Option<String>,
+    /// ABI providers to try for this network, overriding `abi.resolution_order`
+    #[serde(default)]
+    pub abi_providers: Option<Vec<String>>,
+    /// Multicall3 contract used by `batch_call`; defaults to the canonical deployment
+    #[serde(default)]
+    pub multicall_address: Option<String>,
     pub gas: GasConfig,
 }
 
@@ -38,6 +55,21 @@ pub struct SecurityConfig {
 pub struct ServerConfig {
     pub transport: String,
     pub stdio: StdioConfig,
+    /// Directory fork session recordings are saved to and loaded from; defaults to
+    /// `fork_recordings` under the cache directory
+    #[serde(default)]
+    pub fork_recordings_dir: Option<PathBuf>,
+}
+
+impl ServerConfig {
+    pub fn fork_recordings_dir(&self) -> PathBuf {
+        self.fork_recordings_dir.clone().unwrap_or_else(|| {
+            dirs::cache_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join("contract-mcp")
+                .join("fork_recordings")
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +77,39 @@ pub struct StdioConfig {
     pub buffer_size: Option<usize>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AbiConfig {
+    /// Base URL of the Sourcify server, e.g. a self-hosted instance
+    pub sourcify_url: String,
+    /// Foundry or Hardhat projects and ABI directories, used by the `artifacts` provider
+    pub artifact_dirs: Vec<PathBuf>,
+    /// Extra function signatures, one per line, for naming selectors in unverified bytecode
+    pub signature_files: Vec<PathBuf>,
+    /// Contract deployed at each address, per network: a contract name (`Token` or
+    /// `Token.sol:Token`) or an artifact path
+    pub deployments: HashMap<String, HashMap<String, String>>,
+    /// ABI providers to try, in order: manual, artifacts, etherscan, sourcify, blockscout
+    pub resolution_order: Vec<String>,
+    /// Seconds before a cached ABI is fetched again; 0 keeps it until the contract code changes
+    pub cache_ttl_secs: u64,
+}
+
+impl Default for AbiConfig {
+    fn default() -> Self {
+        Self {
+            sourcify_url: "https://sourcify.dev/server".to_string(),
+            artifact_dirs: Vec::new(),
+            signature_files: Vec::new(),
+            deployments: HashMap::new(),
+            resolution_order: ["manual", "artifacts", "etherscan", "sourcify", "blockscout"]
+                .map(String::from)
+                .to_vec(),
+            cache_ttl_secs: 7 * 24 * 60 * 60,
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         let mut networks = HashMap::new();
@@ -55,6 +120,11 @@ impl Default for Config {
                 rpc_url: "https://eth-mainnet.g.alchemy.com/v2/demo".to_string(),
                 chain_id: 1,
                 explorer_url: Some("https://etherscan.io".to_string()),
+                explorer_api_url: None,
+                explorer_api_key: None,
+                blockscout_url: None,
+                abi_providers: None,
+                multicall_address: None,
                 gas: GasConfig {
                     default_gas_limit: 100000,
                     max_gas_price: Some(50_000_000_000), // 50 Gwei
@@ -69,6 +139,11 @@ impl Default for Config {
                 rpc_url: "https://eth-sepolia.g.alchemy.com/v2/demo".to_string(),
                 chain_id: 11155111,
                 explorer_url: Some("https://sepolia.etherscan.io".to_string()),
+                explorer_api_url: None,
+                explorer_api_key: None,
+                blockscout_url: None,
+                abi_providers: None,
+                multicall_address: None,
                 gas: GasConfig {
                     default_gas_limit: 100000,
                     max_gas_price: Some(20_000_000_000), // 20 Gwei
@@ -83,6 +158,11 @@ impl Default for Config {
                 rpc_url: "https://polygon-mainnet.g.alchemy.com/v2/demo".to_string(),
                 chain_id: 137,
                 explorer_url: Some("https://polygonscan.com".to_string()),
+                explorer_api_url: None,
+                explorer_api_key: None,
+                blockscout_url: None,
+                abi_providers: None,
+                multicall_address: None,
                 gas: GasConfig {
                     default_gas_limit: 100000,
                     max_gas_price: Some(500_000_000_000), // 500 Gwei
@@ -97,6 +177,11 @@ impl Default for Config {
                 rpc_url: "https://arb-mainnet.g.alchemy.com/v2/demo".to_string(),
                 chain_id: 42161,
                 explorer_url: Some("https://arbiscan.io".to_string()),
+                explorer_api_url: None,
+                explorer_api_key: None,
+                blockscout_url: None,
+                abi_providers: None,
+                multicall_address: None,
                 gas: GasConfig {
                     default_gas_limit: 100000,
                     max_gas_price: Some(5_000_000_000), // 5 Gwei
@@ -118,7 +203,9 @@ impl Default for Config {
                 stdio: StdioConfig {
                     buffer_size: Some(1024 * 1024), // 1MB buffer
                 },
+                fork_recordings_dir: None,
             },
+            abi: AbiConfig::default(),
         }
     }
 }
@@ -293,10 +380,39 @@ require_confirmation = true
 # Server configuration
 [server]
 transport = "stdio"
+# fork_recordings_dir = "/path/to/recordings"  # Where fork session recordings are kept
 
 [server.stdio]
 buffer_size = 1048576  # 1MB
 
+# ABI resolution
+[abi]
+sourcify_url = "https://sourcify.dev/server"  # Point at a self-hosted Sourcify if needed
+resolution_order = ["manual", "artifacts", "etherscan", "sourcify", "blockscout"]
+cache_ttl_secs = 604800  # Refetch cached ABIs after a week; 0 = only when the code changes
+# Foundry/Hardhat projects and directories of *.abi.json or <address>.json files
+# artifact_dirs = ["./my-project", "./abis"]
+# Function signatures ("function balanceOf(address) view returns (uint256)", one per line) used
+# with the built-in ones to infer ABIs of unverified contracts from their bytecode
+# signature_files = ["./signatures.txt"]
+
+# Bind local artifacts to deployed addresses (hardhat-deploy and Foundry broadcast files
+# in artifact_dirs are picked up automatically)
+# [abi.deployments.sepolia]
+# "0x742d35Cc6435C9c1c72c5E7b18BaB7e1DB7a5d6e" = "Token"
+
+# Any network in [networks] gets Etherscan ABIs through the V2 endpoint, using its chain_id.
+# Networks with another Etherscan-compatible explorer can point at it instead:
+# explorer_api_url = "https://api.routescan.io/v2/network/mainnet/evm/43114/etherscan/api"
+# explorer_api_key = "..."
+#
+# Networks without Etherscan support can use their own order and a Blockscout instance:
+# [networks.mychain]
+# blockscout_url = "https://explorer.mychain.example"
+# abi_providers = ["artifacts", "blockscout", "sourcify"]
+# Multicall3 for batch_call, on chains without it at 0xcA11bde05977b3631167028862bE2a173976CA11
+# multicall_address = "0x..."
+
 # Environment variables that can be used:
 # PRIVATE_KEY - Your private key for transaction signing (used as default if not provided in tool calls)
 # ETHERSCAN_API_KEY - Your Etherscan API key for ABI resolution
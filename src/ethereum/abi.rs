@@ -1,9 +1,27 @@
-use alloy::json_abi::JsonAbi;
+use crate::config::{Config, NetworkConfig};
+use crate::ethereum::abi_provider::{
+    AbiLookup, AbiProvider, BlockscoutProvider, EtherscanProvider, ManualProvider, ResolvedAbi,
+    SourcifyProvider,
+};
+use crate::ethereum::artifacts::ArtifactsProvider;
+use crate::ethereum::explorer::ExplorerApi;
+use crate::ethereum::proxy::ProxyInfo;
+use crate::ethereum::selectors::SignatureDb;
+use crate::ethereum::source::{self, SourceCode};
+use crate::ethereum::utils::CachedLookup;
+use alloy::{
+    json_abi::{AbiItem, JsonAbi},
+    primitives::{Address, B256},
+};
 use anyhow::{anyhow, Result};
 use reqwest::Client;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::fs;
 use tracing::{debug, info, warn};
 
@@ -11,11 +29,27 @@ use tracing::{debug, info, warn};
 #[derive(Debug, Clone)]
 pub struct AbiSource {
     pub etherscan_api_key: Option<String>,
+    pub sourcify_url: String,
     pub cache_dir: PathBuf,
+    /// Seconds before a cached ABI is fetched again; 0 keeps entries until the code changes
+    pub cache_ttl_secs: u64,
+    pub artifact_dirs: Vec<PathBuf>,
+    pub signature_files: Vec<PathBuf>,
+    pub deployments: HashMap<String, HashMap<String, String>>,
+    /// Providers to try, in order, for networks without their own `abi_providers`
+    pub resolution_order: Vec<String>,
+    pub networks: HashMap<String, NetworkConfig>,
+    pub default_network: String,
 }
 
 impl Default for AbiSource {
     fn default() -> Self {
+        Self::from_config(&Config::default())
+    }
+}
+
+impl AbiSource {
+    pub fn from_config(config: &Config) -> Self {
         let cache_dir = dirs::cache_dir()
             .unwrap_or_else(|| PathBuf::from("."))
             .join("contract-mcp")
@@ -23,114 +57,345 @@ impl Default for AbiSource {
 
         Self {
             etherscan_api_key: std::env::var("ETHERSCAN_API_KEY").ok(),
+            sourcify_url: config.abi.sourcify_url.clone(),
             cache_dir,
+            cache_ttl_secs: config.abi.cache_ttl_secs,
+            artifact_dirs: config.abi.artifact_dirs.clone(),
+            signature_files: config.abi.signature_files.clone(),
+            deployments: config.abi.deployments.clone(),
+            resolution_order: config.abi.resolution_order.clone(),
+            networks: config.networks.clone(),
+            default_network: config.default_network.clone(),
         }
     }
 }
 
-/// ABI resolver that can fetch and cache contract ABIs
+/// A cached ABI, with when and against which code it was fetched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedAbi {
+    #[serde(flatten)]
+    pub resolved: ResolvedAbi,
+    /// Unix timestamp of the fetch
+    pub fetched_at: u64,
+    /// keccak256 of the contract's runtime code at fetch time
+    pub code_hash: Option<B256>,
+}
+
+impl CachedAbi {
+    /// Why the entry can no longer be used, if it can't
+    fn invalidation_reason(&self, ttl_secs: u64, code_hash: Option<B256>) -> Option<&'static str> {
+        if ttl_secs > 0 && unix_now().saturating_sub(self.fetched_at) >= ttl_secs {
+            return Some("TTL expired");
+        }
+        match (self.code_hash, code_hash) {
+            (Some(cached), Some(current)) if cached != current => Some("code hash changed"),
+            _ => None,
+        }
+    }
+}
+
+/// Result of proxy detection, with the code it was detected against
+#[derive(Debug, Clone)]
+struct CachedProxy {
+    proxy: Option<ProxyInfo>,
+    detected_at: u64,
+    code_hash: B256,
+}
+
+/// ABI resolver that asks each configured provider in turn and caches remote results
+///
+/// Entries are keyed by chain ID and checksummed address, so a contract is cached once no
+/// matter which name its network is given under.
 #[derive(Debug)]
 pub struct AbiResolver {
-    client: Client,
     config: AbiSource,
-    memory_cache: HashMap<String, JsonAbi>,
+    providers: Vec<Box<dyn AbiProvider>>,
+    manual_abis: Arc<RwLock<HashMap<String, JsonAbi>>>,
+    memory_cache: HashMap<String, CachedAbi>,
+    proxy_cache: HashMap<String, CachedProxy>,
+    source_cache: HashMap<String, CachedLookup<SourceCode>>,
+    signatures: SignatureDb,
+    client: Client,
 }
 
 impl AbiResolver {
     pub fn new(config: AbiSource) -> Self {
+        let client = Client::new();
+        let manual_abis = Arc::new(RwLock::new(load_manual_abis(
+            &config.cache_dir.join("manual"),
+            &config.networks,
+        )));
+
+        let providers: Vec<Box<dyn AbiProvider>> = vec![
+            Box::new(ManualProvider::new(manual_abis.clone())),
+            Box::new(ArtifactsProvider::new(
+                config.artifact_dirs.clone(),
+                config.deployments.clone(),
+            )),
+            Box::new(EtherscanProvider::new(
+                client.clone(),
+                config.etherscan_api_key.clone(),
+            )),
+            Box::new(SourcifyProvider::new(
+                client.clone(),
+                config.sourcify_url.clone(),
+            )),
+            Box::new(BlockscoutProvider::new(client.clone())),
+        ];
+
+        let signatures = SignatureDb::load(&config.signature_files);
+
         Self {
-            client: Client::new(),
             config,
+            providers,
+            manual_abis,
             memory_cache: HashMap::new(),
+            proxy_cache: HashMap::new(),
+            source_cache: HashMap::new(),
+            signatures,
+            client,
         }
     }
 
-    /// Get ABI for a contract, trying cache first, then Etherscan
+    /// Add a provider, replacing any existing provider with the same name
+    #[allow(dead_code)]
+    pub fn register_provider(&mut self, provider: Box<dyn AbiProvider>) {
+        self.providers.retain(|p| p.name() != provider.name());
+        self.providers.push(provider);
+    }
+
+    /// Get ABI for a contract
+    #[allow(dead_code)]
     pub async fn get_abi(&mut self, address: &str, network: Option<&str>) -> Result<JsonAbi> {
+        Ok(self.resolve_abi(address, network, None).await?.abi)
+    }
+
+    /// Get ABI for a contract along with the provider that supplied it
+    ///
+    /// Providers are tried in the network's resolution order. The cache is consulted just
+    /// before the first remote provider, so local sources always reflect the latest files.
+    /// Cached entries are dropped once their TTL expires, or when `code_hash` (the hash of the
+    /// contract's current runtime code) differs from the one they were fetched against.
+    pub async fn resolve_abi(
+        &mut self,
+        address: &str,
+        network: Option<&str>,
+        code_hash: Option<B256>,
+    ) -> Result<ResolvedAbi> {
         let address = address.to_lowercase();
-        let cache_key = format!("{}_{}", network.unwrap_or("mainnet"), address);
+        let network = network.unwrap_or(&self.config.default_network).to_string();
+        let cache_key = self.cache_key(&address, Some(&network));
+        let lookup = AbiLookup {
+            address: &address,
+            key: &cache_key,
+            network: &network,
+            network_config: self.config.networks.get(&network),
+        };
 
-        // Check memory cache first
-        if let Some(abi) = self.memory_cache.get(&cache_key) {
-            debug!("ABI cache hit for {}", address);
-            return Ok(abi.clone());
-        }
+        let mut cache_checked = false;
+        let mut errors = Vec::new();
 
-        // Check disk cache
-        if let Ok(abi) = self.load_cached_abi(&cache_key).await {
-            debug!("ABI disk cache hit for {}", address);
-            self.memory_cache.insert(cache_key.clone(), abi.clone());
-            return Ok(abi);
-        }
+        for name in self.resolution_order(&network) {
+            let Some(provider) = self.providers.iter().find(|p| p.name() == name) else {
+                errors.push(format!("{}: unknown or unconfigured ABI provider", name));
+                continue;
+            };
+
+            if provider.is_remote() && !cache_checked {
+                cache_checked = true;
+
+                if let Some(cached) = self.cached_abi(&cache_key).await {
+                    match cached.invalidation_reason(self.config.cache_ttl_secs, code_hash) {
+                        None => {
+                            debug!("ABI cache hit for {}", address);
+                            self.memory_cache.insert(cache_key.clone(), cached.clone());
+                            return Ok(cached.resolved);
+                        }
+                        Some(reason) => {
+                            info!("Dropping cached ABI for {}: {}", address, reason);
+                            self.memory_cache.remove(&cache_key);
+                            self.remove_cached_abi(&cache_key).await;
+                        }
+                    }
+                }
+            }
 
-        // Fetch from Etherscan
-        info!("Fetching ABI from Etherscan for {}", address);
-        let abi = self.fetch_from_etherscan(&address, network).await?;
+            debug!("Looking up ABI for {} via {}", address, name);
+            match provider.fetch_abi(&lookup).await {
+                Ok(resolved) => {
+                    info!("Resolved ABI for {} from {}", address, resolved.provider);
 
-        // Cache the result
-        if let Err(e) = self.cache_abi(&cache_key, &abi).await {
-            warn!("Failed to cache ABI for {}: {}", address, e);
+                    if provider.is_remote() {
+                        // Cache the result
+                        let cached = CachedAbi {
+                            resolved: resolved.clone(),
+                            fetched_at: unix_now(),
+                            code_hash,
+                        };
+                        if let Err(e) = self.cache_abi(&cache_key, &cached).await {
+                            warn!("Failed to cache ABI for {}: {}", address, e);
+                        }
+                        self.memory_cache.insert(cache_key, cached);
+                    }
+                    return Ok(resolved);
+                }
+                Err(e) => errors.push(format!("{}: {}", name, e)),
+            }
         }
 
-        self.memory_cache.insert(cache_key, abi.clone());
-        Ok(abi)
+        Err(anyhow!(
+            "No ABI provider could supply the ABI ({})",
+            errors.join("; ")
+        ))
     }
 
-    /// Fetch ABI from Etherscan API
-    async fn fetch_from_etherscan(&self, address: &str, network: Option<&str>) -> Result<JsonAbi> {
-        let base_url = match network.unwrap_or("mainnet") {
-            "mainnet" | "ethereum" => "https://api.etherscan.io",
-            "sepolia" => "https://api-sepolia.etherscan.io",
-            "goerli" => "https://api-goerli.etherscan.io",
-            "polygon" => "https://api.polygonscan.com",
-            "arbitrum" => "https://api.arbiscan.io",
-            "optimism" => "https://api-optimistic.etherscan.io",
-            other => return Err(anyhow!("Unsupported network for Etherscan: {}", other)),
-        };
+    /// Proxy detected earlier for a contract, if its code hasn't changed since and the TTL
+    /// hasn't expired; the inner `None` records that the contract is not a proxy. Only results
+    /// that are fixed by the code are ever cached, see [`Self::cache_proxy`].
+    pub fn cached_proxy(
+        &self,
+        address: &str,
+        network: Option<&str>,
+        code_hash: B256,
+    ) -> Option<Option<ProxyInfo>> {
+        let cached = self.proxy_cache.get(&self.cache_key(address, network))?;
+        let ttl_secs = self.config.cache_ttl_secs;
+        if cached.code_hash != code_hash
+            || (ttl_secs > 0 && unix_now().saturating_sub(cached.detected_at) >= ttl_secs)
+        {
+            return None;
+        }
+        Some(cached.proxy.clone())
+    }
 
-        let mut url = format!(
-            "{}/api?module=contract&action=getabi&address={}&format=json",
-            base_url, address
+    /// Remember the result of proxy detection against the code with `code_hash`
+    ///
+    /// Upgradeable proxies are not cached: an upgrade only writes their implementation, beacon
+    /// or admin slot, which leaves the code hash unchanged, so they are detected again on every
+    /// resolve.
+    pub fn cache_proxy(
+        &mut self,
+        address: &str,
+        network: Option<&str>,
+        code_hash: B256,
+        proxy: Option<ProxyInfo>,
+    ) {
+        if proxy.as_ref().is_some_and(ProxyInfo::is_upgradeable) {
+            return;
+        }
+        let cache_key = self.cache_key(address, network);
+        self.proxy_cache.insert(
+            cache_key,
+            CachedProxy {
+                proxy,
+                detected_at: unix_now(),
+                code_hash,
+            },
         );
+    }
+
+    /// Provider names to try for a network
+    fn resolution_order(&self, network: &str) -> Vec<String> {
+        self.config
+            .networks
+            .get(network)
+            .and_then(|config| config.abi_providers.clone())
+            .unwrap_or_else(|| self.config.resolution_order.clone())
+    }
 
-        // Add API key if available
-        if let Some(api_key) = &self.config.etherscan_api_key {
-            url.push_str(&format!("&apikey={}", api_key));
+    /// Best-effort ABI from the function selectors in runtime bytecode, matched against the
+    /// signature database; `None` when no selector is known
+    pub fn infer_abi(&self, code: &[u8]) -> Option<ResolvedAbi> {
+        let (abi, _) = self.signatures.infer_abi(code);
+        if abi.functions.is_empty() {
+            return None;
         }
+        Some(ResolvedAbi {
+            abi,
+            provider: "inferred".to_string(),
+            metadata: None,
+            storage_layout: None,
+        })
+    }
 
-        let response: Value = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| anyhow!("Failed to fetch from Etherscan: {}", e))?
-            .json()
-            .await
-            .map_err(|e| anyhow!("Failed to parse Etherscan response: {}", e))?;
+    /// Selectors in runtime bytecode that match no known signature
+    pub fn unknown_selectors(&self, code: &[u8]) -> Vec<String> {
+        let (_, unknown) = self.signatures.infer_abi(code);
+        unknown
+            .iter()
+            .map(|selector| selector.to_string())
+            .collect()
+    }
 
-        // Check if the response is successful
-        if response["status"] != "1" {
-            let message = response["message"].as_str().unwrap_or("Unknown error");
-            return Err(anyhow!("Etherscan API error: {}", message));
+    /// Explorer API of a configured network
+    pub fn explorer(&self, network: Option<&str>) -> Result<ExplorerApi> {
+        let network = network.unwrap_or(&self.config.default_network);
+        let network_config = self
+            .config
+            .networks
+            .get(network)
+            .ok_or_else(|| anyhow!("Network '{}' is not configured", network))?;
+        Ok(ExplorerApi::for_network(
+            self.client.clone(),
+            network_config,
+            self.config.etherscan_api_key.as_deref(),
+        ))
+    }
+
+    /// Verified source code of a contract, from the network's explorer or else Sourcify
+    ///
+    /// Contracts without verified source are remembered too, so they aren't looked up again
+    /// on every call.
+    pub async fn source_code(
+        &mut self,
+        address: &str,
+        network: Option<&str>,
+    ) -> Result<SourceCode> {
+        let cache_key = self.cache_key(address, network);
+        if let Some(cached) = self
+            .source_cache
+            .get(&cache_key)
+            .and_then(CachedLookup::get)
+        {
+            return cached;
         }
 
-        // Parse the ABI
-        let abi_str = response["result"]
-            .as_str()
-            .ok_or_else(|| anyhow!("No ABI found in response"))?;
+        let source = self.fetch_source_code(address, network).await;
+        self.source_cache
+            .insert(cache_key, CachedLookup::new(&source));
+        source
+    }
 
-        if abi_str == "Contract source code not verified" {
-            return Err(anyhow!("Contract source code is not verified on Etherscan"));
+    async fn fetch_source_code(&self, address: &str, network: Option<&str>) -> Result<SourceCode> {
+        let explorer = self.explorer(network)?;
+        match explorer.source_code(&address.to_lowercase()).await {
+            Ok(source) => Ok(source),
+            Err(explorer_error) => {
+                let checksummed = Address::from_str(address)
+                    .map_err(|e| anyhow!("Invalid address '{}': {}", address, e))?
+                    .to_checksum(None);
+                source::fetch_sourcify_source(
+                    &self.client,
+                    &self.config.sourcify_url,
+                    explorer.chain_id(),
+                    &checksummed,
+                )
+                .await
+                .map_err(|e| anyhow!("{}: {}; sourcify: {}", explorer.name(), explorer_error, e))
+            }
         }
+    }
 
-        let abi: JsonAbi = serde_json::from_str(abi_str)
-            .map_err(|e| anyhow!("Failed to parse ABI JSON: {}", e))?;
-
-        Ok(abi)
+    /// Cached entry from memory, or failing that from disk
+    async fn cached_abi(&self, cache_key: &str) -> Option<CachedAbi> {
+        if let Some(cached) = self.memory_cache.get(cache_key) {
+            return Some(cached.clone());
+        }
+        self.load_cached_abi(cache_key).await.ok()
     }
 
     /// Load ABI from disk cache
-    async fn load_cached_abi(&self, cache_key: &str) -> Result<JsonAbi> {
+    async fn load_cached_abi(&self, cache_key: &str) -> Result<CachedAbi> {
         let cache_path = self.config.cache_dir.join(format!("{}.json", cache_key));
 
         if !cache_path.exists() {
@@ -141,14 +406,21 @@ impl AbiResolver {
             .await
             .map_err(|e| anyhow!("Failed to read cache file: {}", e))?;
 
-        let abi: JsonAbi = serde_json::from_str(&content)
-            .map_err(|e| anyhow!("Failed to parse cached ABI: {}", e))?;
+        serde_json::from_str(&content).map_err(|e| anyhow!("Failed to parse cached ABI: {}", e))
+    }
 
-        Ok(abi)
+    /// Remove a cache entry from disk
+    async fn remove_cached_abi(&self, cache_key: &str) {
+        let cache_path = self.config.cache_dir.join(format!("{}.json", cache_key));
+        if cache_path.exists() {
+            if let Err(e) = fs::remove_file(&cache_path).await {
+                warn!("Failed to remove cache file {:?}: {}", cache_path, e);
+            }
+        }
     }
 
     /// Save ABI to disk cache
-    async fn cache_abi(&self, cache_key: &str, abi: &JsonAbi) -> Result<()> {
+    async fn cache_abi(&self, cache_key: &str, cached: &CachedAbi) -> Result<()> {
         // Create cache directory if it doesn't exist
         if !self.config.cache_dir.exists() {
             fs::create_dir_all(&self.config.cache_dir)
@@ -157,7 +429,7 @@ impl AbiResolver {
         }
 
         let cache_path = self.config.cache_dir.join(format!("{}.json", cache_key));
-        let content = serde_json::to_string_pretty(abi)
+        let content = serde_json::to_string_pretty(cached)
             .map_err(|e| anyhow!("Failed to serialize ABI: {}", e))?;
 
         fs::write(&cache_path, content)
@@ -168,29 +440,57 @@ impl AbiResolver {
         Ok(())
     }
 
-    /// Add ABI manually (for unverified contracts)
-    #[allow(dead_code)]
-    pub fn add_manual_abi(&mut self, address: &str, network: Option<&str>, abi: JsonAbi) {
-        let cache_key = format!(
-            "{}_{}",
-            network.unwrap_or("mainnet"),
-            address.to_lowercase()
-        );
-        self.memory_cache.insert(cache_key, abi);
+    /// Add ABI manually (for unverified contracts), persisting it so it survives a restart
+    pub async fn add_manual_abi(
+        &mut self,
+        address: &str,
+        network: Option<&str>,
+        abi: JsonAbi,
+    ) -> Result<StoredAbi> {
+        let cache_key = self.cache_key(address, network);
+
+        let manual_dir = self.manual_dir();
+        fs::create_dir_all(&manual_dir)
+            .await
+            .map_err(|e| anyhow!("Failed to create manual ABI directory: {}", e))?;
+        let content = serde_json::to_string_pretty(&abi)
+            .map_err(|e| anyhow!("Failed to serialize ABI: {}", e))?;
+        fs::write(manual_dir.join(format!("{}.json", cache_key)), content)
+            .await
+            .map_err(|e| anyhow!("Failed to write manual ABI: {}", e))?;
+
+        let resolved = ResolvedAbi {
+            abi,
+            provider: "manual".to_string(),
+            metadata: None,
+            storage_layout: None,
+        };
+        let stored = self
+            .stored_entry(&cache_key, &resolved, None, None)
+            .ok_or_else(|| anyhow!("Invalid ABI cache key '{}'", cache_key))?;
+
+        // A cached ABI from a remote provider would otherwise shadow the manual one
+        self.memory_cache.remove(&cache_key);
+        self.manual_abis
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(cache_key, resolved.abi);
         info!("Added manual ABI for {}", address);
+        Ok(stored)
     }
 
     /// Check if we have an ABI for a contract (without fetching)
-    #[allow(dead_code)]
     pub async fn has_abi(&self, address: &str, network: Option<&str>) -> bool {
-        let cache_key = format!(
-            "{}_{}",
-            network.unwrap_or("mainnet"),
-            address.to_lowercase()
-        );
+        let cache_key = self.cache_key(address, network);
 
-        // Check memory cache
-        if self.memory_cache.contains_key(&cache_key) {
+        // Check manual registrations and memory cache
+        if self
+            .manual_abis
+            .read()
+            .map(|abis| abis.contains_key(&cache_key))
+            .unwrap_or(false)
+            || self.memory_cache.contains_key(&cache_key)
+        {
             return true;
         }
 
@@ -199,15 +499,197 @@ impl AbiResolver {
         cache_path.exists()
     }
 
-    /// Clear all cached ABIs
-    #[allow(dead_code)]
-    pub async fn clear_cache(&mut self) -> Result<()> {
+    /// The stored ABI for a contract, manual registrations first, without fetching
+    pub async fn stored_abi(&self, address: &str, network: Option<&str>) -> Option<ResolvedAbi> {
+        let cache_key = self.cache_key(address, network);
+
+        if let Some(abi) = self.manual_abis.read().ok()?.get(&cache_key) {
+            return Some(ResolvedAbi {
+                abi: abi.clone(),
+                provider: "manual".to_string(),
+                metadata: None,
+                storage_layout: None,
+            });
+        }
+        self.cached_abi(&cache_key)
+            .await
+            .map(|cached| cached.resolved)
+    }
+
+    /// Every stored ABI: manual registrations and cached fetches
+    pub async fn list_abis(&self) -> Vec<StoredAbi> {
+        let mut stored: HashMap<String, StoredAbi> = HashMap::new();
+
+        // Disk cache entries cover everything fetched, including by earlier runs
+        if let Ok(mut entries) = fs::read_dir(&self.config.cache_dir).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let path = entry.path();
+                let Some(cache_key) = path
+                    .file_name()
+                    .and_then(|f| f.to_str())
+                    .and_then(|f| f.strip_suffix(".json"))
+                else {
+                    continue;
+                };
+                if let Ok(cached) = self.load_cached_abi(cache_key).await {
+                    if let Some(entry) = self.cached_entry(cache_key, &cached) {
+                        stored.insert(cache_key.to_string(), entry);
+                    }
+                }
+            }
+        }
+
+        for (cache_key, cached) in &self.memory_cache {
+            if let Some(entry) = self.cached_entry(cache_key, cached) {
+                stored.insert(cache_key.clone(), entry);
+            }
+        }
+
+        if let Ok(manual_abis) = self.manual_abis.read() {
+            for (cache_key, abi) in manual_abis.iter() {
+                let resolved = ResolvedAbi {
+                    abi: abi.clone(),
+                    provider: "manual".to_string(),
+                    metadata: None,
+                    storage_layout: None,
+                };
+                if let Some(entry) = self.stored_entry(cache_key, &resolved, None, None) {
+                    stored.insert(cache_key.clone(), entry);
+                }
+            }
+        }
+
+        let mut stored: Vec<StoredAbi> = stored.into_values().collect();
+        stored.sort_by(|a, b| (&a.network, &a.address).cmp(&(&b.network, &b.address)));
+        stored
+    }
+
+    /// Remove a contract's manual registration and cached ABI
+    pub async fn remove_abi(&mut self, address: &str, network: Option<&str>) -> Result<()> {
+        let cache_key = self.cache_key(address, network);
+
+        self.memory_cache.remove(&cache_key);
+        self.proxy_cache.remove(&cache_key);
+        self.manual_abis
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&cache_key);
+
+        for path in [
+            self.manual_dir().join(format!("{}.json", cache_key)),
+            self.config.cache_dir.join(format!("{}.json", cache_key)),
+        ] {
+            if path.exists() {
+                fs::remove_file(&path)
+                    .await
+                    .map_err(|e| anyhow!("Failed to remove {:?}: {}", path, e))?;
+            }
+        }
+
+        info!("Removed ABI for {}", address);
+        Ok(())
+    }
+
+    /// Cache key for a contract: `<chain id>_<checksummed address>`, falling back to the
+    /// network name for networks without a configured chain ID
+    pub fn cache_key(&self, address: &str, network: Option<&str>) -> String {
+        let network = network.unwrap_or(&self.config.default_network);
+        let chain = self
+            .config
+            .networks
+            .get(network)
+            .map(|config| config.chain_id.to_string())
+            .unwrap_or_else(|| network.to_string());
+        let address = Address::from_str(address)
+            .map(|address| address.to_checksum(None))
+            .unwrap_or_else(|_| address.to_lowercase());
+        format!("{}_{}", chain, address)
+    }
+
+    /// Configured network for a chain ID, preferring the default network
+    fn network_for_chain(&self, chain_id: u64) -> Option<String> {
+        let default = &self.config.default_network;
+        if self.config.networks.get(default).map(|c| c.chain_id) == Some(chain_id) {
+            return Some(default.clone());
+        }
+        self.config
+            .networks
+            .iter()
+            .filter(|(_, config)| config.chain_id == chain_id)
+            .map(|(name, _)| name.clone())
+            .min()
+    }
+
+    fn cached_entry(&self, cache_key: &str, cached: &CachedAbi) -> Option<StoredAbi> {
+        self.stored_entry(
+            cache_key,
+            &cached.resolved,
+            Some(cached.fetched_at),
+            cached.code_hash,
+        )
+    }
+
+    fn stored_entry(
+        &self,
+        cache_key: &str,
+        resolved: &ResolvedAbi,
+        fetched_at: Option<u64>,
+        code_hash: Option<B256>,
+    ) -> Option<StoredAbi> {
+        let (chain, address) = cache_key.rsplit_once('_')?;
+        let chain_id = chain.parse::<u64>().ok();
+        let network = chain_id
+            .and_then(|id| self.network_for_chain(id))
+            .unwrap_or_else(|| chain.to_string());
+        Some(StoredAbi {
+            address: address.to_string(),
+            network,
+            chain_id,
+            provider: resolved.provider.clone(),
+            functions: resolved.abi.functions().count(),
+            events: resolved.abi.events().count(),
+            errors: resolved.abi.errors().count(),
+            fetched_at,
+            code_hash,
+        })
+    }
+
+    /// Directory holding manual registrations, inside the cache directory
+    fn manual_dir(&self) -> PathBuf {
+        self.config.cache_dir.join("manual")
+    }
+
+    /// Clear all cached ABIs, and manual registrations too if asked
+    pub async fn clear_cache(&mut self, include_manual: bool) -> Result<()> {
         self.memory_cache.clear();
+        self.proxy_cache.clear();
+        self.source_cache.clear();
+        for provider in &self.providers {
+            provider.clear_cache().await;
+        }
 
-        if self.config.cache_dir.exists() {
-            fs::remove_dir_all(&self.config.cache_dir)
-                .await
-                .map_err(|e| anyhow!("Failed to clear cache directory: {}", e))?;
+        if let Ok(mut entries) = fs::read_dir(&self.config.cache_dir).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == "json") {
+                    fs::remove_file(&path)
+                        .await
+                        .map_err(|e| anyhow!("Failed to clear cache file {:?}: {}", path, e))?;
+                }
+            }
+        }
+
+        if include_manual {
+            self.manual_abis
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .clear();
+            let manual_dir = self.manual_dir();
+            if manual_dir.exists() {
+                fs::remove_dir_all(&manual_dir)
+                    .await
+                    .map_err(|e| anyhow!("Failed to clear manual ABIs: {}", e))?;
+            }
         }
 
         info!("Cleared ABI cache");
@@ -215,6 +697,180 @@ impl AbiResolver {
     }
 }
 
+/// Summary of a stored ABI, for listing
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredAbi {
+    pub address: String,
+    pub network: String,
+    pub chain_id: Option<u64>,
+    pub provider: String,
+    pub functions: usize,
+    pub events: usize,
+    pub errors: usize,
+    /// Unix timestamp of the fetch, for cached entries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetched_at: Option<u64>,
+    /// Code hash the cached entry was fetched against
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_hash: Option<B256>,
+}
+
+/// Parse an ABI given as JSON (an ABI array, an artifact with an `abi` field, or either as a
+/// string) or as human-readable signatures (an array of strings, or one per line)
+pub fn parse_abi(value: &Value) -> Result<JsonAbi> {
+    match value {
+        Value::String(text) => match serde_json::from_str::<Value>(text) {
+            Ok(json) if json.is_array() || json.is_object() => parse_abi(&json),
+            _ => parse_human_readable(text.lines()),
+        },
+        Value::Array(items) if items.iter().all(Value::is_string) && !items.is_empty() => {
+            parse_human_readable(items.iter().filter_map(Value::as_str))
+        }
+        Value::Object(artifact) if artifact.contains_key("abi") => parse_abi(&artifact["abi"]),
+        _ => serde_json::from_value(value.clone()).map_err(|e| anyhow!("Invalid JSON ABI: {}", e)),
+    }
+}
+
+fn parse_human_readable<'a>(signatures: impl Iterator<Item = &'a str>) -> Result<JsonAbi> {
+    let signatures: Vec<&str> = signatures
+        .map(|s| s.trim().trim_end_matches(';').trim())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if signatures.is_empty() {
+        return Err(anyhow!("ABI has no entries"));
+    }
+
+    let mut abi = JsonAbi::new();
+    for signature in signatures {
+        let item = AbiItem::parse(signature)
+            .map_err(|e| anyhow!("Invalid signature '{}': {}", signature, e))?;
+        match item {
+            AbiItem::Function(function) => abi
+                .functions
+                .entry(function.name.clone())
+                .or_default()
+                .push(function.into_owned()),
+            AbiItem::Event(event) => abi
+                .events
+                .entry(event.name.clone())
+                .or_default()
+                .push(event.into_owned()),
+            AbiItem::Error(error) => abi
+                .errors
+                .entry(error.name.clone())
+                .or_default()
+                .push(error.into_owned()),
+            AbiItem::Constructor(constructor) => abi.constructor = Some(constructor.into_owned()),
+            AbiItem::Fallback(fallback) => abi.fallback = Some(fallback.into_owned()),
+            AbiItem::Receive(receive) => abi.receive = Some(receive.into_owned()),
+        }
+    }
+    Ok(abi)
+}
+
+/// Render an ABI as human-readable signatures
+pub fn to_human_readable(abi: &JsonAbi) -> Vec<String> {
+    let mut signatures = Vec::new();
+    if let Some(constructor) = &abi.constructor {
+        let inputs: Vec<String> = constructor
+            .inputs
+            .iter()
+            .map(|p| {
+                format!("{} {}", p.selector_type(), p.name)
+                    .trim_end()
+                    .to_string()
+            })
+            .collect();
+        signatures.push(format!("constructor({})", inputs.join(", ")));
+    }
+    signatures.extend(abi.functions().map(|f| f.full_signature()));
+    signatures.extend(abi.events().map(|e| e.full_signature()));
+    signatures.extend(abi.errors().map(|e| format!("error {}", e.signature())));
+    if abi.fallback.is_some() {
+        signatures.push("fallback()".to_string());
+    }
+    if abi.receive.is_some() {
+        signatures.push("receive() external payable".to_string());
+    }
+    signatures
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Manual registrations persisted by earlier runs
+///
+/// Registrations saved under the older `<network>_<lowercase address>` names are renamed to
+/// the current chain ID keys, so they keep matching.
+fn load_manual_abis(
+    manual_dir: &Path,
+    networks: &HashMap<String, NetworkConfig>,
+) -> HashMap<String, JsonAbi> {
+    let mut abis = HashMap::new();
+    let Ok(entries) = std::fs::read_dir(manual_dir) else {
+        return abis;
+    };
+
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let Some(file_key) = path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .and_then(|f| f.strip_suffix(".json"))
+        else {
+            continue;
+        };
+        let abi = match std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+        {
+            Ok(abi) => abi,
+            Err(e) => {
+                warn!("Skipping manual ABI {:?}: {}", path, e);
+                continue;
+            }
+        };
+
+        let cache_key = match legacy_manual_key(file_key, networks) {
+            Some(cache_key) => {
+                let migrated = manual_dir.join(format!("{}.json", cache_key));
+                if migrated.exists() {
+                    warn!(
+                        "Ignoring manual ABI {:?}: {:?} registers the same contract",
+                        path, migrated
+                    );
+                    continue;
+                }
+                match std::fs::rename(&path, &migrated) {
+                    Ok(()) => info!("Migrated manual ABI {:?} to {:?}", path, migrated),
+                    Err(e) => warn!("Failed to migrate manual ABI {:?}: {}", path, e),
+                }
+                cache_key
+            }
+            None => file_key.to_string(),
+        };
+        abis.insert(cache_key, abi);
+    }
+    abis
+}
+
+/// Current cache key of a manual registration saved as `<network>_<lowercase address>`,
+/// when the network is configured with a chain ID
+fn legacy_manual_key(file_key: &str, networks: &HashMap<String, NetworkConfig>) -> Option<String> {
+    let (network, address) = file_key.rsplit_once('_')?;
+    if network.parse::<u64>().is_ok() {
+        return None;
+    }
+    let chain_id = networks.get(network)?.chain_id;
+    let address = Address::from_str(address)
+        .map(|address| address.to_checksum(None))
+        .unwrap_or_else(|_| address.to_lowercase());
+    Some(format!("{}_{}", chain_id, address))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -226,6 +882,7 @@ mod tests {
         let config = AbiSource {
             etherscan_api_key: None,
             cache_dir: temp_dir.path().to_path_buf(),
+            ..AbiSource::default()
         };
 
         let resolver = AbiResolver::new(config);
@@ -238,12 +895,16 @@ mod tests {
         let config = AbiSource {
             etherscan_api_key: None,
             cache_dir: temp_dir.path().to_path_buf(),
+            ..AbiSource::default()
         };
 
         let mut resolver = AbiResolver::new(config);
         let test_abi: JsonAbi = serde_json::from_str("[]").unwrap();
 
-        resolver.add_manual_abi("0x123", Some("mainnet"), test_abi.clone());
+        resolver
+            .add_manual_abi("0x123", Some("mainnet"), test_abi.clone())
+            .await
+            .unwrap();
 
         assert!(resolver.has_abi("0x123", Some("mainnet")).await);
         let retrieved_abi = resolver.get_abi("0x123", Some("mainnet")).await.unwrap();
@@ -252,4 +913,344 @@ mod tests {
             test_abi.functions().count()
         );
     }
+
+    #[derive(Debug)]
+    struct FixtureProvider {
+        name: &'static str,
+        abi: Option<&'static str>,
+    }
+
+    #[async_trait::async_trait]
+    impl AbiProvider for FixtureProvider {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn is_remote(&self) -> bool {
+            false
+        }
+
+        async fn fetch_abi(&self, _lookup: &AbiLookup<'_>) -> Result<ResolvedAbi> {
+            let abi = self.abi.ok_or_else(|| anyhow!("not found"))?;
+            Ok(ResolvedAbi {
+                abi: serde_json::from_str(abi).unwrap(),
+                provider: self.name.to_string(),
+                metadata: None,
+                storage_layout: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolution_order() {
+        let temp_dir = tempdir().unwrap();
+        let mut config = AbiSource {
+            etherscan_api_key: None,
+            cache_dir: temp_dir.path().to_path_buf(),
+            resolution_order: vec!["empty".to_string(), "fixture".to_string()],
+            ..AbiSource::default()
+        };
+        config.networks.get_mut("polygon").unwrap().abi_providers = Some(vec!["empty".to_string()]);
+
+        let mut resolver = AbiResolver::new(config);
+        resolver.register_provider(Box::new(FixtureProvider {
+            name: "empty",
+            abi: None,
+        }));
+        resolver.register_provider(Box::new(FixtureProvider {
+            name: "fixture",
+            abi: Some("[]"),
+        }));
+
+        let resolved = resolver.resolve_abi("0x123", None, None).await.unwrap();
+        assert_eq!(resolved.provider, "fixture");
+
+        // The per-network order overrides the default one, and every failure is reported
+        let error = resolver
+            .resolve_abi("0x123", Some("polygon"), None)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("empty: not found"));
+        assert!(!error.contains("fixture"));
+    }
+
+    #[derive(Debug, Default)]
+    struct CountingProvider {
+        calls: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl AbiProvider for CountingProvider {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        async fn fetch_abi(&self, _lookup: &AbiLookup<'_>) -> Result<ResolvedAbi> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(ResolvedAbi {
+                abi: JsonAbi::new(),
+                provider: self.name().to_string(),
+                metadata: None,
+                storage_layout: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_invalidation() {
+        let temp_dir = tempdir().unwrap();
+        let config = AbiSource {
+            etherscan_api_key: None,
+            cache_dir: temp_dir.path().to_path_buf(),
+            resolution_order: vec!["counting".to_string()],
+            ..AbiSource::default()
+        };
+        let address = "0x742d35cc6435c9c1c72c5e7b18bab7e1db7a5d6e";
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let fetches = || calls.load(std::sync::atomic::Ordering::SeqCst);
+
+        let mut resolver = AbiResolver::new(config.clone());
+        resolver.register_provider(Box::new(CountingProvider {
+            calls: calls.clone(),
+        }));
+
+        let (old_code, new_code) = (B256::repeat_byte(1), B256::repeat_byte(2));
+        resolver
+            .resolve_abi(address, Some("ethereum"), Some(old_code))
+            .await
+            .unwrap();
+        assert_eq!(fetches(), 1);
+
+        // Same code, or no code hash to compare against, is a hit; the key is by chain ID
+        resolver
+            .resolve_abi(address, Some("ethereum"), Some(old_code))
+            .await
+            .unwrap();
+        resolver.resolve_abi(address, None, None).await.unwrap();
+        assert_eq!(fetches(), 1);
+        let checksummed = Address::from_str(address).unwrap().to_checksum(None);
+        assert!(temp_dir
+            .path()
+            .join(format!("1_{}.json", checksummed))
+            .exists());
+
+        // New code at the address invalidates the entry
+        resolver
+            .resolve_abi(address, None, Some(new_code))
+            .await
+            .unwrap();
+        assert_eq!(fetches(), 2);
+        let listed = resolver.list_abis().await;
+        assert_eq!(listed[0].code_hash, Some(new_code));
+
+        // So does an expired TTL, including for entries read back from disk
+        let mut resolver = AbiResolver::new(AbiSource {
+            cache_ttl_secs: 60,
+            ..config
+        });
+        resolver.register_provider(Box::new(CountingProvider {
+            calls: calls.clone(),
+        }));
+        let key = resolver.cache_key(address, None);
+        let mut cached = resolver.load_cached_abi(&key).await.unwrap();
+        cached.fetched_at -= 120;
+        resolver.cache_abi(&key, &cached).await.unwrap();
+        resolver
+            .resolve_abi(address, None, Some(new_code))
+            .await
+            .unwrap();
+        assert_eq!(fetches(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_cache() {
+        use crate::ethereum::proxy::ProxyType;
+
+        let temp_dir = tempdir().unwrap();
+        let mut resolver = AbiResolver::new(AbiSource {
+            cache_dir: temp_dir.path().to_path_buf(),
+            ..AbiSource::default()
+        });
+        let (proxy_address, plain_address) = (
+            "0x742d35cc6435c9c1c72c5e7b18bab7e1db7a5d6e",
+            "0x0000000000000000000000000000000000000001",
+        );
+        let (old_code, new_code) = (B256::repeat_byte(1), B256::repeat_byte(2));
+        let proxy = ProxyInfo {
+            proxy_type: ProxyType::Eip1167,
+            implementation: Some("0x0000000000000000000000000000000000000002".to_string()),
+            admin: None,
+            beacon: None,
+            facets: Vec::new(),
+        };
+        let upgradeable = ProxyInfo {
+            proxy_type: ProxyType::Eip1967,
+            ..proxy.clone()
+        };
+
+        assert!(resolver
+            .cached_proxy(proxy_address, None, old_code)
+            .is_none());
+        resolver.cache_proxy(proxy_address, Some("ethereum"), old_code, Some(proxy));
+        resolver.cache_proxy(plain_address, None, old_code, None);
+
+        // Upgrades leave the code alone, so upgradeable proxies are never cached
+        let upgradeable_address = "0x0000000000000000000000000000000000000003";
+        resolver.cache_proxy(upgradeable_address, None, old_code, Some(upgradeable));
+        assert!(resolver
+            .cached_proxy(upgradeable_address, None, old_code)
+            .is_none());
+
+        // Keyed by chain ID, and a contract that isn't a proxy is cached too
+        let cached = resolver.cached_proxy(proxy_address, None, old_code);
+        assert_eq!(
+            cached.unwrap().unwrap().implementation.as_deref(),
+            Some("0x0000000000000000000000000000000000000002")
+        );
+        assert!(matches!(
+            resolver.cached_proxy(plain_address, None, old_code),
+            Some(None)
+        ));
+
+        // New code at the address means detecting again
+        assert!(resolver
+            .cached_proxy(proxy_address, None, new_code)
+            .is_none());
+
+        resolver.clear_cache(false).await.unwrap();
+        assert!(resolver
+            .cached_proxy(plain_address, None, old_code)
+            .is_none());
+    }
+
+    #[test]
+    fn test_parse_abi_formats() {
+        let signatures = serde_json::json!([
+            "function balanceOf(address owner) view returns (uint256)",
+            "event Transfer(address indexed from, address indexed to, uint256 value)",
+            "error Unauthorized(address caller)"
+        ]);
+        let abi = parse_abi(&signatures).unwrap();
+        assert_eq!(
+            (
+                abi.functions().count(),
+                abi.events().count(),
+                abi.errors().count()
+            ),
+            (1, 1, 1)
+        );
+
+        // One signature per line, and JSON in a string
+        let lines = Value::from(
+            "function totalSupply() view returns (uint256)\n\
+             function decimals() view returns (uint8);\n",
+        );
+        assert_eq!(parse_abi(&lines).unwrap().functions().count(), 2);
+
+        let json = serde_json::to_string(&abi).unwrap();
+        assert_eq!(parse_abi(&Value::from(json)).unwrap(), abi);
+        assert_eq!(
+            parse_abi(&serde_json::json!({ "abi": abi })).unwrap().len(),
+            3
+        );
+
+        assert!(parse_abi(&Value::from("function (")).is_err());
+        assert!(parse_abi(&serde_json::json!([])).unwrap().is_empty());
+
+        let human_readable = to_human_readable(&abi);
+        assert!(human_readable.contains(&"error Unauthorized(address)".to_string()));
+        let round_trip = parse_abi(&serde_json::json!(human_readable)).unwrap();
+        assert_eq!(round_trip.functions().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_manual_abis_migrate() {
+        let temp_dir = tempdir().unwrap();
+        let config = AbiSource {
+            etherscan_api_key: None,
+            cache_dir: temp_dir.path().to_path_buf(),
+            ..AbiSource::default()
+        };
+        let address = "0x742d35cc6435c9c1c72c5e7b18bab7e1db7a5d6e";
+        let abi = JsonAbi::parse(["function totalSupply() view returns (uint256)"]).unwrap();
+        let manual_dir = temp_dir.path().join("manual");
+        std::fs::create_dir_all(&manual_dir).unwrap();
+        std::fs::write(
+            manual_dir.join(format!("sepolia_{}.json", address)),
+            serde_json::to_string(&abi).unwrap(),
+        )
+        .unwrap();
+
+        let mut resolver = AbiResolver::new(config);
+        let resolved = resolver
+            .resolve_abi(address, Some("sepolia"), None)
+            .await
+            .unwrap();
+        assert_eq!(resolved.provider, "manual");
+
+        let checksummed = Address::from_str(address).unwrap().to_checksum(None);
+        assert!(manual_dir
+            .join(format!("11155111_{}.json", checksummed))
+            .exists());
+        assert!(!manual_dir
+            .join(format!("sepolia_{}.json", address))
+            .exists());
+    }
+
+    #[tokio::test]
+    async fn test_manual_abis_persist() {
+        let temp_dir = tempdir().unwrap();
+        let config = AbiSource {
+            etherscan_api_key: None,
+            cache_dir: temp_dir.path().to_path_buf(),
+            ..AbiSource::default()
+        };
+        let address = "0x742d35Cc6435C9c1c72c5E7b18BaB7e1DB7a5d6e";
+        let abi = JsonAbi::parse(["function totalSupply() view returns (uint256)"]).unwrap();
+
+        let mut resolver = AbiResolver::new(config.clone());
+        resolver
+            .add_manual_abi(address, Some("sepolia"), abi.clone())
+            .await
+            .unwrap();
+
+        // A new resolver, as after a restart, still has the registration
+        let mut resolver = AbiResolver::new(config.clone());
+        let resolved = resolver
+            .resolve_abi(address, Some("sepolia"), None)
+            .await
+            .unwrap();
+        assert_eq!(resolved.provider, "manual");
+        assert_eq!(resolved.abi, abi);
+
+        let listed = resolver.list_abis().await;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].network, "sepolia");
+        assert_eq!(listed[0].chain_id, Some(11155111));
+        assert_eq!(
+            listed[0].address,
+            Address::from_str(address).unwrap().to_checksum(None)
+        );
+
+        // Clearing the cache keeps manual registrations unless asked
+        resolver.clear_cache(false).await.unwrap();
+        assert!(resolver.has_abi(address, Some("sepolia")).await);
+        resolver.clear_cache(true).await.unwrap();
+        assert!(!resolver.has_abi(address, Some("sepolia")).await);
+        assert!(AbiResolver::new(config.clone())
+            .list_abis()
+            .await
+            .is_empty());
+
+        resolver
+            .add_manual_abi(address, Some("sepolia"), abi)
+            .await
+            .unwrap();
+        resolver.remove_abi(address, Some("sepolia")).await.unwrap();
+        assert!(AbiResolver::new(config)
+            .stored_abi(address, Some("sepolia"))
+            .await
+            .is_none());
+    }
 }
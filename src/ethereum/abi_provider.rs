@@ -0,0 +1,369 @@
+use crate::config::NetworkConfig;
+use crate::ethereum::explorer::ExplorerApi;
+use alloy::{json_abi::JsonAbi, primitives::Address};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+/// A contract lookup handed to each ABI provider
+#[derive(Debug, Clone)]
+pub struct AbiLookup<'a> {
+    /// Lowercase contract address
+    pub address: &'a str,
+    /// Cache key of the contract, `<chain id>_<checksummed address>`
+    pub key: &'a str,
+    pub network: &'a str,
+    /// Configuration of the network, when it is one of the configured networks
+    pub network_config: Option<&'a NetworkConfig>,
+}
+
+impl AbiLookup<'_> {
+    pub fn chain_id(&self) -> Result<u64> {
+        self.network_config
+            .map(|config| config.chain_id)
+            .ok_or_else(|| anyhow!("No chain ID configured for network '{}'", self.network))
+    }
+
+    pub fn checksum_address(&self) -> Result<String> {
+        let address = Address::from_str(self.address)
+            .map_err(|e| anyhow!("Invalid address '{}': {}", self.address, e))?;
+        Ok(address.to_checksum(None))
+    }
+}
+
+/// An ABI together with the provider that supplied it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedAbi {
+    pub abi: JsonAbi,
+    pub provider: String,
+    /// Solidity compiler metadata, when the provider has it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    /// Solidity `storageLayout` compiler output, when the provider has it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_layout: Option<Value>,
+}
+
+/// A source of contract ABIs
+#[async_trait]
+pub trait AbiProvider: Send + Sync + std::fmt::Debug {
+    /// Name used in the `resolution_order` config and reported with each resolved ABI
+    fn name(&self) -> &str;
+
+    /// Whether lookups go over the network, in which case results are cached
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    async fn fetch_abi(&self, lookup: &AbiLookup<'_>) -> Result<ResolvedAbi>;
+
+    /// Forget anything gathered by earlier lookups
+    async fn clear_cache(&self) {}
+}
+
+/// Etherscan, through its V2 multichain endpoint, or the network's own `explorer_api_url`
+#[derive(Debug)]
+pub struct EtherscanProvider {
+    client: Client,
+    api_key: Option<String>,
+}
+
+impl EtherscanProvider {
+    pub fn new(client: Client, api_key: Option<String>) -> Self {
+        Self { client, api_key }
+    }
+
+    /// Explorer API for the lookup's network, which must be a configured network
+    pub fn explorer(&self, lookup: &AbiLookup<'_>) -> Result<ExplorerApi> {
+        let network_config = lookup.network_config.ok_or_else(|| {
+            anyhow!(
+                "Network '{}' is not configured, so its chain ID for Etherscan is unknown",
+                lookup.network
+            )
+        })?;
+        Ok(ExplorerApi::for_network(
+            self.client.clone(),
+            network_config,
+            self.api_key.as_deref(),
+        ))
+    }
+}
+
+#[async_trait]
+impl AbiProvider for EtherscanProvider {
+    fn name(&self) -> &str {
+        "etherscan"
+    }
+
+    async fn fetch_abi(&self, lookup: &AbiLookup<'_>) -> Result<ResolvedAbi> {
+        let explorer = self.explorer(lookup)?;
+        let response = explorer
+            .get("contract", "getabi", &[("address", lookup.address)])
+            .await?;
+
+        Ok(ResolvedAbi {
+            abi: parse_explorer_abi(&response, explorer.name())?,
+            provider: self.name().to_string(),
+            metadata: None,
+            storage_layout: None,
+        })
+    }
+}
+
+/// Sourcify, looked up by chain ID; accepts full and partial matches
+#[derive(Debug)]
+pub struct SourcifyProvider {
+    client: Client,
+    base_url: String,
+}
+
+impl SourcifyProvider {
+    pub fn new(client: Client, base_url: String) -> Self {
+        Self { client, base_url }
+    }
+}
+
+#[async_trait]
+impl AbiProvider for SourcifyProvider {
+    fn name(&self) -> &str {
+        "sourcify"
+    }
+
+    async fn fetch_abi(&self, lookup: &AbiLookup<'_>) -> Result<ResolvedAbi> {
+        let chain_id = lookup.chain_id()?;
+        let url = format!(
+            "{}/v2/contract/{}/{}?fields=abi,metadata,storageLayout",
+            self.base_url.trim_end_matches('/'),
+            chain_id,
+            lookup.checksum_address()?
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to fetch from Sourcify: {}", e))?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(anyhow!(
+                "Contract is not verified on Sourcify for chain {} (404 not found)",
+                chain_id
+            ));
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("Sourcify API error: HTTP {}", response.status()));
+        }
+
+        let response: Value = response
+            .json()
+            .await
+            .map_err(|e| anyhow!("Failed to parse Sourcify response: {}", e))?;
+
+        let (abi, metadata, full_match) = parse_sourcify_contract(&response)?;
+        tracing::debug!(
+            "Sourcify {} match for {}",
+            if full_match { "full" } else { "partial" },
+            lookup.address
+        );
+
+        Ok(ResolvedAbi {
+            abi,
+            provider: self.name().to_string(),
+            metadata,
+            storage_layout: Some(response["storageLayout"].clone()).filter(|l| l.is_object()),
+        })
+    }
+}
+
+/// Blockscout instances, configured per network with `blockscout_url`
+#[derive(Debug)]
+pub struct BlockscoutProvider {
+    client: Client,
+}
+
+impl BlockscoutProvider {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl AbiProvider for BlockscoutProvider {
+    fn name(&self) -> &str {
+        "blockscout"
+    }
+
+    async fn fetch_abi(&self, lookup: &AbiLookup<'_>) -> Result<ResolvedAbi> {
+        let base_url = lookup
+            .network_config
+            .and_then(|config| config.blockscout_url.as_deref())
+            .ok_or_else(|| {
+                anyhow!(
+                    "No blockscout_url configured for network '{}'",
+                    lookup.network
+                )
+            })?;
+
+        // Blockscout serves an Etherscan-compatible API under /api
+        let url = format!(
+            "{}/api?module=contract&action=getabi&address={}",
+            base_url.trim_end_matches('/'),
+            lookup.address
+        );
+
+        let response: Value = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to fetch from Blockscout: {}", e))?
+            .json()
+            .await
+            .map_err(|e| anyhow!("Failed to parse Blockscout response: {}", e))?;
+
+        Ok(ResolvedAbi {
+            abi: parse_explorer_abi(&response, "Blockscout")?,
+            provider: self.name().to_string(),
+            metadata: None,
+            storage_layout: None,
+        })
+    }
+}
+
+/// ABIs registered by hand, keyed like the cache by `<chain id>_<checksummed address>`
+#[derive(Debug, Default)]
+pub struct ManualProvider {
+    abis: Arc<RwLock<HashMap<String, JsonAbi>>>,
+}
+
+impl ManualProvider {
+    pub fn new(abis: Arc<RwLock<HashMap<String, JsonAbi>>>) -> Self {
+        Self { abis }
+    }
+}
+
+#[async_trait]
+impl AbiProvider for ManualProvider {
+    fn name(&self) -> &str {
+        "manual"
+    }
+
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    async fn fetch_abi(&self, lookup: &AbiLookup<'_>) -> Result<ResolvedAbi> {
+        let abis = self
+            .abis
+            .read()
+            .map_err(|_| anyhow!("Manual ABI registry is poisoned"))?;
+
+        abis.get(lookup.key)
+            .map(|abi| ResolvedAbi {
+                abi: abi.clone(),
+                provider: self.name().to_string(),
+                metadata: None,
+                storage_layout: None,
+            })
+            .ok_or_else(|| anyhow!("No manual ABI registered"))
+    }
+}
+
+/// Extract the ABI from an Etherscan-style `getabi` response
+fn parse_explorer_abi(response: &Value, explorer: &str) -> Result<JsonAbi> {
+    // Check if the response is successful
+    if response["status"] != "1" {
+        let message = response["result"]
+            .as_str()
+            .or_else(|| response["message"].as_str())
+            .unwrap_or("Unknown error");
+        return Err(anyhow!("{} API error: {}", explorer, message));
+    }
+
+    // Parse the ABI
+    let abi_str = response["result"]
+        .as_str()
+        .ok_or_else(|| anyhow!("No ABI found in response"))?;
+
+    if abi_str == "Contract source code not verified" {
+        return Err(anyhow!(
+            "Contract source code is not verified on {}",
+            explorer
+        ));
+    }
+
+    serde_json::from_str(abi_str).map_err(|e| anyhow!("Failed to parse ABI JSON: {}", e))
+}
+
+/// Parse a Sourcify v2 contract lookup response into the ABI, metadata and whether it is a
+/// full match (metadata hash included) rather than a partial one (bytecode only)
+fn parse_sourcify_contract(response: &Value) -> Result<(JsonAbi, Option<Value>, bool)> {
+    let full_match = match response["match"].as_str() {
+        Some("exact_match") => true,
+        Some("match") => false,
+        _ => return Err(anyhow!("Contract is not verified on Sourcify")),
+    };
+
+    let abi: JsonAbi = serde_json::from_value(response["abi"].clone())
+        .map_err(|e| anyhow!("Failed to parse Sourcify ABI: {}", e))?;
+
+    let metadata = match &response["metadata"] {
+        Value::Null => None,
+        metadata => Some(metadata.clone()),
+    };
+
+    Ok((abi, metadata, full_match))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sourcify_contract() {
+        let response = serde_json::json!({
+            "match": "match",
+            "chainId": "1",
+            "address": "0x742d35Cc6435C9c1c72c5E7b18BaB7e1DB7a5d6e",
+            "abi": [{
+                "type": "function",
+                "name": "totalSupply",
+                "stateMutability": "view",
+                "inputs": [],
+                "outputs": [{ "name": "", "type": "uint256" }]
+            }],
+            "metadata": { "compiler": { "version": "0.8.24+commit.e11b9ed9" } }
+        });
+
+        let (abi, metadata, full_match) = parse_sourcify_contract(&response).unwrap();
+        assert!(!full_match);
+        assert_eq!(abi.functions().count(), 1);
+        assert_eq!(
+            metadata.unwrap()["compiler"]["version"],
+            "0.8.24+commit.e11b9ed9"
+        );
+
+        let unverified = serde_json::json!({ "match": null, "abi": null });
+        assert!(parse_sourcify_contract(&unverified).is_err());
+    }
+
+    #[test]
+    fn test_parse_explorer_abi() {
+        let ok = serde_json::json!({ "status": "1", "message": "OK", "result": "[]" });
+        assert_eq!(parse_explorer_abi(&ok, "Blockscout").unwrap().len(), 0);
+
+        let unverified = serde_json::json!({
+            "status": "0",
+            "message": "NOTOK",
+            "result": "Contract source code not verified"
+        });
+        let error = parse_explorer_abi(&unverified, "Blockscout").unwrap_err();
+        assert!(error.to_string().contains("not verified"));
+    }
+}
@@ -0,0 +1,584 @@
+use crate::ethereum::abi_provider::{AbiLookup, AbiProvider, ResolvedAbi};
+use alloy::json_abi::JsonAbi;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Directories never searched for artifacts (dependencies, caches, build info)
+const SKIPPED_DIRS: &[&str] = &["node_modules", "lib", "cache", "build-info", "solcInputs"];
+
+/// ABIs from local projects: Foundry `out/`, Hardhat `artifacts/` and plain `*.abi.json` files
+///
+/// Artifacts carry no address, so each contract is bound to its deployments in one of these
+/// ways, tried in order:
+///
+/// - a file named after the address, `<dir>/<network>/<address>.json` or `<dir>/<address>.json`
+/// - the `[abi.deployments.<network>]` table in the config, mapping addresses to contract
+///   names (`Token` or `Token.sol:Token`) or artifact paths
+/// - hardhat-deploy files, `deployments/<network>/<Name>.json`
+/// - Foundry broadcasts, `broadcast/<Script>/<chain id>/run-latest.json`
+///
+/// The directories are indexed on the first lookup and again after the cache is cleared;
+/// the artifacts themselves are read when they are used.
+#[derive(Debug)]
+pub struct ArtifactsProvider {
+    dirs: Vec<PathBuf>,
+    /// Contract bound to each lowercase address, per network
+    deployments: HashMap<String, HashMap<String, String>>,
+    index: Mutex<Option<Arc<ArtifactIndex>>>,
+}
+
+impl ArtifactsProvider {
+    pub fn new(dirs: Vec<PathBuf>, deployments: HashMap<String, HashMap<String, String>>) -> Self {
+        let deployments = deployments
+            .into_iter()
+            .map(|(network, bindings)| {
+                let bindings = bindings
+                    .into_iter()
+                    .map(|(address, contract)| (address.to_lowercase(), contract))
+                    .collect();
+                (network, bindings)
+            })
+            .collect();
+
+        Self {
+            dirs,
+            deployments,
+            index: Mutex::new(None),
+        }
+    }
+
+    /// Index of the artifact directories, built on first use
+    async fn index(&self) -> Result<Arc<ArtifactIndex>> {
+        let mut index = self.index.lock().await;
+        if let Some(index) = index.as_ref() {
+            return Ok(index.clone());
+        }
+
+        let dirs = self.dirs.clone();
+        let built = tokio::task::spawn_blocking(move || ArtifactIndex::build(&dirs))
+            .await
+            .map_err(|e| anyhow!("Failed to index artifact directories: {}", e))?;
+        let built = Arc::new(built);
+        *index = Some(built.clone());
+        Ok(built)
+    }
+
+    /// Load the ABI of a bound contract, given as a contract name or an artifact path
+    async fn load_contract(&self, index: &ArtifactIndex, contract: &str) -> Result<ResolvedAbi> {
+        if contract.ends_with(".json") {
+            let candidates = std::iter::once(PathBuf::from(contract))
+                .chain(self.dirs.iter().map(|dir| dir.join(contract)));
+            for path in candidates {
+                if tokio::fs::metadata(&path)
+                    .await
+                    .is_ok_and(|metadata| metadata.is_file())
+                {
+                    return self.load_artifact(&path).await;
+                }
+            }
+            return Err(anyhow!("Artifact file '{}' does not exist", contract));
+        }
+
+        // `Token.sol:Token` picks between contracts sharing a name
+        let (source, name) = match contract.split_once(':') {
+            Some((source, name)) => (Some(source), name),
+            None => (None, contract),
+        };
+
+        let matches: Vec<&Artifact> = index
+            .artifacts
+            .iter()
+            .filter(|artifact| artifact.name == name)
+            // Match whole file names, so `Token.sol` doesn't pick up `MyToken.sol`
+            .filter(|artifact| {
+                source.is_none_or(|source| {
+                    artifact.source == source || artifact.source.ends_with(&format!("/{source}"))
+                })
+            })
+            .collect();
+
+        let first = matches
+            .first()
+            .ok_or_else(|| anyhow!("No artifact found for contract '{}'", contract))?;
+
+        // Foundry and Hardhat both building the same file is fine; different files are not
+        let mut sources: Vec<&str> = matches
+            .iter()
+            .map(|artifact| artifact.source.as_str())
+            .collect();
+        sources.sort_unstable();
+        sources.dedup();
+        if sources.len() > 1 {
+            return Err(anyhow!(
+                "Contract name '{}' is ambiguous ({}); bind it as '<File>.sol:{}'",
+                name,
+                sources.join(", "),
+                name
+            ));
+        }
+
+        self.load_artifact(&first.path).await
+    }
+
+    async fn load_artifact(&self, path: &Path) -> Result<ResolvedAbi> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| anyhow!("Failed to read artifact {:?}: {}", path, e))?;
+        let artifact: Value = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Failed to parse artifact {:?}: {}", path, e))?;
+
+        let abi = artifact.get("abi").unwrap_or(&artifact).clone();
+        let abi: JsonAbi = serde_json::from_value(abi)
+            .map_err(|e| anyhow!("Failed to parse ABI in {:?}: {}", path, e))?;
+
+        Ok(ResolvedAbi {
+            abi,
+            provider: self.name().to_string(),
+            metadata: artifact.get("metadata").filter(|m| m.is_object()).cloned(),
+            storage_layout: artifact
+                .get("storageLayout")
+                .filter(|l| l.is_object())
+                .cloned(),
+        })
+    }
+}
+
+#[async_trait]
+impl AbiProvider for ArtifactsProvider {
+    fn name(&self) -> &str {
+        "artifacts"
+    }
+
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    async fn fetch_abi(&self, lookup: &AbiLookup<'_>) -> Result<ResolvedAbi> {
+        if self.dirs.is_empty() && self.deployments.is_empty() {
+            return Err(anyhow!("No artifact directories or deployments configured"));
+        }
+
+        let index = self.index().await?;
+        if let Some(path) = index.address_file(lookup) {
+            return self.load_artifact(path).await;
+        }
+
+        if let Some(contract) = self
+            .deployments
+            .get(lookup.network)
+            .and_then(|bindings| bindings.get(lookup.address))
+        {
+            return self.load_contract(&index, contract).await;
+        }
+
+        for dir in &index.dirs {
+            if let Some(path) = dir.hardhat_deployment(lookup) {
+                if let Ok(resolved) = self.load_artifact(path).await {
+                    return Ok(resolved);
+                }
+            }
+            if let Some(contract) = dir.foundry_broadcast(lookup) {
+                return self.load_contract(&index, contract).await;
+            }
+        }
+
+        Err(anyhow!(
+            "No artifact is bound to this address on network '{}'",
+            lookup.network
+        ))
+    }
+
+    async fn clear_cache(&self) {
+        *self.index.lock().await = None;
+    }
+}
+
+/// What the artifact directories hold, gathered in one walk
+#[derive(Debug, Default)]
+struct ArtifactIndex {
+    /// Compiled contracts in all directories, for bindings by name
+    artifacts: Vec<Artifact>,
+    /// Address bindings of each directory, in configured order
+    dirs: Vec<DirIndex>,
+}
+
+/// Files in one artifact directory that bind an address to a contract
+#[derive(Debug, Default)]
+struct DirIndex {
+    /// Files named after a lowercase address, directly in the directory
+    address_files: HashMap<String, PathBuf>,
+    /// Files named after a lowercase address in a `<network>/` subdirectory
+    network_address_files: HashMap<(String, String), PathBuf>,
+    hardhat_deployments: Vec<HardhatDeployment>,
+    /// Contract names from Foundry broadcasts, by chain ID and lowercase address
+    broadcasts: HashMap<(String, String), String>,
+}
+
+/// A hardhat-deploy deployment file
+#[derive(Debug)]
+struct HardhatDeployment {
+    /// Name of the `deployments/` subdirectory
+    network: String,
+    /// Contents of the subdirectory's `.chainId` file
+    chain_id: Option<String>,
+    /// Lowercase address of the deployment
+    address: String,
+    path: PathBuf,
+}
+
+impl ArtifactIndex {
+    fn build(dirs: &[PathBuf]) -> Self {
+        let mut index = Self::default();
+        for dir in dirs {
+            collect_artifacts(dir, &mut index.artifacts);
+            index.dirs.push(DirIndex::build(dir));
+        }
+        index
+    }
+
+    /// A file named after the contract address, preferring the network's subdirectory
+    fn address_file(&self, lookup: &AbiLookup<'_>) -> Option<&Path> {
+        let network_key = (lookup.network.to_string(), lookup.address.to_string());
+        self.dirs.iter().find_map(|dir| {
+            dir.network_address_files
+                .get(&network_key)
+                .or_else(|| dir.address_files.get(lookup.address))
+                .map(PathBuf::as_path)
+        })
+    }
+}
+
+impl DirIndex {
+    fn build(dir: &Path) -> Self {
+        let mut index = Self::default();
+
+        for path in read_dir(dir) {
+            if let Some(address) = address_file_name(&path) {
+                index.address_files.insert(address, path);
+            } else if path.is_dir() {
+                let Some(network) = path.file_name().and_then(|f| f.to_str()) else {
+                    continue;
+                };
+                for file in read_dir(&path) {
+                    if let Some(address) = address_file_name(&file) {
+                        index
+                            .network_address_files
+                            .insert((network.to_string(), address), file);
+                    }
+                }
+            }
+        }
+
+        for network_dir in read_dir(&dir.join("deployments")) {
+            let Some(network) = network_dir.file_name().and_then(|f| f.to_str()) else {
+                continue;
+            };
+            let chain_id = fs::read_to_string(network_dir.join(".chainId"))
+                .ok()
+                .map(|id| id.trim().to_string());
+
+            for path in read_dir(&network_dir) {
+                if path.extension().is_none_or(|ext| ext != "json") {
+                    continue;
+                }
+                let Some(deployment) = read_json(&path) else {
+                    continue;
+                };
+                let Some(address) = deployment["address"].as_str() else {
+                    continue;
+                };
+                index.hardhat_deployments.push(HardhatDeployment {
+                    network: network.to_string(),
+                    chain_id: chain_id.clone(),
+                    address: address.to_lowercase(),
+                    path,
+                });
+            }
+        }
+
+        for script_dir in read_dir(&dir.join("broadcast")) {
+            for chain_dir in read_dir(&script_dir) {
+                let Some(chain_id) = chain_dir.file_name().and_then(|f| f.to_str()) else {
+                    continue;
+                };
+                let Some(run) = read_json(&chain_dir.join("run-latest.json")) else {
+                    continue;
+                };
+                let transactions = run["transactions"].as_array().into_iter().flatten();
+                for transaction in transactions {
+                    let address = transaction["contractAddress"].as_str();
+                    let name = transaction["contractName"].as_str();
+                    if let (Some(address), Some(name)) = (address, name) {
+                        index
+                            .broadcasts
+                            .entry((chain_id.to_string(), address.to_lowercase()))
+                            .or_insert_with(|| name.to_string());
+                    }
+                }
+            }
+        }
+
+        index
+    }
+
+    /// A hardhat-deploy deployment of the address, under `deployments/<network>/`
+    fn hardhat_deployment(&self, lookup: &AbiLookup<'_>) -> Option<&Path> {
+        let chain_id = lookup.chain_id().ok().map(|id| id.to_string());
+        self.hardhat_deployments
+            .iter()
+            .filter(|deployment| {
+                deployment.network == lookup.network
+                    || (deployment.chain_id.is_some() && deployment.chain_id == chain_id)
+            })
+            .find(|deployment| deployment.address == lookup.address)
+            .map(|deployment| deployment.path.as_path())
+    }
+
+    /// The contract name a Foundry broadcast recorded for the address
+    fn foundry_broadcast(&self, lookup: &AbiLookup<'_>) -> Option<&str> {
+        let chain_id = lookup.chain_id().ok()?.to_string();
+        self.broadcasts
+            .get(&(chain_id, lookup.address.to_string()))
+            .map(String::as_str)
+    }
+}
+
+/// A compiled contract found on disk
+#[derive(Debug)]
+struct Artifact {
+    name: String,
+    /// Source file the artifact was built from, e.g. `Token.sol`, or the `*.abi.json` file name
+    source: String,
+    path: PathBuf,
+}
+
+/// Recursively collect Foundry, Hardhat and `*.abi.json` artifacts under a directory
+fn collect_artifacts(dir: &Path, artifacts: &mut Vec<Artifact>) {
+    for path in read_dir(dir) {
+        let Some(file_name) = path.file_name().and_then(|f| f.to_str()) else {
+            continue;
+        };
+
+        if path.is_dir() {
+            if !file_name.starts_with('.') && !SKIPPED_DIRS.contains(&file_name) {
+                collect_artifacts(&path, artifacts);
+            }
+        } else if let Some((name, source)) = artifact_name(&path) {
+            artifacts.push(Artifact { name, source, path });
+        }
+    }
+}
+
+/// Contract name and source file of an artifact path, e.g. `out/Token.sol/Token.json`
+fn artifact_name(path: &Path) -> Option<(String, String)> {
+    let file_name = path.file_name()?.to_str()?;
+    if let Some(name) = file_name.strip_suffix(".abi.json") {
+        return Some((name.to_string(), file_name.to_string()));
+    }
+    if file_name.ends_with(".dbg.json") {
+        return None;
+    }
+
+    let stem = file_name.strip_suffix(".json")?;
+    let source = path.parent()?.file_name()?.to_str()?;
+    if !source.ends_with(".sol") && !source.ends_with(".vy") {
+        return None;
+    }
+
+    // Foundry names builds with several compiler versions `Token.0.8.20.json`
+    let name = stem.split('.').next()?;
+    Some((name.to_string(), source.to_string()))
+}
+
+/// Lowercase address of a file named `<address>.json`
+fn address_file_name(path: &Path) -> Option<String> {
+    let stem = path.file_name()?.to_str()?.strip_suffix(".json")?;
+    let hex_digits = stem.strip_prefix("0x")?;
+    if hex_digits.len() != 40 || !hex_digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    path.is_file().then(|| stem.to_lowercase())
+}
+
+fn read_dir(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+    paths
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const ADDRESS: &str = "0x742d35cc6435c9c1c72c5e7b18bab7e1db7a5d6e";
+    const TOKEN_ABI: &str = r#"[{"type": "function", "name": "balanceOf", "stateMutability": "view",
+        "inputs": [{"name": "owner", "type": "address"}], "outputs": [{"name": "", "type": "uint256"}]}]"#;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn lookup(network: &str) -> AbiLookup<'_> {
+        AbiLookup {
+            address: ADDRESS,
+            key: ADDRESS,
+            network,
+            network_config: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_address_named_files() {
+        let temp_dir = tempdir().unwrap();
+        write(
+            &temp_dir
+                .path()
+                .join("sepolia")
+                .join(format!("{}.json", ADDRESS)),
+            &format!(r#"{{"abi": {}}}"#, TOKEN_ABI),
+        );
+
+        let provider = ArtifactsProvider::new(vec![temp_dir.path().to_path_buf()], HashMap::new());
+        let resolved = provider.fetch_abi(&lookup("sepolia")).await.unwrap();
+        assert_eq!(resolved.provider, "artifacts");
+        assert_eq!(resolved.abi.functions().count(), 1);
+
+        assert!(provider.fetch_abi(&lookup("ethereum")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_config_bindings() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write(
+            &root.join("out/Token.sol/Token.json"),
+            &format!(
+                r#"{{"abi": {}, "metadata": {{"language": "Solidity"}}}}"#,
+                TOKEN_ABI
+            ),
+        );
+        write(
+            &root.join("artifacts/contracts/Token.sol/Token.json"),
+            &format!(r#"{{"contractName": "Token", "abi": {}}}"#, TOKEN_ABI),
+        );
+        write(
+            &root.join("artifacts/contracts/Token.sol/Token.dbg.json"),
+            r#"{"buildInfo": "../../build-info/x.json"}"#,
+        );
+        write(&root.join("abis/Vault.abi.json"), "[]");
+        write(&root.join("other/Vault.sol/Vault.json"), r#"{"abi": []}"#);
+        write(&root.join("out/MyVault.sol/Vault.json"), r#"{"abi": []}"#);
+
+        let bindings = |contract: &str| {
+            HashMap::from([(
+                "sepolia".to_string(),
+                HashMap::from([(
+                    ADDRESS.to_uppercase().replace("0X", "0x"),
+                    contract.to_string(),
+                )]),
+            )])
+        };
+
+        // The same contract built by Foundry and Hardhat is not ambiguous
+        let provider = ArtifactsProvider::new(vec![root.to_path_buf()], bindings("Token"));
+        let resolved = provider.fetch_abi(&lookup("sepolia")).await.unwrap();
+        assert_eq!(resolved.abi.functions().count(), 1);
+
+        // Two different Vault sources are, unless qualified
+        let provider = ArtifactsProvider::new(vec![root.to_path_buf()], bindings("Vault"));
+        let error = provider.fetch_abi(&lookup("sepolia")).await.unwrap_err();
+        assert!(error.to_string().contains("ambiguous"));
+
+        let provider =
+            ArtifactsProvider::new(vec![root.to_path_buf()], bindings("Vault.sol:Vault"));
+        assert!(provider.fetch_abi(&lookup("sepolia")).await.is_ok());
+
+        let provider =
+            ArtifactsProvider::new(vec![root.to_path_buf()], bindings("MyVault.sol:Vault"));
+        assert!(provider.fetch_abi(&lookup("sepolia")).await.is_ok());
+
+        let provider =
+            ArtifactsProvider::new(vec![root.to_path_buf()], bindings("abis/Vault.abi.json"));
+        assert!(provider.fetch_abi(&lookup("sepolia")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_index_is_cached() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write(&root.join("out/Token.sol/Token.json"), r#"{"abi": []}"#);
+
+        let provider = ArtifactsProvider::new(vec![root.to_path_buf()], HashMap::new());
+        assert!(provider.fetch_abi(&lookup("sepolia")).await.is_err());
+
+        // Files added after the first lookup are found once the cache is cleared
+        write(
+            &root.join("deployments/sepolia/Token.json"),
+            &format!(r#"{{"address": "{}", "abi": {}}}"#, ADDRESS, TOKEN_ABI),
+        );
+        assert!(provider.fetch_abi(&lookup("sepolia")).await.is_err());
+        provider.clear_cache().await;
+        assert!(provider.fetch_abi(&lookup("sepolia")).await.is_ok());
+
+        // Indexed artifacts are read when used, so a rebuild takes effect immediately
+        write(
+            &root.join("deployments/sepolia/Token.json"),
+            &format!(r#"{{"address": "{}", "abi": []}}"#, ADDRESS),
+        );
+        let resolved = provider.fetch_abi(&lookup("sepolia")).await.unwrap();
+        assert_eq!(resolved.abi.functions().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_deployment_files() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path();
+        write(
+            &root.join("deployments/sepolia/Token.json"),
+            &format!(r#"{{"address": "{}", "abi": {}}}"#, ADDRESS, TOKEN_ABI),
+        );
+
+        let provider = ArtifactsProvider::new(vec![root.to_path_buf()], HashMap::new());
+        let resolved = provider.fetch_abi(&lookup("sepolia")).await.unwrap();
+        assert_eq!(resolved.abi.functions().count(), 1);
+        assert!(provider.fetch_abi(&lookup("polygon")).await.is_err());
+
+        let broadcast = tempdir().unwrap();
+        let root = broadcast.path();
+        write(
+            &root.join("out/Token.sol/Token.json"),
+            &format!(r#"{{"abi": {}}}"#, TOKEN_ABI),
+        );
+        write(
+            &root.join("broadcast/Deploy.s.sol/1/run-latest.json"),
+            &format!(
+                r#"{{"transactions": [{{"transactionType": "CREATE", "contractName": "Token", "contractAddress": "{}"}}]}}"#,
+                ADDRESS
+            ),
+        );
+
+        let network_config = crate::config::Config::default().networks["ethereum"].clone();
+        let provider = ArtifactsProvider::new(vec![root.to_path_buf()], HashMap::new());
+        let lookup = AbiLookup {
+            network_config: Some(&network_config),
+            ..lookup("ethereum")
+        };
+        let resolved = provider.fetch_abi(&lookup).await.unwrap();
+        assert_eq!(resolved.abi.functions().count(), 1);
+    }
+}
@@ -1,29 +1,86 @@
 use alloy::{
-    dyn_abi::{DynSolValue, FunctionExt, JsonAbiExt, Word},
-    primitives::{Address, Bytes, U256},
-    providers::Provider,
-    rpc::types::{Filter, TransactionRequest},
+    dyn_abi::{DecodedEvent, DynSolValue, EventExt, FunctionExt, JsonAbiExt, Word},
+    eips::{BlockId, BlockNumberOrTag},
+    json_abi::{Event, EventParam, JsonAbi, Param},
+    primitives::{address, keccak256, Address, Bytes, Function, Selector, B256, I256, U256},
+    providers::{Provider, RootProvider},
+    rpc::types::{
+        state::StateOverride, BlockTransactionsKind, Filter, Log, Topic, TransactionRequest,
+    },
+    transports::http::{Client, Http},
 };
 use anyhow::{anyhow, Result};
 use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
-use super::{CallResult, ContractInfo, EventInfo, FunctionCall, TransactionInfo};
-use crate::ethereum::{abi::AbiResolver, provider::ProviderManager, utils};
+use super::{
+    BatchCall, CallBlock, CallResult, ContractInfo, EventFilter, EventInfo, EventPage,
+    EventPagination, FunctionCall, TransactionInfo,
+};
+use crate::ethereum::{
+    abi::{self, AbiResolver, StoredAbi},
+    abi_provider::ResolvedAbi,
+    deployment::{self, Deployment},
+    fork::{self, ForkBlock, ForkRecording, ForkSession, ForkSessionInfo, ForkTransaction},
+    overrides::{self, Erc20Mapping, MappingSlot},
+    provider::ProviderManager,
+    proxy::{self, ProxyInfo},
+    selectors,
+    source::SourceInfo,
+    standards, storage,
+    trace::{self, CallTrace},
+    utils::{self, CachedLookup},
+};
+
+/// Largest block range requested in a single eth_getLogs call
+const LOG_CHUNK_SIZE: u64 = 10_000;
+/// Upper bound on eth_getLogs calls made for one page of events
+const MAX_LOG_REQUESTS_PER_PAGE: usize = 100;
+/// Events returned per page when the caller gives no limit
+const DEFAULT_EVENT_LIMIT: usize = 1_000;
+/// Largest page size a caller may request
+const MAX_EVENT_LIMIT: usize = 10_000;
+/// Canonical Multicall3 deployment, at the same address on most chains
+const MULTICALL3_ADDRESS: Address = address!("cA11bde05977b3631167028862bE2a173976CA11");
+/// `aggregate3` with calls as (target, allowFailure, callData) and results as
+/// (success, returnData)
+const AGGREGATE3_SIGNATURE: &str =
+    "function aggregate3((address,bool,bytes)[] calls) payable returns ((bool,bytes)[] results)";
+/// Most calls sent in one batch
+const MAX_BATCH_CALLS: usize = 1_000;
+/// Most contracts whose ABIs are resolved to decode a call trace or a fork transaction's logs
+const MAX_TRACE_ABIS: usize = 50;
+/// Most fork sessions open at once; each holds the state it has loaded in memory
+const MAX_FORK_SESSIONS: usize = 16;
 
 #[derive(Debug)]
 pub struct ContractManager {
     provider_manager: ProviderManager,
     abi_resolver: AbiResolver,
+    /// Deployment lookups so far, by network and address. Deployments never change; failed
+    /// lookups are retried once they expire
+    deployments: HashMap<String, CachedLookup<Deployment>>,
+    /// ERC-20 balance and allowance mapping lookups so far, by network, token and mapping;
+    /// failed probes are retried once they expire
+    erc20_mappings: HashMap<String, CachedLookup<MappingSlot>>,
+    /// Open fork sessions by id
+    fork_sessions: HashMap<String, ForkSession>,
+    /// Fork sessions created so far, numbering the next one
+    fork_session_count: u64,
 }
 
 impl ContractManager {
     pub fn new(provider_manager: ProviderManager) -> Self {
         use crate::ethereum::abi::AbiSource;
-        let abi_resolver = AbiResolver::new(AbiSource::default());
+        let abi_resolver = AbiResolver::new(AbiSource::from_config(provider_manager.config()));
         Self {
             provider_manager,
             abi_resolver,
+            deployments: HashMap::new(),
+            erc20_mappings: HashMap::new(),
+            fork_sessions: HashMap::new(),
+            fork_session_count: 0,
         }
     }
 
@@ -68,37 +125,298 @@ impl ContractManager {
             ));
         }
 
-        // Try to get ABI from Etherscan
-        let (abi_value, verified) = match self.abi_resolver.get_abi(address, network).await {
-            Ok(abi) => {
-                let abi_value =
-                    serde_json::to_value(&abi).unwrap_or_else(|_| serde_json::json!([]));
-                (abi_value, true)
-            }
+        // Try to get ABI from the configured providers
+        let (abi_value, abi_provider, metadata, proxy, mut selectors) =
+            match Self::resolve_contract_abi_with_code(
+                &mut self.abi_resolver,
+                provider,
+                contract_address,
+                Some(bytecode.clone()),
+                BlockId::latest(),
+                network,
+            )
+            .await
+            {
+                Ok((resolved, proxy)) => {
+                    let abi_value = serde_json::to_value(&resolved.abi)
+                        .unwrap_or_else(|_| serde_json::json!([]));
+                    let selectors: HashSet<Selector> =
+                        resolved.abi.functions().map(|f| f.selector()).collect();
+                    (
+                        abi_value,
+                        Some(resolved.provider),
+                        resolved.metadata,
+                        proxy,
+                        selectors,
+                    )
+                }
+                Err(e) => {
+                    let friendly_error = utils::interpret_abi_error(&e.to_string(), address);
+                    tracing::debug!("ABI resolution failed for {}: {}", address, friendly_error);
+                    let proxy = proxy::detect_proxy(
+                        provider,
+                        contract_address,
+                        &bytecode,
+                        BlockId::latest(),
+                    )
+                    .await
+                    .ok()
+                    .flatten();
+                    (serde_json::json!([]), None, None, proxy, HashSet::new())
+                }
+            };
+        let verified = matches!(
+            abi_provider.as_deref(),
+            Some("etherscan" | "sourcify" | "blockscout")
+        );
+
+        let source = match self.abi_resolver.source_code(address, network).await {
+            Ok(source) => Some(source.info),
             Err(e) => {
-                let friendly_error = utils::interpret_abi_error(&e.to_string(), address);
-                tracing::debug!("ABI resolution failed for {}: {}", address, friendly_error);
-                (serde_json::json!([]), false)
+                tracing::debug!("No verified source for {}: {}", address, e);
+                metadata
+                    .as_ref()
+                    .map(|metadata| SourceInfo::from_metadata(metadata, "metadata"))
             }
         };
 
+        selectors.extend(selectors::extract_selectors(&bytecode));
+        let standards = standards::detect_standards(provider, contract_address, &selectors).await;
+
+        let deployment = self.deployment(contract_address, network).await;
+        let unknown_selectors = (abi_provider.as_deref() == Some("inferred"))
+            .then(|| self.abi_resolver.unknown_selectors(&bytecode));
+
         let info = ContractInfo {
             address: format!("{:?}", contract_address), // This gives us the checksummed address
-            name: None, // Could be extracted from ABI or contract name resolution
+            name: source.as_ref().and_then(|s| s.contract_name.clone()),
             abi: abi_value,
             bytecode: if bytecode.is_empty() {
                 None
             } else {
                 Some(format!("0x{}", hex::encode(&bytecode)))
             },
-            deployment_block: None, // Would need to search for contract creation
-            creator: None,          // Would need creation transaction analysis
+            deployment_block: deployment.as_ref().map(|d| d.block),
+            creator: deployment
+                .as_ref()
+                .and_then(|d| d.creator)
+                .map(|creator| format!("{:?}", creator)),
+            creation_transaction: deployment
+                .as_ref()
+                .and_then(|d| d.transaction_hash)
+                .map(|hash| format!("{:?}", hash)),
             verified,
+            abi_provider,
+            metadata,
+            proxy,
+            source,
+            unknown_selectors,
+            standards,
         };
 
         Ok(info)
     }
 
+    /// Deployment block, creation transaction and creator of a contract, when they can be found
+    async fn deployment(&mut self, address: Address, network: Option<&str>) -> Option<Deployment> {
+        let key = self
+            .abi_resolver
+            .cache_key(&format!("{:?}", address), network);
+        let deployment = match self.deployments.get(&key).and_then(CachedLookup::get) {
+            Some(deployment) => deployment,
+            None => {
+                let provider = self.provider_manager.get_provider(network).ok()?;
+                let explorer = self.abi_resolver.explorer(network).ok();
+                let deployment =
+                    deployment::find_deployment(provider, explorer.as_ref(), address).await;
+                self.deployments.insert(key, CachedLookup::new(&deployment));
+                deployment
+            }
+        };
+        deployment
+            .map_err(|e| tracing::debug!("Deployment of {:?} not found: {}", address, e))
+            .ok()
+    }
+
+    /// Resolve a contract's ABI as of `block`. For proxies the ABIs of the implementations live
+    /// at that block are merged in, so calls reach the functions the proxy delegated to then.
+    async fn resolve_contract_abi(
+        abi_resolver: &mut AbiResolver,
+        provider: &RootProvider<Http<Client>>,
+        address: Address,
+        block: BlockId,
+        network: Option<&str>,
+    ) -> Result<(ResolvedAbi, Option<ProxyInfo>)> {
+        let code = match provider.get_code_at(address).block_id(block).await {
+            Ok(code) => Some(code),
+            Err(e) => {
+                tracing::debug!("Failed to fetch bytecode for {:?}: {}", address, e);
+                None
+            }
+        };
+        Self::resolve_contract_abi_with_code(abi_resolver, provider, address, code, block, network)
+            .await
+    }
+
+    /// [`Self::resolve_contract_abi`] for a caller that already fetched the runtime code at
+    /// `block`
+    async fn resolve_contract_abi_with_code(
+        abi_resolver: &mut AbiResolver,
+        provider: &RootProvider<Http<Client>>,
+        address: Address,
+        code: Option<Bytes>,
+        block: BlockId,
+        network: Option<&str>,
+    ) -> Result<(ResolvedAbi, Option<ProxyInfo>)> {
+        let contract_address = format!("{:?}", address);
+
+        // Cached ABIs are only reused while the code they were fetched against is deployed
+        let code_hash = code.as_ref().filter(|code| !code.is_empty()).map(keccak256);
+        let own_abi = abi_resolver
+            .resolve_abi(&contract_address, network, code_hash)
+            .await
+            .or_else(|e| {
+                // Unverified contracts get an ABI inferred from their dispatcher
+                code.as_ref()
+                    .and_then(|code| abi_resolver.infer_abi(code))
+                    .ok_or(e)
+            });
+
+        // Only clones and non-proxies are cached; upgradeable proxies are re-read every time
+        let cached_proxy =
+            code_hash.and_then(|hash| abi_resolver.cached_proxy(&contract_address, network, hash));
+        let proxy = match (&code, cached_proxy) {
+            (_, Some(proxy)) => proxy,
+            (Some(code), None) => match proxy::detect_proxy(provider, address, code, block).await {
+                Ok(proxy) => {
+                    if let Some(hash) = code_hash {
+                        abi_resolver.cache_proxy(&contract_address, network, hash, proxy.clone());
+                    }
+                    proxy
+                }
+                Err(e) => {
+                    tracing::debug!("Proxy detection failed for {}: {}", contract_address, e);
+                    None
+                }
+            },
+            (None, None) => None,
+        };
+        let Some(proxy) = proxy else {
+            return Ok((own_abi?, None));
+        };
+
+        let mut merged: Option<ResolvedAbi> = None;
+        for implementation in proxy.implementation_addresses() {
+            let resolved = Self::resolve_implementation_abi(
+                abi_resolver,
+                provider,
+                &implementation,
+                block,
+                network,
+            )
+            .await;
+            match (resolved, &mut merged) {
+                (Some(resolved), Some(merged)) => proxy::merge_abi(&mut merged.abi, &resolved.abi),
+                (Some(resolved), None) => merged = Some(resolved),
+                (None, _) => {}
+            }
+        }
+
+        // Implementation entries win over proxy entries with the same selector
+        let resolved = match (merged, own_abi) {
+            (Some(mut merged), Ok(own)) => {
+                proxy::merge_abi(&mut merged.abi, &own.abi);
+                merged
+            }
+            (Some(merged), Err(_)) => merged,
+            (None, own) => own?,
+        };
+
+        Ok((resolved, Some(proxy)))
+    }
+
+    /// ABI of a proxy's implementation, checked against its code at `block` so a cached ABI is
+    /// dropped if the address is redeployed, and inferred from that code when no provider has it
+    async fn resolve_implementation_abi(
+        abi_resolver: &mut AbiResolver,
+        provider: &RootProvider<Http<Client>>,
+        implementation: &str,
+        block: BlockId,
+        network: Option<&str>,
+    ) -> Option<ResolvedAbi> {
+        let code = match Address::from_str(implementation) {
+            Ok(address) => provider.get_code_at(address).block_id(block).await.ok(),
+            Err(_) => None,
+        };
+        let code_hash = code.as_ref().filter(|code| !code.is_empty()).map(keccak256);
+        match abi_resolver
+            .resolve_abi(implementation, network, code_hash)
+            .await
+        {
+            Ok(resolved) => Some(resolved),
+            Err(e) => {
+                tracing::debug!("No ABI for implementation {}: {}", implementation, e);
+                code.and_then(|code| abi_resolver.infer_abi(&code))
+            }
+        }
+    }
+
+    /// Resolve the function to call. A fragment that declares its outputs, such as
+    /// `balanceOf(address)(uint256)` or `function transfer(address to, uint256 amt) returns
+    /// (bool)`, is used as is, like `cast call` does. Anything else is looked up in the contract
+    /// ABI as of `block`, falling back to a bare signature when the ABI is unavailable or lacks
+    /// it.
+    async fn resolve_call_function(
+        abi_resolver: &mut AbiResolver,
+        provider: &RootProvider<Http<Client>>,
+        address: Address,
+        function_name: &str,
+        block: BlockId,
+        network: Option<&str>,
+    ) -> Result<alloy::json_abi::Function> {
+        let fragment = Self::function_fragment(function_name)?;
+        if let Some(fragment) = fragment.as_ref().filter(|f| !f.outputs.is_empty()) {
+            return Ok(fragment.clone());
+        }
+        let abi = Self::resolve_contract_abi(abi_resolver, provider, address, block, network)
+            .await
+            .map(|(resolved, _)| resolved.abi);
+        Self::select_function(abi, fragment, function_name, address)
+    }
+
+    /// Parse a function name given as a signature; bare names and selectors are not fragments
+    fn function_fragment(function_name: &str) -> Result<Option<alloy::json_abi::Function>> {
+        let function_name = function_name.trim();
+        if !function_name.contains('(') {
+            return Ok(None);
+        }
+        alloy::json_abi::Function::parse(function_name)
+            .map(Some)
+            .map_err(|e| anyhow!("Invalid function signature '{}': {}", function_name, e))
+    }
+
+    /// Look a function up in the contract ABI, falling back to the fragment it was named by when
+    /// the ABI is unavailable or lacks it
+    fn select_function(
+        abi: Result<JsonAbi>,
+        fragment: Option<alloy::json_abi::Function>,
+        function_name: &str,
+        address: Address,
+    ) -> Result<alloy::json_abi::Function> {
+        match abi {
+            Ok(abi) => match Self::resolve_function(&abi, function_name) {
+                Ok(function) => Ok(function.clone()),
+                Err(e) => fragment.ok_or(e),
+            },
+            Err(e) => fragment.ok_or_else(|| {
+                anyhow!(
+                    "{} Alternatively, call it by fragment, e.g. 'balanceOf(address)(uint256)'",
+                    utils::interpret_abi_error(&e.to_string(), &format!("{:?}", address))
+                )
+            }),
+        }
+    }
+
     pub async fn call_view_function(
         &mut self,
         contract_address: &str,
@@ -123,44 +441,47 @@ impl ContractManager {
             .get_provider(network)
             .map_err(|e| anyhow!("Failed to get provider: {}", e))?;
 
-        // Get the ABI for the contract
-        let abi = match self.abi_resolver.get_abi(contract_address, network).await {
-            Ok(abi) => abi,
+        // Find the function in the contract ABI as of the call's block, or take it from the
+        // fragment given
+        let (block, call_block) =
+            match Self::pin_block(provider, function_call.block.as_ref()).await {
+                Ok(pinned) => pinned,
+                Err(e) => {
+                    return Ok(CallResult {
+                        success: false,
+                        result: None,
+                        error: Some(e.to_string()),
+                        gas_used: None,
+                        transaction_hash: None,
+                        block: None,
+                    });
+                }
+            };
+        let function = match Self::resolve_call_function(
+            &mut self.abi_resolver,
+            provider,
+            address,
+            &function_call.function_name,
+            block,
+            network,
+        )
+        .await
+        {
+            Ok(function) => function,
             Err(e) => {
                 return Ok(CallResult {
                     success: false,
                     result: None,
-                    error: Some(utils::interpret_abi_error(&e.to_string(), contract_address)),
+                    error: Some(e.to_string()),
                     gas_used: None,
                     transaction_hash: None,
+                    block: None,
                 });
             }
         };
 
-        // Find the function in the ABI
-        let function = abi
-            .functions()
-            .find(|f| f.name == function_call.function_name)
-            .ok_or_else(|| {
-                let available_functions: Vec<String> =
-                    abi.functions().map(|f| f.name.clone()).collect();
-
-                if available_functions.is_empty() {
-                    anyhow!(
-                        "Function '{}' not found. The contract ABI contains no functions.",
-                        function_call.function_name
-                    )
-                } else {
-                    anyhow!(
-                        "Function '{}' not found in contract ABI. Available functions: {}",
-                        function_call.function_name,
-                        available_functions.join(", ")
-                    )
-                }
-            })?;
-
         // Encode the function call
-        let calldata = match self.encode_function_call(function, &function_call.parameters) {
+        let calldata = match self.encode_function_call(&function, &function_call.parameters) {
             Ok(data) => data,
             Err(e) => {
                 return Ok(CallResult {
@@ -169,45 +490,529 @@ impl ContractManager {
                     error: Some(format!("Failed to encode function call: {}", e)),
                     gas_used: None,
                     transaction_hash: None,
+                    block: None,
                 });
             }
         };
 
         // Make the eth_call
+        let state_override = Self::state_overrides(
+            &mut self.abi_resolver,
+            &mut self.erc20_mappings,
+            provider,
+            function_call.state_overrides.as_ref(),
+            block,
+            network,
+        )
+        .await?;
         let call_request = TransactionRequest::default()
             .to(address)
             .input(calldata.into());
 
-        match provider.call(&call_request).await {
+        let mut call = provider.call(&call_request).block(block);
+        if let Some(state_override) = &state_override {
+            call = call.overrides(state_override);
+        }
+        let mut result = match call.await {
             Ok(result_bytes) => {
-                // Decode the result
-                match self.decode_function_result(function, &result_bytes) {
-                    Ok(decoded) => Ok(CallResult {
-                        success: true,
-                        result: Some(decoded),
-                        error: None,
-                        gas_used: None,
-                        transaction_hash: None,
-                    }),
-                    Err(e) => Ok(CallResult {
+                self.call_result(&function, &result_bytes, function_call.include_types)
+            }
+            Err(e) => CallResult {
+                success: false,
+                result: None,
+                error: Some(utils::interpret_rpc_error(&e.to_string())),
+                gas_used: None,
+                transaction_hash: None,
+                block: None,
+            },
+        };
+        result.block = Some(call_block);
+        Ok(result)
+    }
+
+    /// Resolve the block a call runs against. Tags other than `pending` are fixed to the block
+    /// they point at, so that the call and the block reported with it agree. Block numbers are
+    /// already fixed, so they are used as given without fetching the block for its hash.
+    async fn pin_block(
+        provider: &RootProvider<Http<Client>>,
+        block: Option<&Value>,
+    ) -> Result<(BlockId, CallBlock)> {
+        let block = Self::parse_call_block(block)?;
+        if block.is_number() || block == BlockId::pending() {
+            return Ok((block, Self::requested_block(block)));
+        }
+
+        let header = match block {
+            BlockId::Hash(hash) => {
+                provider
+                    .get_block_by_hash(hash.block_hash, BlockTransactionsKind::Hashes)
+                    .await
+            }
+            BlockId::Number(number) => {
+                provider
+                    .get_block_by_number(number, BlockTransactionsKind::Hashes)
+                    .await
+            }
+        }
+        .map_err(|e| anyhow!("Failed to fetch block {}: {}", block, e))?
+        .ok_or_else(|| anyhow!("Block {} not found", block))?
+        .header;
+
+        let pinned = match block {
+            BlockId::Hash(_) => block,
+            BlockId::Number(_) => BlockId::number(header.number),
+        };
+        let call_block = CallBlock {
+            number: Some(header.number),
+            hash: Some(format!("{:?}", header.hash)),
+            ..Self::requested_block(block)
+        };
+        Ok((pinned, call_block))
+    }
+
+    /// Block a call asks for; defaults to latest
+    fn parse_call_block(block: Option<&Value>) -> Result<BlockId> {
+        match block {
+            Some(block) => utils::parse_block_id(block),
+            None => Ok(BlockId::latest()),
+        }
+    }
+
+    /// Report a block as it was requested, without looking it up
+    fn requested_block(block: BlockId) -> CallBlock {
+        match block {
+            BlockId::Hash(hash) => CallBlock {
+                number: None,
+                hash: Some(format!("{:?}", hash.block_hash)),
+                tag: None,
+            },
+            BlockId::Number(BlockNumberOrTag::Number(number)) => CallBlock {
+                number: Some(number),
+                hash: None,
+                tag: None,
+            },
+            BlockId::Number(tag) => CallBlock {
+                number: None,
+                hash: None,
+                tag: Some(tag.to_string()),
+            },
+        }
+    }
+
+    /// Trace a call with Geth's callTracer and decode the call tree with the ABIs of the
+    /// contracts it calls
+    async fn trace_call(
+        abi_resolver: &mut AbiResolver,
+        provider: &RootProvider<Http<Client>>,
+        tx_request: &TransactionRequest,
+        block: BlockId,
+        state_override: Option<&StateOverride>,
+        network: Option<&str>,
+    ) -> Result<CallTrace> {
+        let mut config = serde_json::json!({ "tracer": "callTracer" });
+        if let Some(state_override) = state_override {
+            config["stateOverrides"] = serde_json::to_value(state_override)?;
+        }
+        let frame: Value = provider
+            .raw_request("debug_traceCall".into(), (tx_request, block, config))
+            .await
+            .map_err(|e| {
+                anyhow!(
+                    "debug_traceCall failed: {}",
+                    utils::interpret_rpc_error(&e.to_string())
+                )
+            })?;
+        let frame = trace::CallFrame::from_value(&frame)?;
+
+        let mut abis = HashMap::new();
+        for target in frame.targets().into_iter().take(MAX_TRACE_ABIS) {
+            // Precompiles have no ABI
+            if target.0[..19].iter().all(|byte| *byte == 0) {
+                continue;
+            }
+            match Self::resolve_contract_abi(abi_resolver, provider, target, block, network).await {
+                Ok((resolved, _)) => {
+                    abis.insert(target, resolved.abi);
+                }
+                Err(e) => tracing::debug!("No ABI for traced contract {:?}: {}", target, e),
+            }
+        }
+        Ok(CallTrace::new(&frame, &abis))
+    }
+
+    /// Build the state overrides of a call. ERC-20 balances and allowances are written to the
+    /// token's mapping slots, which are looked up in its storage layout or found by probing.
+    async fn state_overrides(
+        abi_resolver: &mut AbiResolver,
+        erc20_mappings: &mut HashMap<String, CachedLookup<MappingSlot>>,
+        provider: &RootProvider<Http<Client>>,
+        value: Option<&Value>,
+        block: BlockId,
+        network: Option<&str>,
+    ) -> Result<Option<StateOverride>> {
+        let Some(value) = value else {
+            return Ok(None);
+        };
+        let (mut state_override, erc20) = overrides::parse_state_override(value)?;
+        for entry in &erc20 {
+            let mapping = Self::erc20_mapping(
+                abi_resolver,
+                erc20_mappings,
+                provider,
+                entry.token,
+                entry.mapping,
+                block,
+                network,
+            )
+            .await?;
+            overrides::set_erc20_slot(&mut state_override, entry, mapping);
+        }
+        Ok(Some(state_override))
+    }
+
+    /// Balance or allowance mapping of an ERC-20 token, found once per token
+    async fn erc20_mapping(
+        abi_resolver: &mut AbiResolver,
+        erc20_mappings: &mut HashMap<String, CachedLookup<MappingSlot>>,
+        provider: &RootProvider<Http<Client>>,
+        token: Address,
+        mapping: Erc20Mapping,
+        block: BlockId,
+        network: Option<&str>,
+    ) -> Result<MappingSlot> {
+        let key = format!(
+            "{}_{}",
+            abi_resolver.cache_key(&format!("{:?}", token), network),
+            mapping.name()
+        );
+        if let Some(slot) = erc20_mappings.get(&key).and_then(CachedLookup::get) {
+            return slot;
+        }
+
+        let layout = Self::resolve_contract_abi(abi_resolver, provider, token, block, network)
+            .await
+            .ok()
+            .and_then(|(resolved, _)| resolved.storage_layout)
+            .and_then(|layout| storage::StorageLayout::from_value(&layout).ok());
+        let slot =
+            overrides::find_erc20_mapping(provider, token, mapping, layout.as_ref(), block).await;
+        erc20_mappings.insert(key, CachedLookup::new(&slot));
+        slot
+    }
+
+    /// Call read-only functions, possibly on several contracts, in a single eth_call through
+    /// Multicall3 `aggregate3`. Results are returned in the order of `calls`; a call that cannot
+    /// be prepared or that reverts fails the whole batch unless it allows failure. All calls run
+    /// against the same pinned block, which every result reports.
+    pub async fn batch_call(
+        &mut self,
+        calls: &[BatchCall],
+        block: Option<&Value>,
+        network: Option<&str>,
+    ) -> Result<Vec<CallResult>> {
+        if calls.is_empty() {
+            return Err(anyhow!("No calls given"));
+        }
+        if calls.len() > MAX_BATCH_CALLS {
+            return Err(anyhow!(
+                "Too many calls: {} (at most {} per batch)",
+                calls.len(),
+                MAX_BATCH_CALLS
+            ));
+        }
+        if let Some(net) = network {
+            let available_networks = self.provider_manager.get_available_networks();
+            utils::validate_network(net, &available_networks)
+                .map_err(|e| anyhow!("Network validation failed: {}", e))?;
+        }
+        let multicall = match &self
+            .provider_manager
+            .get_network_config(network)?
+            .multicall_address
+        {
+            Some(address) => utils::validate_address(address)
+                .map_err(|e| anyhow!("Invalid multicall_address: {}", e))?,
+            None => MULTICALL3_ADDRESS,
+        };
+        let provider = self
+            .provider_manager
+            .get_provider(network)
+            .map_err(|e| anyhow!("Failed to get provider: {}", e))?;
+        let (block, call_block) = Self::pin_block(provider, block).await?;
+
+        // Resolve and encode every call, resolving each function of a contract once
+        let mut functions: HashMap<(Address, &str), alloy::json_abi::Function> = HashMap::new();
+        let mut prepared = Vec::with_capacity(calls.len());
+        for (index, batch_call) in calls.iter().enumerate() {
+            let result = Self::resolve_batch_function(
+                &mut self.abi_resolver,
+                provider,
+                &mut functions,
+                batch_call,
+                block,
+                network,
+            )
+            .await
+            .and_then(|(address, function)| {
+                let calldata = self
+                    .encode_function_call(&function, &batch_call.call.parameters)
+                    .map_err(|e| anyhow!("Failed to encode function call: {}", e))?;
+                Ok((address, function, calldata))
+            });
+
+            match result {
+                Ok(call) => prepared.push(Ok(call)),
+                Err(e) if batch_call.allow_failure => prepared.push(Err(e.to_string())),
+                Err(e) => return Err(anyhow!("Call #{}: {}", index + 1, e)),
+            }
+        }
+
+        let aggregated: Vec<DynSolValue> = calls
+            .iter()
+            .zip(&prepared)
+            .filter_map(|(batch_call, prepared)| {
+                let (address, _, calldata) = prepared.as_ref().ok()?;
+                Some(DynSolValue::Tuple(vec![
+                    DynSolValue::Address(*address),
+                    DynSolValue::Bool(batch_call.allow_failure),
+                    DynSolValue::Bytes(calldata.to_vec()),
+                ]))
+            })
+            .collect();
+        let aggregate3 = alloy::json_abi::Function::parse(AGGREGATE3_SIGNATURE)
+            .map_err(|e| anyhow!("Invalid aggregate3 signature: {}", e))?;
+
+        let returned = if aggregated.is_empty() {
+            Vec::new()
+        } else {
+            let input = aggregate3
+                .abi_encode_input(&[DynSolValue::Array(aggregated)])
+                .map_err(|e| anyhow!("Failed to encode aggregate3 call: {}", e))?;
+            let request = TransactionRequest::default()
+                .to(multicall)
+                .input(Bytes::from(input).into());
+            let output = provider.call(&request).block(block).await.map_err(|e| {
+                anyhow!(
+                    "Batch call failed, possibly because a call without allow_failure reverted: {}",
+                    utils::interpret_rpc_error(&e.to_string())
+                )
+            })?;
+            if output.is_empty() {
+                return Err(anyhow!(
+                    "No Multicall3 contract at {:?} on network '{}'. Set multicall_address in \
+                     the network config",
+                    multicall,
+                    network.unwrap_or("default")
+                ));
+            }
+            Self::aggregate3_results(&aggregate3, &output)?
+        };
+
+        let mut results = self.batch_results(calls, prepared, returned)?;
+        for result in &mut results {
+            result.block = Some(call_block.clone());
+        }
+        Ok(results)
+    }
+
+    /// The `(success, returnData)` results in `aggregate3` output
+    fn aggregate3_results(
+        aggregate3: &alloy::json_abi::Function,
+        output: &[u8],
+    ) -> Result<Vec<DynSolValue>> {
+        match aggregate3.abi_decode_output(output, false) {
+            Ok(values) => match values.into_iter().next() {
+                Some(DynSolValue::Array(results)) => Ok(results),
+                _ => Ok(Vec::new()),
+            },
+            Err(e) => Err(anyhow!("Failed to decode aggregate3 result: {}", e)),
+        }
+    }
+
+    /// Pair `aggregate3` results with the calls of a batch. Calls that could not be prepared
+    /// were left out of the multicall, so they take no result.
+    fn batch_results(
+        &self,
+        calls: &[BatchCall],
+        prepared: Vec<Result<(Address, alloy::json_abi::Function, Bytes), String>>,
+        returned: Vec<DynSolValue>,
+    ) -> Result<Vec<CallResult>> {
+        let mut returned = returned.into_iter();
+        let mut results = Vec::with_capacity(calls.len());
+        for (batch_call, prepared) in calls.iter().zip(prepared) {
+            let (_, function, _) = match prepared {
+                Ok(prepared) => prepared,
+                Err(error) => {
+                    results.push(CallResult {
                         success: false,
-                        result: Some(serde_json::json!({
-                            "raw_result": format!("0x{}", hex::encode(&result_bytes)),
-                            "decode_error": e.to_string()
-                        })),
-                        error: Some(format!("Failed to decode result: {}", e)),
+                        result: None,
+                        error: Some(error),
                         gas_used: None,
                         transaction_hash: None,
-                    }),
+                        block: None,
+                    });
+                    continue;
                 }
-            }
-            Err(e) => Ok(CallResult {
+            };
+            let (success, output) = match returned.next() {
+                Some(DynSolValue::Tuple(values)) => match values.as_slice() {
+                    [DynSolValue::Bool(success), DynSolValue::Bytes(output)] => {
+                        (*success, Bytes::from(output.clone()))
+                    }
+                    _ => return Err(anyhow!("Unexpected aggregate3 result")),
+                },
+                _ => return Err(anyhow!("aggregate3 returned fewer results than calls")),
+            };
+
+            results.push(if success {
+                self.call_result(&function, &output, batch_call.call.include_types)
+            } else {
+                CallResult {
+                    success: false,
+                    result: None,
+                    error: Some(utils::revert_message(&output)),
+                    gas_used: None,
+                    transaction_hash: None,
+                    block: None,
+                }
+            });
+        }
+        Ok(results)
+    }
+
+    /// Resolve the function of a batched call, reusing functions already resolved in the batch
+    async fn resolve_batch_function<'a>(
+        abi_resolver: &mut AbiResolver,
+        provider: &RootProvider<Http<Client>>,
+        functions: &mut HashMap<(Address, &'a str), alloy::json_abi::Function>,
+        batch_call: &'a BatchCall,
+        block: BlockId,
+        network: Option<&str>,
+    ) -> Result<(Address, alloy::json_abi::Function)> {
+        let address = utils::validate_address(&batch_call.contract_address)
+            .map_err(|e| anyhow!("Invalid contract address: {}", e))?;
+        let function_name = batch_call.call.function_name.as_str();
+        utils::validate_function_name(function_name)
+            .map_err(|e| anyhow!("Invalid function name: {}", e))?;
+
+        if let Some(function) = functions.get(&(address, function_name)) {
+            return Ok((address, function.clone()));
+        }
+        let function = Self::resolve_call_function(
+            abi_resolver,
+            provider,
+            address,
+            function_name,
+            block,
+            network,
+        )
+        .await?;
+        functions.insert((address, function_name), function.clone());
+        Ok((address, function))
+    }
+
+    /// Decode the output of a successful call into a result
+    fn call_result(
+        &self,
+        function: &alloy::json_abi::Function,
+        output: &Bytes,
+        include_types: bool,
+    ) -> CallResult {
+        match Self::decode_function_result(function, output, include_types) {
+            Ok(decoded) => CallResult {
+                success: true,
+                result: Some(decoded),
+                error: None,
+                gas_used: None,
+                transaction_hash: None,
+                block: None,
+            },
+            Err(e) => CallResult {
                 success: false,
-                result: None,
-                error: Some(utils::interpret_rpc_error(&e.to_string())),
+                result: Some(serde_json::json!({
+                    "raw_result": format!("0x{}", hex::encode(output)),
+                    "decode_error": e.to_string()
+                })),
+                error: Some(format!("Failed to decode result: {}", e)),
                 gas_used: None,
                 transaction_hash: None,
-            }),
+                block: None,
+            },
+        }
+    }
+
+    /// Find a function in the ABI by bare name, full signature or 4-byte selector.
+    ///
+    /// A bare name must be unambiguous; for overloaded functions the candidate
+    /// signatures are listed so the caller can pick one.
+    fn resolve_function<'a>(
+        abi: &'a JsonAbi,
+        function_name: &str,
+    ) -> Result<&'a alloy::json_abi::Function> {
+        let function_name = function_name.trim();
+        let available_functions: Vec<String> = abi.functions().map(|f| f.signature()).collect();
+
+        if available_functions.is_empty() {
+            return Err(anyhow!(
+                "Function '{}' not found. The contract ABI contains no functions.",
+                function_name
+            ));
+        }
+
+        let selector_hex = function_name
+            .strip_prefix("0x")
+            .or_else(|| function_name.strip_prefix("0X"));
+        if let Some(selector_hex) = selector_hex {
+            let selector = Selector::from_str(selector_hex)
+                .map_err(|_| anyhow!("Invalid function selector: '{}'", function_name))?;
+            return abi
+                .functions()
+                .find(|f| f.selector() == selector)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "No function with selector {} in contract ABI. Available functions: {}",
+                        function_name,
+                        available_functions.join(", ")
+                    )
+                });
+        }
+
+        if function_name.contains('(') {
+            let parsed = alloy::json_abi::Function::parse(function_name)
+                .map_err(|e| anyhow!("Invalid function signature '{}': {}", function_name, e))?;
+            let selector = parsed.selector();
+            return abi
+                .functions()
+                .find(|f| f.selector() == selector)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Function '{}' not found in contract ABI. Available functions: {}",
+                        parsed.signature(),
+                        available_functions.join(", ")
+                    )
+                });
+        }
+
+        let candidates: Vec<&alloy::json_abi::Function> = abi
+            .function(function_name)
+            .map(|overloads| overloads.iter().collect())
+            .unwrap_or_default();
+
+        match candidates.as_slice() {
+            [function] => Ok(function),
+            [] => Err(anyhow!(
+                "Function '{}' not found in contract ABI. Available functions: {}",
+                function_name,
+                available_functions.join(", ")
+            )),
+            overloads => {
+                let signatures: Vec<String> = overloads.iter().map(|f| f.signature()).collect();
+                Err(anyhow!(
+                    "Function '{}' is overloaded. Use a full signature or selector to pick one of: {}",
+                    function_name,
+                    signatures.join(", ")
+                ))
+            }
         }
     }
 
@@ -240,16 +1045,20 @@ impl ContractManager {
                 for (i, param_value) in params.iter().enumerate() {
                     let expected_type = &function.inputs[i].ty;
                     let param_name = &function.inputs[i].name;
-                    let dyn_value = ContractManager::json_to_dyn_sol_value(param_value, expected_type)
-                        .map_err(|e| {
-                            anyhow!(
-                                "Invalid parameter #{} ('{}' of type '{}'): {}",
-                                i + 1,
-                                param_name,
-                                expected_type,
-                                e
-                            )
-                        })?;
+                    let dyn_value = ContractManager::json_to_dyn_sol_value(
+                        param_value,
+                        expected_type,
+                        &function.inputs[i].components,
+                    )
+                    .map_err(|e| {
+                        anyhow!(
+                            "Invalid parameter #{} ('{}' of type '{}'): {}",
+                            i + 1,
+                            param_name,
+                            expected_type,
+                            e
+                        )
+                    })?;
                     dyn_values.push(dyn_value);
                 }
                 dyn_values
@@ -271,7 +1080,7 @@ impl ContractManager {
                             input.name, input.ty, function.name, expected_params.join(", ")
                         ))?;
                     let dyn_value =
-                        Self::json_to_dyn_sol_value(param_value, &input.ty)
+                        Self::json_to_dyn_sol_value(param_value, &input.ty, &input.components)
                             .map_err(|e| {
                                 anyhow!(
                                     "Invalid parameter '{}' of type '{}': {}",
@@ -307,27 +1116,86 @@ impl ContractManager {
         Ok(encoded.into())
     }
 
-    /// Decode function call result
+    /// Decode function call result.
+    ///
+    /// A single return value is returned as-is; multiple return values become an
+    /// object keyed by the ABI output names. When `include_types` is set, every
+    /// value is wrapped as `{"type": ..., "value": ...}`.
     fn decode_function_result(
-        &self,
         function: &alloy::json_abi::Function,
         result_bytes: &Bytes,
+        include_types: bool,
     ) -> Result<Value> {
         if result_bytes.is_empty() {
             return Ok(Value::Null);
         }
 
+        // Without declared outputs (a fragment called without an ABI) return the raw data
+        if function.outputs.is_empty() {
+            return Ok(Value::String(result_bytes.to_string()));
+        }
+
         let decoded = function
             .abi_decode_output(result_bytes, false)
             .map_err(|e| anyhow!("Failed to decode output: {}", e))?;
 
         // Convert DynSolValue to JSON
-        self.dyn_sol_values_to_json(&decoded)
+        Self::dyn_sol_values_to_json(&decoded, &function.outputs, include_types)
     }
 
-    /// Convert JSON value to DynSolValue based on expected Solidity type
-    fn json_to_dyn_sol_value(value: &Value, sol_type: &str) -> Result<DynSolValue> {
+    /// Convert JSON value to DynSolValue based on expected Solidity type.
+    ///
+    /// `components` are the ABI components of the parameter and are only used
+    /// for `tuple` types (including arrays of tuples).
+    fn json_to_dyn_sol_value(
+        value: &Value,
+        sol_type: &str,
+        components: &[Param],
+    ) -> Result<DynSolValue> {
         match sol_type {
+            ty if ty.ends_with(']') => {
+                // Array type (checked first so that e.g. uint256[] is not taken for a uint).
+                // The outermost dimension is the last one, so address[2][] is a dynamic
+                // array of address[2].
+                let open = ty
+                    .rfind('[')
+                    .ok_or_else(|| anyhow!("Invalid array type: {}", ty))?;
+                let element_type = &ty[..open];
+                let size = &ty[open + 1..ty.len() - 1];
+
+                let array = value
+                    .as_array()
+                    .ok_or_else(|| anyhow!("Array parameter of type '{}' must be an array", ty))?;
+
+                let fixed_len = if size.is_empty() {
+                    None
+                } else {
+                    let len = size
+                        .parse::<usize>()
+                        .map_err(|_| anyhow!("Invalid array length in type: {}", ty))?;
+                    if array.len() != len {
+                        return Err(anyhow!(
+                            "Fixed-size array of type '{}' requires exactly {} elements, got {}",
+                            ty,
+                            len,
+                            array.len()
+                        ));
+                    }
+                    Some(len)
+                };
+
+                let mut dyn_array = Vec::new();
+                for (i, element) in array.iter().enumerate() {
+                    let dyn_value = Self::json_to_dyn_sol_value(element, element_type, components)
+                        .map_err(|e| anyhow!("Invalid array element #{}: {}", i, e))?;
+                    dyn_array.push(dyn_value);
+                }
+
+                match fixed_len {
+                    Some(_) => Ok(DynSolValue::FixedArray(dyn_array)),
+                    None => Ok(DynSolValue::Array(dyn_array)),
+                }
+            }
             "address" => {
                 let addr_str = value
                     .as_str()
@@ -336,28 +1204,84 @@ impl ContractManager {
                 Ok(DynSolValue::Address(address))
             }
             ty if ty.starts_with("uint") => {
+                // Extract bit size from type (e.g., uint24 -> 24, uint256 -> 256)
+                let bit_size = Self::parse_int_bit_size(ty, "uint")?;
+
                 let num = match value {
-                    Value::Number(n) => {
-                        if let Some(u) = n.as_u64() {
-                            U256::from(u)
-                        } else {
-                            return Err(anyhow!("Invalid uint value"));
+                    Value::Number(n) => n.as_u64().map(U256::from).ok_or_else(|| {
+                        anyhow!(
+                            "Invalid {} value: {}. Use a string for large or negative numbers",
+                            ty,
+                            n
+                        )
+                    })?,
+                    Value::String(s) => {
+                        let s = s.trim();
+                        match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+                            Some(hex_digits) => U256::from_str_radix(hex_digits, 16),
+                            None => U256::from_str_radix(s, 10),
                         }
+                        .map_err(|_| anyhow!("Invalid {} string: {}", ty, s))?
                     }
-                    Value::String(s) => U256::from_str_radix(s.trim_start_matches("0x"), 16)
-                        .or_else(|_| U256::from_str(s))
-                        .map_err(|_| anyhow!("Invalid uint string: {}", s))?,
                     _ => return Err(anyhow!("Uint must be a number or string")),
                 };
-                
-                // Extract bit size from type (e.g., uint24 -> 24, uint256 -> 256)
-                let bit_size = if ty == "uint" {
-                    256
+
+                if num.bit_len() > bit_size {
+                    return Err(anyhow!(
+                        "Value {} is out of range for {} (maximum is 2^{} - 1)",
+                        num,
+                        ty,
+                        bit_size
+                    ));
+                }
+
+                Ok(DynSolValue::Uint(num, bit_size))
+            }
+            ty if ty.starts_with("int") => {
+                let bit_size = Self::parse_int_bit_size(ty, "int")?;
+
+                let num = match value {
+                    Value::Number(n) => n
+                        .as_i64()
+                        .and_then(|i| I256::try_from(i).ok())
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "Invalid {} value: {}. Use a string for large numbers",
+                                ty,
+                                n
+                            )
+                        })?,
+                    Value::String(s) => {
+                        let s = s.trim();
+                        let unsigned = s.trim_start_matches(['-', '+']);
+                        if unsigned.starts_with("0x") || unsigned.starts_with("0X") {
+                            I256::from_hex_str(&s.replacen("0X", "0x", 1))
+                        } else {
+                            I256::from_dec_str(s)
+                        }
+                        .map_err(|_| anyhow!("Invalid {} string: {}", ty, s))?
+                    }
+                    _ => return Err(anyhow!("Int must be a number or string")),
+                };
+
+                // intN holds values in [-2^(N-1), 2^(N-1) - 1]
+                let bound = U256::from(1) << (bit_size - 1);
+                let in_range = if num.is_negative() {
+                    num.unsigned_abs() <= bound
                 } else {
-                    ty[4..].parse::<usize>().unwrap_or(256)
+                    num.into_raw() < bound
                 };
-                
-                Ok(DynSolValue::Uint(num, bit_size))
+                if !in_range {
+                    return Err(anyhow!(
+                        "Value {} is out of range for {} (range is -2^{} to 2^{} - 1)",
+                        num,
+                        ty,
+                        bit_size - 1,
+                        bit_size - 1
+                    ));
+                }
+
+                Ok(DynSolValue::Int(num, bit_size))
             }
             "string" => {
                 let s = value
@@ -371,120 +1295,287 @@ impl ContractManager {
                     .ok_or_else(|| anyhow!("Bool parameter must be a boolean"))?;
                 Ok(DynSolValue::Bool(b))
             }
+            "function" => {
+                // External function reference: 20-byte address followed by a 4-byte selector
+                let bytes = Self::parse_hex_bytes(value)?;
+                if bytes.len() != 24 {
+                    return Err(anyhow!(
+                        "Function parameter must be exactly 24 bytes (address + selector), got {}",
+                        bytes.len()
+                    ));
+                }
+                Ok(DynSolValue::Function(Function::from_slice(&bytes)))
+            }
             ty if ty.starts_with("bytes") && ty != "bytes" => {
                 // Fixed bytes (e.g., bytes32)
-                let hex_str = value
-                    .as_str()
-                    .ok_or_else(|| anyhow!("Bytes must be a hex string"))?;
-                let bytes = hex::decode(hex_str.trim_start_matches("0x"))
-                    .map_err(|_| anyhow!("Invalid hex string: {}", hex_str))?;
+                let size = ty[5..]
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|size| (1..=32).contains(size))
+                    .ok_or_else(|| anyhow!("Unsupported Solidity type: {}", ty))?;
+                let bytes = Self::parse_hex_bytes(value)?;
+                if bytes.len() > size {
+                    return Err(anyhow!(
+                        "Value for {} is {} bytes long, maximum is {}",
+                        ty,
+                        bytes.len(),
+                        size
+                    ));
+                }
 
-                // Convert to Word (FixedBytes<32>) by padding or truncating
+                // Shorter inputs are right-padded with zeros, as Solidity does for bytesN
                 let mut word_bytes = [0u8; 32];
-                let len = bytes.len().min(32);
-                word_bytes[..len].copy_from_slice(&bytes[..len]);
+                word_bytes[..bytes.len()].copy_from_slice(&bytes);
                 let word = Word::from(word_bytes);
 
-                Ok(DynSolValue::FixedBytes(word, len))
+                Ok(DynSolValue::FixedBytes(word, size))
             }
             "bytes" => {
                 // Dynamic bytes
-                let hex_str = value
-                    .as_str()
-                    .ok_or_else(|| anyhow!("Bytes must be a hex string"))?;
-                let bytes = hex::decode(hex_str.trim_start_matches("0x"))
-                    .map_err(|_| anyhow!("Invalid hex string: {}", hex_str))?;
+                let bytes = Self::parse_hex_bytes(value)?;
                 Ok(DynSolValue::Bytes(bytes))
             }
-            ty if ty.ends_with("[]") => {
-                // Array type
-                let array = value
-                    .as_array()
-                    .ok_or_else(|| anyhow!("Array parameter must be an array"))?;
-                let element_type = &ty[..ty.len() - 2];
-                let mut dyn_array = Vec::new();
-                for element in array {
-                    dyn_array.push(Self::json_to_dyn_sol_value(element, element_type)?);
-                }
-                Ok(DynSolValue::Array(dyn_array))
-            }
-            "tuple" => {
-                // Tuple type - extract parameters from nested object
-                if let Some(params_obj) = value.as_object() {
-                    if let Some(params_array) = params_obj.get("params").and_then(|p| p.as_array()) {
-                        // For now, assume all tuple elements are the appropriate types
-                        // This is a simplified implementation for the Uniswap swap parameters
-                        let mut tuple_values = Vec::new();
-                        
-                        // ExactOutputSingleParams structure:
-                        // tokenIn (address), tokenOut (address), fee (uint24), recipient (address),
-                        // deadline (uint256), amountOut (uint256), amountInMaximum (uint256), sqrtPriceLimitX96 (uint160)
-                        let expected_types = vec![
-                            "address", "address", "uint24", "address", 
-                            "uint256", "uint256", "uint256", "uint160"
-                        ];
-                        
-                        for (i, param_value) in params_array.iter().enumerate() {
-                            if i < expected_types.len() {
-                                tuple_values.push(Self::json_to_dyn_sol_value(param_value, expected_types[i])?);
-                            }
-                        }
-                        
-                        Ok(DynSolValue::Tuple(tuple_values))
-                    } else {
-                        Err(anyhow!("Tuple parameter must contain 'params' array"))
+            "tuple" => Self::json_to_tuple(value, components),
+            _ => Err(anyhow!("Unsupported Solidity type: {}", sol_type)),
+        }
+    }
+
+    /// Extract and validate the bit size of an integer type (e.g. int24 -> 24, uint -> 256)
+    fn parse_int_bit_size(ty: &str, prefix: &str) -> Result<usize> {
+        let digits = &ty[prefix.len()..];
+        if digits.is_empty() {
+            return Ok(256);
+        }
+
+        digits
+            .parse::<usize>()
+            .ok()
+            .filter(|bits| *bits > 0 && *bits <= 256 && bits % 8 == 0)
+            .ok_or_else(|| anyhow!("Unsupported Solidity type: {}", ty))
+    }
+
+    /// Decode a 0x-prefixed (or bare) hex string parameter into bytes
+    fn parse_hex_bytes(value: &Value) -> Result<Vec<u8>> {
+        let hex_str = value
+            .as_str()
+            .ok_or_else(|| anyhow!("Bytes must be a hex string"))?;
+        hex::decode(hex_str.trim_start_matches("0x"))
+            .map_err(|_| anyhow!("Invalid hex string: {}", hex_str))
+    }
+
+    /// Convert JSON value to a tuple DynSolValue using the struct's ABI components.
+    ///
+    /// Accepts either a positional array or an object keyed by component name.
+    fn json_to_tuple(value: &Value, components: &[Param]) -> Result<DynSolValue> {
+        let expected_components: Vec<String> = components
+            .iter()
+            .map(|c| format!("{} {}", c.ty, c.name))
+            .collect();
+
+        match value {
+            Value::Array(items) => {
+                if items.len() != components.len() {
+                    return Err(anyhow!(
+                        "Tuple component count mismatch: expected {} components, got {}.\nExpected components: ({})",
+                        components.len(),
+                        items.len(),
+                        expected_components.join(", ")
+                    ));
+                }
+
+                let mut tuple_values = Vec::new();
+                for (i, (item, component)) in items.iter().zip(components).enumerate() {
+                    let dyn_value =
+                        Self::json_to_dyn_sol_value(item, &component.ty, &component.components)
+                            .map_err(|e| {
+                                anyhow!(
+                                    "Invalid tuple component #{} ('{}' of type '{}'): {}",
+                                    i + 1,
+                                    component.name,
+                                    component.ty,
+                                    e
+                                )
+                            })?;
+                    tuple_values.push(dyn_value);
+                }
+                Ok(DynSolValue::Tuple(tuple_values))
+            }
+            Value::Object(obj) => {
+                // Support the older {"params": [...]} wrapper unless the struct
+                // really has a component called "params"
+                if obj.len() == 1 && !components.iter().any(|c| c.name == "params") {
+                    if let Some(params @ Value::Array(_)) = obj.get("params") {
+                        return Self::json_to_tuple(params, components);
                     }
-                } else {
-                    Err(anyhow!("Tuple parameter must be an object with 'params' array"))
                 }
+
+                if components.iter().any(|c| c.name.is_empty()) {
+                    return Err(anyhow!(
+                        "Tuple has unnamed components and must be provided as an array: ({})",
+                        expected_components.join(", ")
+                    ));
+                }
+
+                if let Some(unknown) = obj
+                    .keys()
+                    .find(|key| !components.iter().any(|c| &c.name == *key))
+                {
+                    return Err(anyhow!(
+                        "Unknown tuple component '{}'. Expected components: ({})",
+                        unknown,
+                        expected_components.join(", ")
+                    ));
+                }
+
+                let mut tuple_values = Vec::new();
+                for component in components {
+                    let item = obj.get(&component.name).ok_or_else(|| {
+                        anyhow!(
+                            "Missing tuple component '{}' of type '{}'. Expected components: ({})",
+                            component.name,
+                            component.ty,
+                            expected_components.join(", ")
+                        )
+                    })?;
+                    let dyn_value =
+                        Self::json_to_dyn_sol_value(item, &component.ty, &component.components)
+                            .map_err(|e| {
+                                anyhow!(
+                                    "Invalid tuple component '{}' of type '{}': {}",
+                                    component.name,
+                                    component.ty,
+                                    e
+                                )
+                            })?;
+                    tuple_values.push(dyn_value);
+                }
+                Ok(DynSolValue::Tuple(tuple_values))
             }
-            _ => Err(anyhow!("Unsupported Solidity type: {}", sol_type)),
+            _ => Err(anyhow!(
+                "Tuple parameter must be an array or an object. Expected components: ({})",
+                expected_components.join(", ")
+            )),
         }
     }
 
-    /// Convert DynSolValue array to JSON
-    fn dyn_sol_values_to_json(&self, values: &[DynSolValue]) -> Result<Value> {
+    /// Convert DynSolValue array to JSON, naming each value after its ABI parameter
+    fn dyn_sol_values_to_json(
+        values: &[DynSolValue],
+        params: &[Param],
+        include_types: bool,
+    ) -> Result<Value> {
         if values.len() == 1 {
             // Single return value
-            Self::dyn_sol_value_to_json(&values[0])
+            let components = params
+                .first()
+                .map(|p| p.components.as_slice())
+                .unwrap_or(&[]);
+            Self::dyn_sol_value_to_json(&values[0], components, include_types)
         } else {
-            // Multiple return values - return as array
-            let mut result = Vec::new();
-            for value in values {
-                result.push(Self::dyn_sol_value_to_json(value)?);
-            }
-            Ok(Value::Array(result))
+            // Multiple return values - same layout as a struct
+            Self::tuple_to_json(values, params, include_types)
         }
     }
 
-    /// Convert single DynSolValue to JSON
-    fn dyn_sol_value_to_json(value: &DynSolValue) -> Result<Value> {
-        match value {
-            DynSolValue::Address(addr) => Ok(Value::String(format!("0x{:x}", addr))),
-            DynSolValue::Uint(num, _) => Ok(Value::String(num.to_string())),
-            DynSolValue::Int(num, _) => Ok(Value::String(num.to_string())),
-            DynSolValue::Bool(b) => Ok(Value::Bool(*b)),
-            DynSolValue::String(s) => Ok(Value::String(s.clone())),
-            DynSolValue::Bytes(bytes) => Ok(Value::String(format!("0x{}", hex::encode(bytes)))),
-            DynSolValue::FixedBytes(bytes, _) => {
-                Ok(Value::String(format!("0x{}", hex::encode(bytes))))
-            }
-            DynSolValue::Array(arr) => {
+    /// Convert single DynSolValue to JSON.
+    ///
+    /// `components` are the ABI components of the value's parameter and are used to
+    /// name the fields of tuples (including tuples nested in arrays).
+    fn dyn_sol_value_to_json(
+        value: &DynSolValue,
+        components: &[Param],
+        include_types: bool,
+    ) -> Result<Value> {
+        let json = match value {
+            DynSolValue::Address(addr) => Value::String(format!("0x{:x}", addr)),
+            DynSolValue::Function(function) => {
+                Value::String(format!("0x{}", hex::encode(function)))
+            }
+            DynSolValue::Uint(num, _) => Value::String(num.to_string()),
+            DynSolValue::Int(num, _) => Value::String(num.to_string()),
+            DynSolValue::Bool(b) => Value::Bool(*b),
+            DynSolValue::String(s) => Value::String(s.clone()),
+            DynSolValue::Bytes(bytes) => Value::String(format!("0x{}", hex::encode(bytes))),
+            DynSolValue::FixedBytes(bytes, size) => {
+                Value::String(format!("0x{}", hex::encode(&bytes[..*size])))
+            }
+            DynSolValue::Array(arr) | DynSolValue::FixedArray(arr) => {
                 let mut json_arr = Vec::new();
                 for item in arr {
-                    json_arr.push(Self::dyn_sol_value_to_json(item)?);
+                    json_arr.push(Self::dyn_sol_value_to_json(
+                        item,
+                        components,
+                        include_types,
+                    )?);
                 }
-                Ok(Value::Array(json_arr))
+                Value::Array(json_arr)
             }
-            DynSolValue::Tuple(tuple) => {
-                let mut json_arr = Vec::new();
-                for item in tuple {
-                    json_arr.push(Self::dyn_sol_value_to_json(item)?);
+            DynSolValue::Tuple(tuple) => Self::tuple_to_json(tuple, components, include_types)?,
+            DynSolValue::CustomStruct {
+                prop_names, tuple, ..
+            } => {
+                let mut obj = serde_json::Map::new();
+                for (name, item) in prop_names.iter().zip(tuple) {
+                    obj.insert(
+                        name.clone(),
+                        Self::dyn_sol_value_to_json(item, &[], include_types)?,
+                    );
                 }
-                Ok(Value::Array(json_arr))
+                Value::Object(obj)
+            }
+        };
+
+        if include_types {
+            let sol_type = value
+                .sol_type_name()
+                .ok_or_else(|| anyhow!("Cannot determine Solidity type of {:?}", value))?;
+            Ok(serde_json::json!({ "type": sol_type, "value": json }))
+        } else {
+            Ok(json)
+        }
+    }
+
+    /// Convert tuple members to a JSON object keyed by component name.
+    ///
+    /// Unnamed components are keyed by their position. If no component is named
+    /// (or the components don't line up with the values), a plain array is returned.
+    fn tuple_to_json(
+        values: &[DynSolValue],
+        components: &[Param],
+        include_types: bool,
+    ) -> Result<Value> {
+        let named =
+            components.len() == values.len() && components.iter().any(|c| !c.name.is_empty());
+
+        if !named {
+            let mut json_arr = Vec::new();
+            for (i, item) in values.iter().enumerate() {
+                let item_components = components
+                    .get(i)
+                    .map(|c| c.components.as_slice())
+                    .unwrap_or(&[]);
+                json_arr.push(Self::dyn_sol_value_to_json(
+                    item,
+                    item_components,
+                    include_types,
+                )?);
             }
-            _ => Err(anyhow!("Unsupported DynSolValue type: {:?}", value)),
+            return Ok(Value::Array(json_arr));
+        }
+
+        let mut obj = serde_json::Map::new();
+        for (i, (item, component)) in values.iter().zip(components).enumerate() {
+            let key = if component.name.is_empty() {
+                i.to_string()
+            } else {
+                component.name.clone()
+            };
+            obj.insert(
+                key,
+                Self::dyn_sol_value_to_json(item, &component.components, include_types)?,
+            );
         }
+        Ok(Value::Object(obj))
     }
 
     pub async fn estimate_gas(
@@ -492,7 +1583,7 @@ impl ContractManager {
         contract_address: &str,
         function_call: &FunctionCall,
         network: Option<&str>,
-    ) -> Result<u64> {
+    ) -> Result<(u64, CallBlock)> {
         // Validate inputs
         let address = utils::validate_address(contract_address)
             .map_err(|e| anyhow!("Invalid contract address for gas estimation: {}", e))?;
@@ -508,40 +1599,41 @@ impl ContractManager {
             .get_provider(network)
             .map_err(|e| anyhow!("Failed to get provider: {}", e))?;
 
-        // If it's a simple ETH transfer (no function call), return base cost
+        // If it's a simple ETH transfer (no function call), return base cost, which is the same
+        // at any block
         if function_call.function_name.is_empty() {
-            return Ok(21000);
+            let block = Self::parse_call_block(function_call.block.as_ref())?;
+            return Ok((21000, Self::requested_block(block)));
         }
 
+        let (block, call_block) = Self::pin_block(provider, function_call.block.as_ref()).await?;
+        let state_override = Self::state_overrides(
+            &mut self.abi_resolver,
+            &mut self.erc20_mappings,
+            provider,
+            function_call.state_overrides.as_ref(),
+            block,
+            network,
+        )
+        .await?;
+
         utils::validate_function_name(&function_call.function_name)
             .map_err(|e| anyhow!("Invalid function name: {}", e))?;
 
-        // Get the ABI and encode the function call
-        let abi = self
-            .abi_resolver
-            .get_abi(contract_address, network)
-            .await
-            .map_err(|e| {
-                anyhow!(
-                    "Could not resolve ABI for gas estimation: {}",
-                    utils::interpret_abi_error(&e.to_string(), contract_address)
-                )
-            })?;
-
-        let function = abi
-            .functions()
-            .find(|f| f.name == function_call.function_name)
-            .ok_or_else(|| {
-                let available_functions: Vec<String> = abi
-                    .functions()
-                    .map(|f| f.name.clone())
-                    .collect();
-                anyhow!("Function '{}' not found in contract ABI for gas estimation. Available functions: {}",
-                    function_call.function_name, available_functions.join(", "))
-            })?;
+        // Resolve the function and encode the call
+        let function = Self::resolve_call_function(
+            &mut self.abi_resolver,
+            provider,
+            address,
+            &function_call.function_name,
+            block,
+            network,
+        )
+        .await
+        .map_err(|e| anyhow!("Cannot resolve function for gas estimation: {}", e))?;
 
         let calldata = self
-            .encode_function_call(function, &function_call.parameters)
+            .encode_function_call(&function, &function_call.parameters)
             .map_err(|e| anyhow!("Failed to encode function call for gas estimation: {}", e))?;
 
         // Build transaction request for gas estimation
@@ -564,228 +1656,1439 @@ impl ContractManager {
         }
 
         // Estimate gas
-        let gas_estimate = provider.estimate_gas(&tx_request).await.map_err(|e| {
+        let mut estimate = provider.estimate_gas(&tx_request).block(block);
+        if let Some(state_override) = &state_override {
+            estimate = estimate.overrides(state_override);
+        }
+        let gas_estimate = estimate.await.map_err(|e| {
             anyhow!(
                 "Gas estimation failed: {}",
                 utils::interpret_rpc_error(&e.to_string())
             )
         })?;
 
-        Ok(gas_estimate)
+        Ok((gas_estimate, call_block))
     }
 
     pub async fn get_contract_events(
-        &self,
+        &mut self,
         contract_address: &str,
-        from_block: Option<u64>,
-        to_block: Option<u64>,
+        from_block: Option<&Value>,
+        to_block: Option<&Value>,
+        event_filter: &EventFilter,
+        pagination: &EventPagination,
         network: Option<&str>,
-    ) -> Result<Vec<EventInfo>> {
+    ) -> Result<EventPage> {
+        let address = utils::validate_address(contract_address)
+            .map_err(|e| anyhow!("Invalid contract address: {}", e))?;
+
+        if let Some(net) = network {
+            let available_networks = self.provider_manager.get_available_networks();
+            utils::validate_network(net, &available_networks)
+                .map_err(|e| anyhow!("Network validation failed: {}", e))?;
+        }
+
+        let limit = pagination.limit.unwrap_or(DEFAULT_EVENT_LIMIT);
+        if limit == 0 || limit > MAX_EVENT_LIMIT {
+            return Err(anyhow!(
+                "Invalid event limit {}. The limit must be between 1 and {}",
+                limit,
+                MAX_EVENT_LIMIT
+            ));
+        }
+
+        // Without a lower bound, scanning starts where the contract was deployed
+        let deployment_block = match (&pagination.cursor, from_block) {
+            (None, None) => self
+                .deployment(address, network)
+                .await
+                .map(|deployment| deployment.block)
+                .unwrap_or(0),
+            _ => 0,
+        };
+
         let provider = self.provider_manager.get_provider(network)?;
-        let address = Address::from_str(contract_address)?;
 
-        let filter = Filter::new()
-            .address(address)
-            .from_block(from_block.unwrap_or(0))
-            .to_block(to_block.unwrap_or(u64::MAX));
+        // Decode with the contract ABI when available, otherwise return raw logs
+        let mut abi = match Self::resolve_contract_abi(
+            &mut self.abi_resolver,
+            provider,
+            address,
+            BlockId::latest(),
+            network,
+        )
+        .await
+        {
+            Ok((resolved, _)) => Some(resolved.abi),
+            Err(e) => {
+                tracing::debug!(
+                    "Returning undecoded events for {}: {}",
+                    contract_address,
+                    utils::interpret_abi_error(&e.to_string(), contract_address)
+                );
+                None
+            }
+        };
+
+        let mut filter = Filter::new().address(address);
+
+        if let Some(event_name) = &event_filter.event {
+            let event = Self::resolve_event(abi.as_ref(), event_name)?;
+            filter.topics = Self::event_topic_filters(&event, event_filter.indexed_args.as_ref())?;
+
+            // Make sure an event given only by signature can still be decoded
+            let abi = abi.get_or_insert_with(JsonAbi::default);
+            let overloads = abi.events.entry(event.name.clone()).or_default();
+            if !overloads.iter().any(|e| e.selector() == event.selector()) {
+                overloads.push(event);
+            }
+        } else if event_filter.indexed_args.is_some() {
+            return Err(anyhow!(
+                "Filtering by indexed arguments requires an event name or signature"
+            ));
+        }
+
+        // A cursor pins the whole remaining range, so later pages don't drift with "latest"
+        let (from_block, skip, to_block) = match &pagination.cursor {
+            Some(cursor) => Self::parse_event_cursor(cursor, &filter)?,
+            None => {
+                let from_block = match from_block {
+                    Some(bound) => Self::resolve_block_bound(provider, bound).await?,
+                    None => deployment_block,
+                };
+                let to_block = match to_block {
+                    Some(bound) => Self::resolve_block_bound(provider, bound).await?,
+                    None => Self::resolve_block_bound(provider, &Value::from("latest")).await?,
+                };
+                (from_block, 0, to_block)
+            }
+        };
+
+        if from_block > to_block {
+            return Err(anyhow!(
+                "Invalid block range: from_block {} is after to_block {}",
+                from_block,
+                to_block
+            ));
+        }
 
-        let logs = provider.get_logs(&filter).await?;
+        let (logs, next_cursor) =
+            Self::fetch_logs_chunked(provider, &filter, from_block, to_block, skip, limit).await?;
 
         let events: Vec<EventInfo> = logs
             .into_iter()
-            .enumerate()
-            .map(|(index, log)| EventInfo {
-                address: format!("0x{:x}", log.address()),
-                topics: log.topics().iter().map(|t| format!("0x{:x}", t)).collect(),
-                data: format!("0x{}", hex::encode(log.data().data.clone())),
-                block_number: log.block_number.unwrap_or_default(),
-                transaction_hash: format!("0x{:x}", log.transaction_hash.unwrap_or_default()),
-                log_index: index as u64,
-                decoded: None, // Would need ABI to decode
+            .map(|log| {
+                let decoded = abi
+                    .as_ref()
+                    .and_then(|abi| Self::decode_event_log(abi, log.topics(), &log.data().data));
+
+                EventInfo {
+                    address: format!("0x{:x}", log.address()),
+                    topics: log.topics().iter().map(|t| format!("0x{:x}", t)).collect(),
+                    data: format!("0x{}", hex::encode(log.data().data.clone())),
+                    block_number: log.block_number.unwrap_or_default(),
+                    transaction_hash: format!("0x{:x}", log.transaction_hash.unwrap_or_default()),
+                    log_index: log.log_index.unwrap_or_default(),
+                    event_name: decoded.as_ref().map(|(event, _)| event.name.clone()),
+                    signature: decoded.as_ref().map(|(event, _)| event.signature()),
+                    decoded: decoded.map(|(_, args)| args),
+                }
             })
             .collect();
 
-        Ok(events)
+        Ok(EventPage {
+            events,
+            from_block,
+            to_block,
+            next_cursor,
+        })
     }
 
-    #[allow(dead_code)]
-    pub async fn get_transaction_history(
-        &self,
-        _contract_address: &str,
-        _limit: Option<usize>,
-        _network: Option<&str>,
-    ) -> Result<Vec<TransactionInfo>> {
-        // This would require indexing service integration
-        // For now, return empty list
-        Ok(vec![])
-    }
+    /// Fetch logs for `[from_block, to_block]` in block-range chunks.
+    ///
+    /// Chunks are halved whenever the provider rejects a range as too large or
+    /// too expensive, and grow back after successful requests. Fetching stops
+    /// once `limit` logs are collected or after a bounded number of requests;
+    /// in either case a cursor for the remaining range is returned. `skip` is
+    /// the number of logs in `from_block` already returned by a previous page.
+    async fn fetch_logs_chunked(
+        provider: &RootProvider<Http<Client>>,
+        filter: &Filter,
+        from_block: u64,
+        to_block: u64,
+        mut skip: usize,
+        limit: usize,
+    ) -> Result<(Vec<Log>, Option<String>)> {
+        let mut logs: Vec<Log> = Vec::new();
+        let mut chunk_size = LOG_CHUNK_SIZE;
+        let mut start = from_block;
+        let mut requests = 0;
 
-    pub async fn simulate_transaction(
-        &mut self,
-        contract_address: &str,
-        function_call: &FunctionCall,
-        network: Option<&str>,
-    ) -> Result<CallResult> {
-        // Validate inputs
-        let address = utils::validate_address(contract_address)
-            .map_err(|e| anyhow!("Invalid contract address for simulation: {}", e))?;
+        while start <= to_block {
+            if requests == MAX_LOG_REQUESTS_PER_PAGE {
+                let cursor = Self::format_event_cursor(start, skip, to_block, filter);
+                return Ok((logs, Some(cursor)));
+            }
+            requests += 1;
 
-        utils::validate_function_name(&function_call.function_name)
-            .map_err(|e| anyhow!("Invalid function name: {}", e))?;
+            let end = start.saturating_add(chunk_size - 1).min(to_block);
+            let chunk_filter = filter.clone().from_block(start).to_block(end);
 
-        if let Some(net) = network {
-            let available_networks = self.provider_manager.get_available_networks();
-            utils::validate_network(net, &available_networks)
-                .map_err(|e| anyhow!("Network validation failed: {}", e))?;
-        }
+            let chunk = match provider.get_logs(&chunk_filter).await {
+                Ok(chunk) => chunk,
+                Err(e) if end > start && utils::is_log_range_error(&e.to_string()) => {
+                    // Half the rejected range; end > start so this is at least one block
+                    chunk_size = (end - start).div_ceil(2);
+                    tracing::debug!(
+                        "Log query for blocks {}-{} rejected ({}), retrying with {} blocks",
+                        start,
+                        end,
+                        e,
+                        chunk_size
+                    );
+                    continue;
+                }
+                Err(e) => {
+                    return Err(anyhow!(
+                        "Failed to fetch logs for blocks {}-{}: {}",
+                        start,
+                        end,
+                        utils::interpret_rpc_error(&e.to_string())
+                    ))
+                }
+            };
 
-        let provider = self
-            .provider_manager
-            .get_provider(network)
-            .map_err(|e| anyhow!("Failed to get provider: {}", e))?;
+            // Logs come back ordered, so the ones already returned for `start` lead the chunk
+            let skipped = std::mem::take(&mut skip);
+            let chunk: Vec<Log> = chunk.into_iter().skip(skipped).collect();
 
-        // Get the ABI and encode the function call
-        let abi = match self.abi_resolver.get_abi(contract_address, network).await {
-            Ok(abi) => abi,
-            Err(e) => {
-                return Ok(CallResult {
-                    success: false,
-                    result: None,
-                    error: Some(utils::interpret_abi_error(&e.to_string(), contract_address)),
-                    gas_used: None,
-                    transaction_hash: None,
-                });
+            let remaining = limit - logs.len();
+            if chunk.len() > remaining {
+                let next_block = chunk[remaining].block_number.unwrap_or(start);
+                let mut returned_in_block = chunk[..remaining]
+                    .iter()
+                    .filter(|log| log.block_number == Some(next_block))
+                    .count();
+                // Still inside the block a previous page stopped in
+                if next_block == start {
+                    returned_in_block += skipped;
+                }
+                logs.extend(chunk.into_iter().take(remaining));
+                let cursor =
+                    Self::format_event_cursor(next_block, returned_in_block, to_block, filter);
+                return Ok((logs, Some(cursor)));
             }
-        };
 
-        let function = abi
-            .functions()
-            .find(|f| f.name == function_call.function_name)
-            .ok_or_else(|| {
-                let available_functions: Vec<String> = abi
-                    .functions()
-                    .map(|f| f.name.clone())
-                    .collect();
-                anyhow!("Function '{}' not found in contract ABI for simulation. Available functions: {}",
-                    function_call.function_name, available_functions.join(", "))
-            })?;
+            logs.extend(chunk);
+            start = end + 1;
+            chunk_size = chunk_size.saturating_mul(2).min(LOG_CHUNK_SIZE);
+        }
 
-        let calldata = match self.encode_function_call(function, &function_call.parameters) {
-            Ok(data) => data,
-            Err(e) => {
-                return Ok(CallResult {
-                    success: false,
-                    result: None,
-                    error: Some(format!("Failed to encode function call: {}", e)),
-                    gas_used: None,
-                    transaction_hash: None,
-                });
+        Ok((logs, None))
+    }
+
+    /// Resolve a block range bound, turning block tags into concrete block numbers
+    async fn resolve_block_bound(
+        provider: &RootProvider<Http<Client>>,
+        bound: &Value,
+    ) -> Result<u64> {
+        match utils::parse_block_bound(bound)? {
+            BlockNumberOrTag::Number(number) => Ok(number),
+            BlockNumberOrTag::Earliest => Ok(0),
+            BlockNumberOrTag::Latest => provider.get_block_number().await.map_err(|e| {
+                anyhow!(
+                    "Failed to fetch latest block number: {}",
+                    utils::interpret_rpc_error(&e.to_string())
+                )
+            }),
+            tag => {
+                let block = provider
+                    .get_block_by_number(tag, BlockTransactionsKind::Hashes)
+                    .await
+                    .map_err(|e| {
+                        anyhow!(
+                            "Failed to fetch {} block: {}",
+                            tag,
+                            utils::interpret_rpc_error(&e.to_string())
+                        )
+                    })?
+                    .ok_or_else(|| anyhow!("The node has no {} block", tag))?;
+                Ok(block.header.number)
             }
-        };
+        }
+    }
 
-        // Build transaction request for simulation
-        let mut tx_request = TransactionRequest::default()
-            .to(address)
-            .input(calldata.into());
+    /// Encode the resume point of an event query as `<block>:<skip>:<to_block>:<filter>`, where
+    /// `<filter>` is a digest of the address and topics the page was fetched with
+    fn format_event_cursor(block: u64, skip: usize, to_block: u64, filter: &Filter) -> String {
+        format!(
+            "{}:{}:{}:{}",
+            block,
+            skip,
+            to_block,
+            Self::event_filter_digest(filter)
+        )
+    }
 
-        // Set from address if provided
-        if let Some(from_str) = &function_call.from {
-            match utils::validate_address(from_str) {
-                Ok(from_address) => {
-                    tx_request = tx_request.from(from_address);
-                }
-                Err(e) => {
-                    return Ok(CallResult {
-                        success: false,
-                        result: None,
-                        error: Some(format!("Invalid 'from' address for simulation: {}", e)),
+    /// Decode a cursor produced by [`Self::format_event_cursor`] into `(block, skip, to_block)`.
+    /// `skip` only makes sense for the filter the cursor was issued for, so any other filter is
+    /// rejected rather than silently skipping or repeating logs.
+    fn parse_event_cursor(cursor: &str, filter: &Filter) -> Result<(u64, usize, u64)> {
+        let invalid = || anyhow!("Invalid event cursor: '{}'", cursor);
+        let mut parts = cursor.trim().split(':');
+        let block = parts
+            .next()
+            .and_then(|p| p.parse::<u64>().ok())
+            .ok_or_else(invalid)?;
+        let skip = parts
+            .next()
+            .and_then(|p| p.parse::<usize>().ok())
+            .ok_or_else(invalid)?;
+        let to_block = parts
+            .next()
+            .and_then(|p| p.parse::<u64>().ok())
+            .ok_or_else(invalid)?;
+        let digest = parts.next().ok_or_else(invalid)?;
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        if digest != Self::event_filter_digest(filter) {
+            return Err(anyhow!(
+                "The event cursor was issued for a different contract, event or indexed_args. \
+                 Pass the same filters as the first page, or start again without a cursor"
+            ));
+        }
+        Ok((block, skip, to_block))
+    }
+
+    /// Short digest of the addresses and topics of a log filter, independent of set order
+    fn event_filter_digest(filter: &Filter) -> String {
+        let mut addresses: Vec<&Address> = filter.address.iter().collect();
+        addresses.sort();
+        let mut preimage: Vec<u8> = addresses.iter().flat_map(|a| a.to_vec()).collect();
+        for topic in &filter.topics {
+            let mut values: Vec<&B256> = topic.iter().collect();
+            values.sort();
+            // The count separates the positions, so topics can't shift between them
+            preimage.push(values.len() as u8);
+            preimage.extend(values.iter().flat_map(|v| v.to_vec()));
+        }
+        hex::encode(&keccak256(preimage)[..4])
+    }
+
+    /// Find an event by bare name or signature.
+    ///
+    /// A signature is matched against the ABI first so that indexed flags and
+    /// argument names come from the contract. Without an ABI match, the parsed
+    /// signature is used as-is; mark arguments `indexed` in it to filter on them.
+    fn resolve_event(abi: Option<&JsonAbi>, event_name: &str) -> Result<Event> {
+        let event_name = event_name.trim();
+
+        if event_name.contains('(') {
+            let parsed = Event::parse(event_name)
+                .map_err(|e| anyhow!("Invalid event signature '{}': {}", event_name, e))?;
+            let from_abi = abi.and_then(|abi| {
+                abi.events()
+                    .find(|e| !e.anonymous && e.selector() == parsed.selector())
+            });
+            return Ok(from_abi.cloned().unwrap_or(parsed));
+        }
+
+        let abi = abi.ok_or_else(|| {
+            anyhow!(
+                "Event '{}' can't be resolved without the contract ABI. Use a full signature such as 'Transfer(address indexed from, address indexed to, uint256 value)'",
+                event_name
+            )
+        })?;
+
+        match abi.event(event_name).map(Vec::as_slice).unwrap_or_default() {
+            [event] => Ok(event.clone()),
+            [] => {
+                let available_events: Vec<String> = abi.events().map(|e| e.signature()).collect();
+                Err(anyhow!(
+                    "Event '{}' not found in contract ABI. Available events: {}",
+                    event_name,
+                    available_events.join(", ")
+                ))
+            }
+            overloads => {
+                let signatures: Vec<String> = overloads.iter().map(|e| e.signature()).collect();
+                Err(anyhow!(
+                    "Event '{}' is overloaded. Use a full signature to pick one of: {}",
+                    event_name,
+                    signatures.join(", ")
+                ))
+            }
+        }
+    }
+
+    /// Build the log topic filters for an event and its indexed argument values.
+    ///
+    /// `indexed_args` is an object keyed by argument name (or position for unnamed
+    /// arguments). An array of values matches any of them.
+    fn event_topic_filters(event: &Event, indexed_args: Option<&Value>) -> Result<[Topic; 4]> {
+        let mut topics: [Topic; 4] = Default::default();
+
+        // Anonymous events have no signature topic, so indexed arguments start at topic 0
+        let offset = if event.anonymous {
+            0
+        } else {
+            topics[0] = event.selector().into();
+            1
+        };
+
+        let Some(indexed_args) = indexed_args else {
+            return Ok(topics);
+        };
+        let indexed_args = indexed_args.as_object().ok_or_else(|| {
+            anyhow!("Indexed argument filters must be an object keyed by argument name")
+        })?;
+
+        let indexed_params: Vec<(usize, &EventParam)> = event
+            .inputs
+            .iter()
+            .enumerate()
+            .filter(|(_, param)| param.indexed)
+            .collect();
+        let indexed_names: Vec<String> = indexed_params
+            .iter()
+            .map(|(i, param)| {
+                if param.name.is_empty() {
+                    i.to_string()
+                } else {
+                    param.name.clone()
+                }
+            })
+            .collect();
+
+        for (key, value) in indexed_args {
+            let position = indexed_names
+                .iter()
+                .position(|name| name == key)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "'{}' is not an indexed argument of {}. Indexed arguments: {}",
+                        key,
+                        event.signature(),
+                        indexed_names.join(", ")
+                    )
+                })?;
+            let param = indexed_params[position].1;
+
+            let values = match value {
+                Value::Array(values) => values.iter().collect(),
+                value => vec![value],
+            };
+            if values.is_empty() {
+                return Err(anyhow!("No values given for indexed argument '{}'", key));
+            }
+
+            let mut topic_values = Vec::new();
+            for value in values {
+                let topic = Self::indexed_arg_topic(value, param)
+                    .map_err(|e| anyhow!("Invalid filter value for '{}': {}", key, e))?;
+                topic_values.push(topic);
+            }
+            topics[offset + position] = topic_values.into();
+        }
+
+        Ok(topics)
+    }
+
+    /// Encode an indexed argument value as the topic a log would carry for it.
+    ///
+    /// Strings and bytes are hashed; arrays and structs must be given as
+    /// `{"hash": "0x..."}`, the same form they are decoded to.
+    fn indexed_arg_topic(value: &Value, param: &EventParam) -> Result<B256> {
+        if let Some(hash) = value.get("hash") {
+            let hash = hash
+                .as_str()
+                .ok_or_else(|| anyhow!("Topic hash must be a hex string"))?;
+            return B256::from_str(hash).map_err(|_| anyhow!("Invalid topic hash: {}", hash));
+        }
+
+        match param.ty.as_str() {
+            "string" => {
+                let s = value
+                    .as_str()
+                    .ok_or_else(|| anyhow!("String parameter must be a string"))?;
+                Ok(keccak256(s))
+            }
+            "bytes" => Ok(keccak256(Self::parse_hex_bytes(value)?)),
+            ty if ty.ends_with(']') || ty.starts_with("tuple") => Err(anyhow!(
+                "Indexed {} arguments are stored as a hash; filter with {{\"hash\": \"0x...\"}}",
+                ty
+            )),
+            ty => Self::json_to_dyn_sol_value(value, ty, &param.components)?
+                .as_word()
+                .ok_or_else(|| anyhow!("Cannot encode {} value as a topic", ty)),
+        }
+    }
+
+    /// Match a log against the events in the ABI and decode its arguments.
+    ///
+    /// Non-anonymous events are matched on topic 0. Anonymous events have no
+    /// signature topic, so they are tried in order and the first one whose
+    /// indexed count and data layout fit the log wins. Returns `None` when no
+    /// event matches.
+    fn decode_event_log<'a>(
+        abi: &'a JsonAbi,
+        topics: &[B256],
+        data: &[u8],
+    ) -> Option<(&'a Event, Value)> {
+        let signature_matches = topics.first().into_iter().flat_map(|topic0| {
+            abi.events()
+                .filter(move |event| !event.anonymous && event.selector() == *topic0)
+        });
+        let anonymous = abi.events().filter(|event| event.anonymous);
+
+        signature_matches.chain(anonymous).find_map(|event| {
+            let decoded = event
+                .decode_log_parts(topics.iter().copied(), data, true)
+                .ok()?;
+            let args = Self::event_args_to_json(event, decoded).ok()?;
+            Some((event, args))
+        })
+    }
+
+    /// Convert decoded event values into an object keyed by argument name.
+    ///
+    /// Indexed arguments of dynamic types (strings, bytes, arrays, structs) are
+    /// only stored as their keccak256 hash in the topic, so they are reported
+    /// as `{"hash": "0x..."}`.
+    fn event_args_to_json(event: &Event, decoded: DecodedEvent) -> Result<Value> {
+        let mut indexed = decoded.indexed.into_iter();
+        let mut body = decoded.body.into_iter();
+        let mut args = serde_json::Map::new();
+
+        for (i, param) in event.inputs.iter().enumerate() {
+            let value = if param.indexed {
+                indexed.next()
+            } else {
+                body.next()
+            }
+            .ok_or_else(|| anyhow!("Missing value for event argument #{}", i))?;
+
+            let json = match value {
+                DynSolValue::FixedBytes(hash, 32) if param.indexed && param.ty != "bytes32" => {
+                    serde_json::json!({ "hash": format!("0x{:x}", hash) })
+                }
+                value => Self::dyn_sol_value_to_json(&value, &param.components, false)?,
+            };
+
+            let key = if param.name.is_empty() {
+                i.to_string()
+            } else {
+                param.name.clone()
+            };
+            args.insert(key, json);
+        }
+
+        Ok(Value::Object(args))
+    }
+
+    /// Register an ABI for a contract, given as JSON or human-readable signatures
+    pub async fn register_abi(
+        &mut self,
+        address: &str,
+        abi: &Value,
+        network: Option<&str>,
+    ) -> Result<StoredAbi> {
+        let address = self.validate_abi_target(address, network)?;
+        let abi = abi::parse_abi(abi).map_err(|e| anyhow!("Invalid ABI: {}", e))?;
+
+        self.abi_resolver
+            .add_manual_abi(&address, network, abi)
+            .await
+    }
+
+    /// Every stored ABI, optionally limited to one network
+    pub async fn list_abis(&self, network: Option<&str>) -> Vec<StoredAbi> {
+        let mut stored = self.abi_resolver.list_abis().await;
+        if let Some(network) = network {
+            stored.retain(|entry| entry.network == network);
+        }
+        stored
+    }
+
+    /// The stored ABI for a contract, as JSON or human-readable signatures
+    pub async fn show_abi(
+        &self,
+        address: &str,
+        network: Option<&str>,
+        human_readable: bool,
+    ) -> Result<Value> {
+        let address = self.validate_abi_target(address, network)?;
+        let resolved = self
+            .abi_resolver
+            .stored_abi(&address, network)
+            .await
+            .ok_or_else(|| anyhow!("No ABI stored for {}", address))?;
+
+        let abi = if human_readable {
+            serde_json::to_value(abi::to_human_readable(&resolved.abi))?
+        } else {
+            serde_json::to_value(&resolved.abi)?
+        };
+        Ok(serde_json::json!({
+            "address": address,
+            "provider": resolved.provider,
+            "abi": abi,
+        }))
+    }
+
+    /// Delete a contract's manual registration and cached ABI
+    pub async fn delete_abi(&mut self, address: &str, network: Option<&str>) -> Result<()> {
+        let address = self.validate_abi_target(address, network)?;
+        if !self.abi_resolver.has_abi(&address, network).await {
+            return Err(anyhow!("No ABI stored for {}", address));
+        }
+        self.abi_resolver.remove_abi(&address, network).await
+    }
+
+    /// Clear cached ABIs, and manual registrations too if asked
+    pub async fn clear_abi_cache(&mut self, include_manual: bool) -> Result<()> {
+        self.abi_resolver.clear_cache(include_manual).await
+    }
+
+    /// A source file of a verified contract, or its file list when no file is named
+    pub async fn get_contract_source(
+        &mut self,
+        address: &str,
+        file: Option<&str>,
+        network: Option<&str>,
+    ) -> Result<Value> {
+        let address = self.validate_abi_target(address, network)?;
+        let source = self
+            .abi_resolver
+            .source_code(&address, network)
+            .await
+            .map_err(|e| anyhow!("No verified source for {}: {}", address, e))?;
+
+        let Some(file) = file else {
+            return Ok(serde_json::to_value(&source.info)?);
+        };
+        let (path, content) = source.file(file)?;
+        Ok(serde_json::json!({
+            "address": address,
+            "contract_name": source.info.contract_name,
+            "file": path,
+            "content": content,
+        }))
+    }
+
+    /// Read contract storage by raw slot, or by a state variable path decoded with the
+    /// contract's storage layout. For proxies the implementation's layout is used.
+    pub async fn read_storage(
+        &mut self,
+        address: &str,
+        slot: Option<&str>,
+        variable: Option<&str>,
+        storage_layout: Option<&Value>,
+        block: Option<&Value>,
+        network: Option<&str>,
+    ) -> Result<Value> {
+        let contract_address = utils::validate_address(address)
+            .map_err(|e| anyhow!("Invalid contract address: {}", e))?;
+        if let Some(net) = network {
+            let available_networks = self.provider_manager.get_available_networks();
+            utils::validate_network(net, &available_networks)
+                .map_err(|e| anyhow!("Network validation failed: {}", e))?;
+        }
+        let provider = self.provider_manager.get_provider(network)?;
+        // A variable can take many reads, which must all see the same block
+        let (block, call_block) = Self::pin_block(provider, block).await?;
+        let mut reader = storage::SlotReader::new(provider, contract_address, block);
+
+        match (slot, variable) {
+            (Some(slot), None) => {
+                let slot = storage::parse_slot(slot)?;
+                let value = reader
+                    .read(slot)
+                    .await
+                    .map_err(|e| anyhow!(utils::interpret_rpc_error(&e.to_string())))?;
+                Ok(serde_json::json!({
+                    "address": format!("{:?}", contract_address),
+                    "slot": format!("{:#x}", slot),
+                    "value": value,
+                    "block": call_block,
+                }))
+            }
+            (None, Some(variable)) => {
+                let layout = match storage_layout {
+                    Some(layout) => layout.clone(),
+                    None => Self::resolve_contract_abi(
+                        &mut self.abi_resolver,
+                        provider,
+                        contract_address,
+                        block,
+                        network,
+                    )
+                    .await
+                    .ok()
+                    .and_then(|(resolved, _)| resolved.storage_layout)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "No storage layout available for {:?}. Layouts come from Sourcify \
+                             metadata or Foundry/Hardhat artifacts built with \
+                             `--extra-output storageLayout`; pass one as storage_layout, or \
+                             read a raw slot instead",
+                            contract_address
+                        )
+                    })?,
+                };
+                let layout = storage::StorageLayout::from_value(&layout)?;
+                let location = layout.locate(variable, &mut reader).await?;
+                let type_label = layout.type_label(&location.type_id);
+                let (slot, offset) = (location.slot, location.offset);
+                let value = layout.decode(location, &mut reader).await?;
+                Ok(serde_json::json!({
+                    "address": format!("{:?}", contract_address),
+                    "variable": variable,
+                    "type": type_label,
+                    "slot": format!("{:#x}", slot),
+                    "offset": offset,
+                    "value": value,
+                    "block": call_block,
+                }))
+            }
+            _ => Err(anyhow!("Provide exactly one of slot or variable")),
+        }
+    }
+
+    /// Storage slots of an ERC-20 holder's balance and, given a spender, of the allowance the
+    /// holder gave it, for writing them with state overrides
+    pub async fn get_erc20_storage_slots(
+        &mut self,
+        token: &str,
+        holder: &str,
+        spender: Option<&str>,
+        network: Option<&str>,
+    ) -> Result<Value> {
+        let token =
+            utils::validate_address(token).map_err(|e| anyhow!("Invalid token address: {}", e))?;
+        let holder = utils::validate_address(holder)
+            .map_err(|e| anyhow!("Invalid holder address: {}", e))?;
+        let spender = spender
+            .map(|spender| {
+                utils::validate_address(spender)
+                    .map_err(|e| anyhow!("Invalid spender address: {}", e))
+            })
+            .transpose()?;
+        if let Some(net) = network {
+            let available_networks = self.provider_manager.get_available_networks();
+            utils::validate_network(net, &available_networks)
+                .map_err(|e| anyhow!("Network validation failed: {}", e))?;
+        }
+        let provider = self.provider_manager.get_provider(network)?;
+
+        let mut entries = vec![Erc20Mapping::Balance { holder }];
+        if let Some(spender) = spender {
+            entries.push(Erc20Mapping::Allowance {
+                owner: holder,
+                spender,
+            });
+        }
+        let mut slots = serde_json::Map::new();
+        slots.insert("token".to_string(), Value::from(format!("{:?}", token)));
+        for entry in entries {
+            let mapping = Self::erc20_mapping(
+                &mut self.abi_resolver,
+                &mut self.erc20_mappings,
+                provider,
+                token,
+                entry,
+                BlockId::latest(),
+                network,
+            )
+            .await?;
+            slots.insert(
+                entry.name().to_string(),
+                serde_json::json!({
+                    "mapping_slot": mapping.slot,
+                    "layout": mapping.layout,
+                    "slot": B256::from(entry.slot(mapping)),
+                }),
+            );
+        }
+        Ok(Value::Object(slots))
+    }
+
+    /// Validate the address and network of an ABI management request
+    fn validate_abi_target(&self, address: &str, network: Option<&str>) -> Result<String> {
+        let address = utils::validate_address(address)
+            .map_err(|e| anyhow!("Invalid contract address: {}", e))?;
+
+        if let Some(net) = network {
+            let available_networks = self.provider_manager.get_available_networks();
+            utils::validate_network(net, &available_networks)
+                .map_err(|e| anyhow!("Network validation failed: {}", e))?;
+        }
+
+        Ok(format!("{:?}", address))
+    }
+
+    #[allow(dead_code)]
+    pub async fn get_transaction_history(
+        &self,
+        _contract_address: &str,
+        _limit: Option<usize>,
+        _network: Option<&str>,
+    ) -> Result<Vec<TransactionInfo>> {
+        // This would require indexing service integration
+        // For now, return empty list
+        Ok(vec![])
+    }
+
+    /// Simulate a transaction with eth_call. With `trace`, the call tree from `debug_traceCall`
+    /// is included, showing which call reverted and why.
+    pub async fn simulate_transaction(
+        &mut self,
+        contract_address: &str,
+        function_call: &FunctionCall,
+        trace: bool,
+        network: Option<&str>,
+    ) -> Result<CallResult> {
+        // Validate inputs
+        let address = utils::validate_address(contract_address)
+            .map_err(|e| anyhow!("Invalid contract address for simulation: {}", e))?;
+
+        utils::validate_function_name(&function_call.function_name)
+            .map_err(|e| anyhow!("Invalid function name: {}", e))?;
+
+        if let Some(net) = network {
+            let available_networks = self.provider_manager.get_available_networks();
+            utils::validate_network(net, &available_networks)
+                .map_err(|e| anyhow!("Network validation failed: {}", e))?;
+        }
+
+        let provider = self
+            .provider_manager
+            .get_provider(network)
+            .map_err(|e| anyhow!("Failed to get provider: {}", e))?;
+
+        // Resolve the function as of the simulation's block and encode the call
+        let (block, call_block) =
+            match Self::pin_block(provider, function_call.block.as_ref()).await {
+                Ok(pinned) => pinned,
+                Err(e) => {
+                    return Ok(CallResult {
+                        success: false,
+                        result: None,
+                        error: Some(e.to_string()),
                         gas_used: None,
                         transaction_hash: None,
+                        block: None,
                     });
                 }
+            };
+        let function = match Self::resolve_call_function(
+            &mut self.abi_resolver,
+            provider,
+            address,
+            &function_call.function_name,
+            block,
+            network,
+        )
+        .await
+        {
+            Ok(function) => function,
+            Err(e) => {
+                return Ok(CallResult {
+                    success: false,
+                    result: None,
+                    error: Some(format!("Cannot resolve function for simulation: {}", e)),
+                    gas_used: None,
+                    transaction_hash: None,
+                    block: None,
+                });
+            }
+        };
+
+        let calldata = match self.encode_function_call(&function, &function_call.parameters) {
+            Ok(data) => data,
+            Err(e) => {
+                return Ok(CallResult {
+                    success: false,
+                    result: None,
+                    error: Some(format!("Failed to encode function call: {}", e)),
+                    gas_used: None,
+                    transaction_hash: None,
+                    block: None,
+                });
+            }
+        };
+
+        // Build transaction request for simulation
+        let mut tx_request = TransactionRequest::default()
+            .to(address)
+            .input(calldata.into());
+
+        // Set from address if provided
+        if let Some(from_str) = &function_call.from {
+            match utils::validate_address(from_str) {
+                Ok(from_address) => {
+                    tx_request = tx_request.from(from_address);
+                }
+                Err(e) => {
+                    return Ok(CallResult {
+                        success: false,
+                        result: None,
+                        error: Some(format!("Invalid 'from' address for simulation: {}", e)),
+                        gas_used: None,
+                        transaction_hash: None,
+                        block: None,
+                    });
+                }
+            }
+        }
+
+        // Set value if provided
+        if let Some(value_str) = &function_call.value {
+            match utils::validate_hex_value(value_str) {
+                Ok(value) => {
+                    tx_request = tx_request.value(value);
+                }
+                Err(e) => {
+                    return Ok(CallResult {
+                        success: false,
+                        result: None,
+                        error: Some(format!("Invalid transaction value for simulation: {}", e)),
+                        gas_used: None,
+                        transaction_hash: None,
+                        block: None,
+                    });
+                }
+            }
+        }
+
+        let state_override = Self::state_overrides(
+            &mut self.abi_resolver,
+            &mut self.erc20_mappings,
+            provider,
+            function_call.state_overrides.as_ref(),
+            block,
+            network,
+        )
+        .await?;
+
+        // Trace first, so that the call tree is there when the transaction reverts
+        let trace = if trace {
+            let trace = Self::trace_call(
+                &mut self.abi_resolver,
+                provider,
+                &tx_request,
+                block,
+                state_override.as_ref(),
+                network,
+            )
+            .await;
+            Some(match trace {
+                Ok(trace) => serde_json::to_value(trace)?,
+                Err(e) => serde_json::json!({ "error": e.to_string() }),
+            })
+        } else {
+            None
+        };
+        let with_trace = |mut result: Value| {
+            if let (Some(trace), Some(object)) = (&trace, result.as_object_mut()) {
+                object.insert("trace".to_string(), trace.clone());
+            }
+            result
+        };
+
+        // First, estimate gas for the transaction
+        let mut estimate = provider.estimate_gas(&tx_request).block(block);
+        let mut call = provider.call(&tx_request).block(block);
+        if let Some(state_override) = &state_override {
+            estimate = estimate.overrides(state_override);
+            call = call.overrides(state_override);
+        }
+        let gas_estimate = match estimate.await {
+            Ok(gas) => Some(gas),
+            Err(e) => {
+                // If gas estimation fails, the transaction would likely fail
+                let friendly_error = utils::interpret_rpc_error(&e.to_string());
+                return Ok(CallResult {
+                    success: false,
+                    result: Some(with_trace(serde_json::json!({
+                        "simulated": true,
+                        "gas_estimation_failed": true,
+                        "error": friendly_error
+                    }))),
+                    error: Some(format!(
+                        "Gas estimation failed (transaction would likely revert): {}",
+                        friendly_error
+                    )),
+                    gas_used: None,
+                    transaction_hash: None,
+                    block: Some(call_block),
+                });
+            }
+        };
+
+        // Simulate with eth_call
+        match call.await {
+            Ok(result_bytes) => {
+                // Try to decode the result
+                let decoded_result = Self::decode_function_result(
+                    &function,
+                    &result_bytes,
+                    function_call.include_types,
+                )
+                .unwrap_or_else(|_| {
+                    serde_json::json!({
+                        "raw_result": format!("0x{}", hex::encode(&result_bytes))
+                    })
+                });
+
+                Ok(CallResult {
+                    success: true,
+                    result: Some(with_trace(serde_json::json!({
+                        "simulated": true,
+                        "result": decoded_result,
+                        "would_succeed": true
+                    }))),
+                    error: None,
+                    gas_used: gas_estimate,
+                    transaction_hash: None,
+                    block: Some(call_block),
+                })
+            }
+            Err(e) => {
+                let friendly_error = utils::interpret_rpc_error(&e.to_string());
+                Ok(CallResult {
+                    success: false,
+                    result: Some(with_trace(serde_json::json!({
+                        "simulated": true,
+                        "would_succeed": false,
+                        "revert_reason": friendly_error
+                    }))),
+                    error: Some(format!("Transaction simulation failed: {}", friendly_error)),
+                    gas_used: gas_estimate,
+                    transaction_hash: None,
+                    block: Some(call_block),
+                })
+            }
+        }
+    }
+
+    /// Start an in-process EVM forked from a block, or from a recording saved by
+    /// `save_fork_session`. State is fetched from the network as transactions read it, unless
+    /// the session is offline and reads only its recording.
+    pub async fn create_fork_session(
+        &mut self,
+        block: Option<&Value>,
+        recording: Option<&str>,
+        offline: bool,
+        network: Option<&str>,
+    ) -> Result<ForkSessionInfo> {
+        if let Some(net) = network {
+            let available_networks = self.provider_manager.get_available_networks();
+            utils::validate_network(net, &available_networks)
+                .map_err(|e| anyhow!("Network validation failed: {}", e))?;
+        }
+        if self.fork_sessions.len() >= MAX_FORK_SESSIONS {
+            return Err(anyhow!(
+                "{} fork sessions are already open; delete one first",
+                MAX_FORK_SESSIONS
+            ));
+        }
+
+        let chain_id = self.provider_manager.get_network_config(network)?.chain_id;
+        let provider = self
+            .provider_manager
+            .get_provider(network)
+            .map_err(|e| anyhow!("Failed to get provider: {}", e))?;
+
+        let recording = match recording {
+            Some(name) => {
+                if block.is_some() {
+                    return Err(anyhow!(
+                        "A recording is forked from the block it was recorded at; omit block"
+                    ));
+                }
+                let dir = self.provider_manager.config().server.fork_recordings_dir();
+                let path = fork::recording_path(&dir, name)?;
+                let content = tokio::fs::read_to_string(&path)
+                    .await
+                    .map_err(|e| anyhow!("Failed to read recording '{}': {}", name, e))?;
+                let recording: ForkRecording = serde_json::from_str(&content)
+                    .map_err(|e| anyhow!("Invalid recording '{}': {}", name, e))?;
+                if recording.chain_id != chain_id {
+                    return Err(anyhow!(
+                        "Recording '{}' is of chain {}, but the network is chain {}",
+                        name,
+                        recording.chain_id,
+                        chain_id
+                    ));
+                }
+                recording
+            }
+            None if offline => return Err(anyhow!("An offline fork session needs a recording")),
+            None => {
+                let block = match block {
+                    Some(block) => utils::parse_block_id(block)?,
+                    None => BlockId::latest(),
+                };
+                ForkRecording::new(chain_id, ForkBlock::fetch(provider, block).await?)
+            }
+        };
+
+        self.fork_session_count += 1;
+        let session_id = format!("fork-{}", self.fork_session_count);
+        let provider = (!offline).then(|| provider.clone());
+        let session = ForkSession::new(recording, provider, network.map(str::to_string));
+        let info = session.info(&session_id);
+        self.fork_sessions.insert(session_id, session);
+        Ok(info)
+    }
+
+    /// Apply a transaction to a fork session. The sender is impersonated, so `from` can be any
+    /// account; state overrides are written into the session before the transaction runs.
+    pub async fn fork_transact(
+        &mut self,
+        session_id: &str,
+        contract_address: &str,
+        function_call: &FunctionCall,
+    ) -> Result<CallResult> {
+        self.fork_execute(session_id, contract_address, function_call, true)
+            .await
+    }
+
+    /// Call a function on the state of a fork session, leaving the state unchanged
+    pub async fn fork_call(
+        &mut self,
+        session_id: &str,
+        contract_address: &str,
+        function_call: &FunctionCall,
+    ) -> Result<CallResult> {
+        if function_call.state_overrides.is_some() {
+            return Err(anyhow!(
+                "State overrides would change the session; apply them with fork_transact"
+            ));
+        }
+        self.fork_execute(session_id, contract_address, function_call, false)
+            .await
+    }
+
+    async fn fork_execute(
+        &mut self,
+        session_id: &str,
+        contract_address: &str,
+        function_call: &FunctionCall,
+        commit: bool,
+    ) -> Result<CallResult> {
+        let address = utils::validate_address(contract_address)
+            .map_err(|e| anyhow!("Invalid contract address: {}", e))?;
+        if function_call.block.is_some() {
+            return Err(anyhow!(
+                "A fork session runs at the block it was created at; omit block"
+            ));
+        }
+
+        let session = self
+            .fork_sessions
+            .get(session_id)
+            .ok_or_else(|| anyhow!("Fork session '{}' not found", session_id))?;
+        let network = session.network.clone();
+        let network = network.as_deref();
+        let fork_block = session.block();
+        let call_block = CallBlock {
+            number: Some(fork_block.number),
+            hash: Some(format!("{:?}", fork_block.hash)),
+            tag: None,
+        };
+        let provider = self
+            .provider_manager
+            .get_provider(network)
+            .map_err(|e| anyhow!("Failed to get provider: {}", e))?;
+
+        // Without a function name, the transaction is a plain ETH transfer
+        let function = if function_call.function_name.is_empty() {
+            None
+        } else {
+            utils::validate_function_name(&function_call.function_name)
+                .map_err(|e| anyhow!("Invalid function name: {}", e))?;
+            let function = match Self::function_fragment(&function_call.function_name)? {
+                Some(fragment) if !fragment.outputs.is_empty() => fragment,
+                fragment => {
+                    let abi =
+                        Self::fork_abi(&mut self.abi_resolver, provider, session, address, network)
+                            .await;
+                    Self::select_function(abi, fragment, &function_call.function_name, address)?
+                }
+            };
+            Some(function)
+        };
+        let state_override = match &function_call.state_overrides {
+            Some(value) => Some(
+                Self::fork_state_override(
+                    &mut self.abi_resolver,
+                    &mut self.erc20_mappings,
+                    provider,
+                    session,
+                    value,
+                    network,
+                )
+                .await?,
+            ),
+            None => None,
+        };
+
+        let calldata = match &function {
+            Some(function) => self
+                .encode_function_call(function, &function_call.parameters)
+                .map_err(|e| anyhow!("Failed to encode function call: {}", e))?,
+            None => Bytes::new(),
+        };
+        let from = match &function_call.from {
+            Some(from) => utils::validate_address(from)
+                .map_err(|e| anyhow!("Invalid 'from' address: {}", e))?,
+            None => Address::ZERO,
+        };
+        let value = match &function_call.value {
+            Some(value) => utils::validate_hex_value(value)
+                .map_err(|e| anyhow!("Invalid transaction value: {}", e))?,
+            None => U256::ZERO,
+        };
+        let gas_price = match &function_call.gas_price {
+            Some(gas_price) => Some(
+                utils::validate_hex_value(gas_price)
+                    .map_err(|e| anyhow!("Invalid gas price: {}", e))?,
+            ),
+            None => None,
+        };
+        let transaction = ForkTransaction {
+            from,
+            to: address,
+            data: calldata,
+            value,
+            gas_limit: function_call.gas_limit,
+            gas_price,
+        };
+
+        let session = self
+            .fork_sessions
+            .get_mut(session_id)
+            .ok_or_else(|| anyhow!("Fork session '{}' not found", session_id))?;
+        if let Some(state_override) = &state_override {
+            session.apply_overrides(state_override)?;
+        }
+        let execution = session.execute(&transaction, commit)?;
+        let error = match (&execution.halt_reason, execution.success) {
+            (_, true) => None,
+            (Some(reason), false) => Some(format!("Execution halted: {}", reason)),
+            (None, false) => {
+                let abi =
+                    Self::fork_abi(&mut self.abi_resolver, provider, session, address, network)
+                        .await
+                        .ok();
+                Some(trace::decode_revert(&execution.output, abi.as_ref()))
+            }
+        };
+        let logs = if commit && execution.success {
+            Self::decode_fork_logs(
+                &mut self.abi_resolver,
+                provider,
+                session,
+                &execution.logs,
+                network,
+            )
+            .await
+        } else {
+            Vec::new()
+        };
+
+        let mut result = if let Some(error) = error {
+            CallResult {
+                success: false,
+                result: None,
+                error: Some(error),
+                gas_used: None,
+                transaction_hash: None,
+                block: None,
+            }
+        } else if !commit {
+            match &function {
+                Some(function) => {
+                    self.call_result(function, &execution.output, function_call.include_types)
+                }
+                None => CallResult {
+                    success: true,
+                    result: None,
+                    error: None,
+                    gas_used: None,
+                    transaction_hash: None,
+                    block: None,
+                },
+            }
+        } else {
+            let decoded_result = function.as_ref().map(|function| {
+                Self::decode_function_result(
+                    function,
+                    &execution.output,
+                    function_call.include_types,
+                )
+                .unwrap_or_else(|_| {
+                    serde_json::json!({
+                        "raw_result": format!("0x{}", hex::encode(&execution.output))
+                    })
+                })
+            });
+            CallResult {
+                success: true,
+                result: Some(serde_json::json!({
+                    "result": decoded_result,
+                    "logs": logs
+                })),
+                error: None,
+                gas_used: None,
+                transaction_hash: None,
+                block: None,
             }
+        };
+        result.gas_used = Some(execution.gas_used);
+        result.block = Some(call_block);
+        Ok(result)
+    }
+
+    /// ABI of a contract in a fork session. Online sessions record the ABIs they resolve, and
+    /// offline sessions only take them from their recording.
+    async fn fork_abi(
+        abi_resolver: &mut AbiResolver,
+        provider: &RootProvider<Http<Client>>,
+        session: &ForkSession,
+        address: Address,
+        network: Option<&str>,
+    ) -> Result<JsonAbi> {
+        if let Some(abi) = session.abi(address) {
+            return Ok(abi);
         }
+        if session.is_offline() {
+            return Err(anyhow!(
+                "The ABI of {:?} is not in the recording, and the session is offline",
+                address
+            ));
+        }
+        let block = BlockId::number(session.block().number);
+        let (resolved, _) =
+            Self::resolve_contract_abi(abi_resolver, provider, address, block, network).await?;
+        session.record_abi(address, resolved.abi.clone());
+        Ok(resolved.abi)
+    }
 
-        // Set value if provided
-        if let Some(value_str) = &function_call.value {
-            match utils::validate_hex_value(value_str) {
-                Ok(value) => {
-                    tx_request = tx_request.value(value);
+    /// Build the state overrides of a fork transaction. ERC-20 mapping slots come from the
+    /// session's recording, or are found and recorded when the session is online.
+    async fn fork_state_override(
+        abi_resolver: &mut AbiResolver,
+        erc20_mappings: &mut HashMap<String, CachedLookup<MappingSlot>>,
+        provider: &RootProvider<Http<Client>>,
+        session: &ForkSession,
+        value: &Value,
+        network: Option<&str>,
+    ) -> Result<StateOverride> {
+        let (mut state_override, erc20) = overrides::parse_state_override(value)?;
+        for entry in &erc20 {
+            let mapping = match session.erc20_mapping(entry.token, entry.mapping) {
+                Some(mapping) => mapping,
+                None if session.is_offline() => {
+                    return Err(anyhow!(
+                        "The ERC-20 {} slot of {:?} is not in the recording, and the session is \
+                         offline",
+                        entry.mapping.name(),
+                        entry.token
+                    ));
                 }
-                Err(e) => {
-                    return Ok(CallResult {
-                        success: false,
-                        result: None,
-                        error: Some(format!("Invalid transaction value for simulation: {}", e)),
-                        gas_used: None,
-                        transaction_hash: None,
-                    });
+                None => {
+                    let mapping = Self::erc20_mapping(
+                        abi_resolver,
+                        erc20_mappings,
+                        provider,
+                        entry.token,
+                        entry.mapping,
+                        BlockId::number(session.block().number),
+                        network,
+                    )
+                    .await?;
+                    session.record_erc20_mapping(entry.token, entry.mapping, mapping);
+                    mapping
                 }
-            }
+            };
+            overrides::set_erc20_slot(&mut state_override, entry, mapping);
         }
+        Ok(state_override)
+    }
 
-        // First, estimate gas for the transaction
-        let gas_estimate = match provider.estimate_gas(&tx_request).await {
-            Ok(gas) => Some(gas),
-            Err(e) => {
-                // If gas estimation fails, the transaction would likely fail
-                let friendly_error = utils::interpret_rpc_error(&e.to_string());
-                return Ok(CallResult {
-                    success: false,
-                    result: Some(serde_json::json!({
-                        "simulated": true,
-                        "gas_estimation_failed": true,
-                        "error": friendly_error
-                    })),
-                    error: Some(format!(
-                        "Gas estimation failed (transaction would likely revert): {}",
-                        friendly_error
-                    )),
-                    gas_used: None,
-                    transaction_hash: None,
-                });
+    /// Decode the logs of a fork transaction with the ABIs of the contracts that emitted them
+    async fn decode_fork_logs(
+        abi_resolver: &mut AbiResolver,
+        provider: &RootProvider<Http<Client>>,
+        session: &ForkSession,
+        logs: &[alloy::primitives::Log],
+        network: Option<&str>,
+    ) -> Vec<Value> {
+        let mut abis: HashMap<Address, Option<JsonAbi>> = HashMap::new();
+        let mut decoded_logs = Vec::new();
+        for log in logs {
+            if !abis.contains_key(&log.address) {
+                let abi = if abis.len() < MAX_TRACE_ABIS {
+                    Self::fork_abi(abi_resolver, provider, session, log.address, network)
+                        .await
+                        .ok()
+                } else {
+                    None
+                };
+                abis.insert(log.address, abi);
             }
-        };
 
-        // Simulate with eth_call
-        match provider.call(&tx_request).await {
-            Ok(result_bytes) => {
-                // Try to decode the result
-                let decoded_result = self
-                    .decode_function_result(function, &result_bytes)
-                    .unwrap_or_else(|_| {
-                        serde_json::json!({
-                            "raw_result": format!("0x{}", hex::encode(&result_bytes))
-                        })
-                    });
-
-                Ok(CallResult {
-                    success: true,
-                    result: Some(serde_json::json!({
-                        "simulated": true,
-                        "result": decoded_result,
-                        "would_succeed": true
-                    })),
-                    error: None,
-                    gas_used: gas_estimate,
-                    transaction_hash: None,
-                })
-            }
-            Err(e) => {
-                let friendly_error = utils::interpret_rpc_error(&e.to_string());
-                Ok(CallResult {
-                    success: false,
-                    result: Some(serde_json::json!({
-                        "simulated": true,
-                        "would_succeed": false,
-                        "revert_reason": friendly_error
-                    })),
-                    error: Some(format!("Transaction simulation failed: {}", friendly_error)),
-                    gas_used: gas_estimate,
-                    transaction_hash: None,
-                })
+            let topics: Vec<String> = log
+                .topics()
+                .iter()
+                .map(|topic| format!("{:?}", topic))
+                .collect();
+            let mut decoded_log = serde_json::json!({
+                "address": format!("{:?}", log.address),
+                "topics": topics,
+                "data": format!("0x{}", hex::encode(&log.data.data)),
+            });
+            let decoded = abis[&log.address]
+                .as_ref()
+                .and_then(|abi| Self::decode_event_log(abi, log.topics(), &log.data.data));
+            if let Some((event, args)) = decoded {
+                decoded_log["event_name"] = Value::String(event.name.clone());
+                decoded_log["signature"] = Value::String(event.signature());
+                decoded_log["decoded"] = args;
             }
+            decoded_logs.push(decoded_log);
         }
+        decoded_logs
+    }
+
+    /// Save the state a fork session has read to the recordings directory, to create the session
+    /// again from it without the network
+    pub async fn save_fork_session(&self, session_id: &str, name: &str) -> Result<ForkSessionInfo> {
+        let session = self
+            .fork_sessions
+            .get(session_id)
+            .ok_or_else(|| anyhow!("Fork session '{}' not found", session_id))?;
+        let dir = self.provider_manager.config().server.fork_recordings_dir();
+        let path = fork::recording_path(&dir, name)?;
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .map_err(|e| anyhow!("Failed to create recordings directory: {}", e))?;
+        let recording = serde_json::to_string_pretty(&session.recording())?;
+        tokio::fs::write(&path, recording)
+            .await
+            .map_err(|e| anyhow!("Failed to write recording '{}': {}", name, e))?;
+        Ok(session.info(session_id))
+    }
+
+    pub fn delete_fork_session(&mut self, session_id: &str) -> Result<()> {
+        self.fork_sessions
+            .remove(session_id)
+            .map(|_| ())
+            .ok_or_else(|| anyhow!("Fork session '{}' not found", session_id))
     }
 
     /// Send a transaction to execute a contract function
@@ -827,48 +3130,26 @@ impl ContractManager {
         let from_address = signer.address();
         tracing::info!("Sending transaction from address: {:?}", from_address);
 
-        // Get the ABI and encode the function call
-        let abi = self
-            .abi_resolver
-            .get_abi(contract_address, network)
-            .await
-            .map_err(|e| {
-                anyhow!(
-                    "Could not resolve ABI for transaction: {}",
-                    utils::interpret_abi_error(&e.to_string(), contract_address)
-                )
-            })?;
-
-        let function = abi
-            .functions()
-            .find(|f| f.name == function_call.function_name)
-            .ok_or_else(|| {
-                let available_functions: Vec<String> = abi
-                    .functions()
-                    .map(|f| f.name.clone())
-                    .collect();
+        let base_provider = self.provider_manager.get_provider(network)?;
 
-                if available_functions.is_empty() {
-                    anyhow!(
-                        "Function '{}' not found. The contract ABI contains no functions.",
-                        function_call.function_name
-                    )
-                } else {
-                    anyhow!(
-                        "Function '{}' not found in contract ABI. Available functions: {}",
-                        function_call.function_name,
-                        available_functions.join(", ")
-                    )
-                }
-            })?;
+        // Resolve the function and encode the call
+        let function = Self::resolve_call_function(
+            &mut self.abi_resolver,
+            base_provider,
+            address,
+            &function_call.function_name,
+            BlockId::latest(),
+            network,
+        )
+        .await
+        .map_err(|e| anyhow!("Cannot resolve function for transaction: {}", e))?;
 
         // Encode function call parameters
         let encoded_input = self
-            .encode_function_call(function, &function_call.parameters)
+            .encode_function_call(&function, &function_call.parameters)
             .map_err(|e| anyhow!("Failed to encode function call for transaction: {}", e))?;
 
-        // Get provider and create wallet-enabled provider
-        let base_provider = self.provider_manager.get_provider(network)?;
+        // Create wallet-enabled provider
         let network_config = self.provider_manager.get_network_config(network)?;
         
         // Parse the URL for the wallet provider
@@ -967,3 +3248,865 @@ impl ContractManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::json_abi::Function;
+    use serde_json::json;
+
+    fn function_from_json(value: Value) -> Function {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn encode(function: &Function, parameters: &Value) -> Result<Vec<DynSolValue>> {
+        function
+            .inputs
+            .iter()
+            .zip(parameters.as_array().unwrap())
+            .map(|(input, value)| {
+                ContractManager::json_to_dyn_sol_value(value, &input.ty, &input.components)
+            })
+            .collect()
+    }
+
+    fn nested_struct_function() -> Function {
+        function_from_json(json!({
+            "type": "function",
+            "name": "submit",
+            "stateMutability": "nonpayable",
+            "outputs": [],
+            "inputs": [{
+                "name": "order",
+                "type": "tuple",
+                "components": [
+                    { "name": "maker", "type": "address" },
+                    { "name": "amounts", "type": "uint256[]" },
+                    {
+                        "name": "legs",
+                        "type": "tuple[]",
+                        "components": [
+                            { "name": "pool", "type": "address" },
+                            { "name": "fee", "type": "uint24" }
+                        ]
+                    },
+                    {
+                        "name": "meta",
+                        "type": "tuple",
+                        "components": [
+                            { "name": "tag", "type": "string" },
+                            { "name": "flag", "type": "bool" }
+                        ]
+                    }
+                ]
+            }]
+        }))
+    }
+
+    #[test]
+    fn test_nested_tuple_encoding_positional_and_named() {
+        let function = nested_struct_function();
+        let maker = "0x742d35Cc6435C9c1c72c5E7b18BaB7e1DB7a5d6e";
+        let pool = "0x0000000000000000000000000000000000000001";
+
+        let positional = json!([[maker, ["1", 2], [[pool, 500]], ["hello", true]]]);
+        let named = json!([{
+            "maker": maker,
+            "amounts": ["1", 2],
+            "legs": [{ "pool": pool, "fee": 500 }],
+            "meta": { "flag": true, "tag": "hello" }
+        }]);
+
+        let from_positional = encode(&function, &positional).unwrap();
+        let from_named = encode(&function, &named).unwrap();
+        assert_eq!(from_positional, from_named);
+        assert!(function.abi_encode_input(&from_named).is_ok());
+
+        let DynSolValue::Tuple(order) = &from_named[0] else {
+            panic!("expected tuple");
+        };
+        assert_eq!(order.len(), 4);
+        assert_eq!(
+            order[2],
+            DynSolValue::Array(vec![DynSolValue::Tuple(vec![
+                DynSolValue::Address(Address::from_str(pool).unwrap()),
+                DynSolValue::Uint(U256::from(500), 24),
+            ])])
+        );
+    }
+
+    #[test]
+    fn test_legacy_params_wrapper() {
+        let function = function_from_json(json!({
+            "type": "function",
+            "name": "exactInputSingle",
+            "stateMutability": "payable",
+            "outputs": [],
+            "inputs": [{
+                "name": "params",
+                "type": "tuple",
+                "components": [
+                    { "name": "tokenIn", "type": "address" },
+                    { "name": "fee", "type": "uint24" }
+                ]
+            }]
+        }));
+        let token = "0x0000000000000000000000000000000000000002";
+
+        let wrapped = encode(&function, &json!([{ "params": [token, 3000] }])).unwrap();
+        let positional = encode(&function, &json!([[token, 3000]])).unwrap();
+        assert_eq!(wrapped, positional);
+    }
+
+    #[test]
+    fn test_tuple_errors() {
+        let function = nested_struct_function();
+        let maker = "0x742d35Cc6435C9c1c72c5E7b18BaB7e1DB7a5d6e";
+
+        // Wrong component count
+        assert!(encode(&function, &json!([[maker, []]])).is_err());
+
+        // Missing named component
+        let missing = json!([{ "maker": maker, "amounts": [], "legs": [] }]);
+        assert!(encode(&function, &missing)
+            .unwrap_err()
+            .to_string()
+            .contains("meta"));
+
+        // Unknown named component
+        let unknown = json!([{
+            "maker": maker,
+            "amounts": [],
+            "legs": [],
+            "meta": ["", false],
+            "extra": 1
+        }]);
+        assert!(encode(&function, &unknown)
+            .unwrap_err()
+            .to_string()
+            .contains("extra"));
+    }
+
+    fn value_for(value: Value, sol_type: &str) -> Result<DynSolValue> {
+        ContractManager::json_to_dyn_sol_value(&value, sol_type, &[])
+    }
+
+    #[test]
+    fn test_uint_parsing_and_range() {
+        assert_eq!(
+            value_for(json!("100"), "uint256").unwrap(),
+            DynSolValue::Uint(U256::from(100), 256)
+        );
+        assert_eq!(
+            value_for(json!("0x100"), "uint").unwrap(),
+            DynSolValue::Uint(U256::from(256), 256)
+        );
+        assert!(value_for(json!(255), "uint8").is_ok());
+        assert!(value_for(json!(256), "uint8").is_err());
+        assert!(value_for(json!(-1), "uint256").is_err());
+        assert!(value_for(json!(1), "uint7").is_err());
+    }
+
+    #[test]
+    fn test_int_parsing_and_range() {
+        assert_eq!(
+            value_for(json!(-887272), "int24").unwrap(),
+            DynSolValue::Int(I256::try_from(-887272).unwrap(), 24)
+        );
+        assert_eq!(
+            value_for(json!("-0x10"), "int256").unwrap(),
+            DynSolValue::Int(I256::try_from(-16).unwrap(), 256)
+        );
+        assert!(value_for(json!(-128), "int8").is_ok());
+        assert!(value_for(json!(127), "int8").is_ok());
+        assert!(value_for(json!(-129), "int8").is_err());
+        assert!(value_for(json!("128"), "int8").is_err());
+        assert!(value_for(json!(8388608), "int24").is_err());
+    }
+
+    #[test]
+    fn test_fixed_and_nested_arrays() {
+        let fixed = value_for(json!(["1", "2", "3"]), "uint256[3]").unwrap();
+        assert!(matches!(fixed, DynSolValue::FixedArray(ref items) if items.len() == 3));
+        assert!(value_for(json!(["1", "2"]), "uint256[3]").is_err());
+
+        let pair = "0x0000000000000000000000000000000000000001";
+        let nested = value_for(
+            json!([[pair, pair], [pair, pair], [pair, pair]]),
+            "address[2][]",
+        )
+        .unwrap();
+        let DynSolValue::Array(outer) = nested else {
+            panic!("expected dynamic outer array");
+        };
+        assert_eq!(outer.len(), 3);
+        assert!(outer
+            .iter()
+            .all(|inner| matches!(inner, DynSolValue::FixedArray(items) if items.len() == 2)));
+        assert!(value_for(json!([[pair]]), "address[2][]").is_err());
+
+        let word = format!("0x{}", "ab".repeat(32));
+        let value = value_for(json!([[word], []]), "bytes32[][]").unwrap();
+        assert_eq!(value.as_type().unwrap().sol_type_name(), "bytes32[][]");
+    }
+
+    #[test]
+    fn test_fixed_bytes_length() {
+        assert_eq!(
+            value_for(json!("0x1234"), "bytes4").unwrap(),
+            DynSolValue::FixedBytes(Word::right_padding_from(&[0x12, 0x34]), 4)
+        );
+        assert!(value_for(json!("0x1234567890"), "bytes4").is_err());
+        assert!(value_for(json!("0x00"), "bytes33").is_err());
+    }
+
+    #[test]
+    fn test_named_output_decoding() {
+        let function = function_from_json(json!({
+            "type": "function",
+            "name": "getReserves",
+            "stateMutability": "view",
+            "inputs": [],
+            "outputs": [
+                { "name": "reserve0", "type": "uint112" },
+                { "name": "reserve1", "type": "uint112" },
+                { "name": "", "type": "uint32" }
+            ]
+        }));
+        let values = vec![
+            DynSolValue::Uint(U256::from(10), 112),
+            DynSolValue::Uint(U256::from(20), 112),
+            DynSolValue::Uint(U256::from(30), 32),
+        ];
+
+        let decoded =
+            ContractManager::dyn_sol_values_to_json(&values, &function.outputs, false).unwrap();
+        assert_eq!(
+            decoded,
+            json!({ "reserve0": "10", "reserve1": "20", "2": "30" })
+        );
+
+        let typed =
+            ContractManager::dyn_sol_values_to_json(&values, &function.outputs, true).unwrap();
+        assert_eq!(
+            typed["reserve0"],
+            json!({ "type": "uint112", "value": "10" })
+        );
+    }
+
+    #[test]
+    fn test_struct_and_unnamed_output_decoding() {
+        let function = function_from_json(json!({
+            "type": "function",
+            "name": "slot0",
+            "stateMutability": "view",
+            "inputs": [],
+            "outputs": [{
+                "name": "",
+                "type": "tuple[]",
+                "components": [
+                    { "name": "tick", "type": "int24" },
+                    { "name": "id", "type": "bytes4" }
+                ]
+            }]
+        }));
+        let values = vec![DynSolValue::Array(vec![DynSolValue::Tuple(vec![
+            DynSolValue::Int(I256::try_from(-5).unwrap(), 24),
+            DynSolValue::FixedBytes(Word::right_padding_from(&[0xde, 0xad, 0xbe, 0xef]), 4),
+        ])])];
+
+        let decoded =
+            ContractManager::dyn_sol_values_to_json(&values, &function.outputs, false).unwrap();
+        assert_eq!(decoded, json!([{ "tick": "-5", "id": "0xdeadbeef" }]));
+
+        // Outputs without names fall back to positional arrays
+        let unnamed = vec![
+            DynSolValue::Bool(true),
+            DynSolValue::Function(alloy::primitives::Function::ZERO),
+        ];
+        let decoded = ContractManager::dyn_sol_values_to_json(&unnamed, &[], false).unwrap();
+        assert_eq!(decoded, json!([true, format!("0x{}", "00".repeat(24))]));
+    }
+
+    fn overloaded_abi() -> JsonAbi {
+        serde_json::from_value(json!([
+            {
+                "type": "function",
+                "name": "safeTransferFrom",
+                "stateMutability": "nonpayable",
+                "inputs": [
+                    { "name": "from", "type": "address" },
+                    { "name": "to", "type": "address" },
+                    { "name": "tokenId", "type": "uint256" }
+                ],
+                "outputs": []
+            },
+            {
+                "type": "function",
+                "name": "safeTransferFrom",
+                "stateMutability": "nonpayable",
+                "inputs": [
+                    { "name": "from", "type": "address" },
+                    { "name": "to", "type": "address" },
+                    { "name": "tokenId", "type": "uint256" },
+                    { "name": "data", "type": "bytes" }
+                ],
+                "outputs": []
+            },
+            {
+                "type": "function",
+                "name": "ownerOf",
+                "stateMutability": "view",
+                "inputs": [{ "name": "tokenId", "type": "uint256" }],
+                "outputs": [{ "name": "", "type": "address" }]
+            }
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_function_overloads() {
+        let abi = overloaded_abi();
+
+        let err = ContractManager::resolve_function(&abi, "safeTransferFrom").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("safeTransferFrom(address,address,uint256,bytes)"));
+
+        let by_signature = ContractManager::resolve_function(
+            &abi,
+            "safeTransferFrom(address, address, uint256, bytes)",
+        )
+        .unwrap();
+        assert_eq!(by_signature.inputs.len(), 4);
+
+        // safeTransferFrom(address,address,uint256)
+        let by_selector = ContractManager::resolve_function(&abi, "0x42842e0e").unwrap();
+        assert_eq!(by_selector.inputs.len(), 3);
+        let by_selector = ContractManager::resolve_function(&abi, "0X42842E0E").unwrap();
+        assert_eq!(by_selector.inputs.len(), 3);
+
+        assert_eq!(
+            ContractManager::resolve_function(&abi, "ownerOf")
+                .unwrap()
+                .name,
+            "ownerOf"
+        );
+        assert!(ContractManager::resolve_function(&abi, "ownerOf(address)").is_err());
+        assert!(ContractManager::resolve_function(&abi, "balanceOf").is_err());
+    }
+
+    fn event_abi() -> JsonAbi {
+        serde_json::from_value(json!([
+            {
+                "type": "event",
+                "name": "Transfer",
+                "anonymous": false,
+                "inputs": [
+                    { "name": "from", "type": "address", "indexed": true },
+                    { "name": "to", "type": "address", "indexed": true },
+                    { "name": "value", "type": "uint256", "indexed": false }
+                ]
+            },
+            {
+                "type": "event",
+                "name": "Registered",
+                "anonymous": false,
+                "inputs": [
+                    { "name": "label", "type": "string", "indexed": true },
+                    { "name": "id", "type": "bytes32", "indexed": true }
+                ]
+            },
+            {
+                "type": "event",
+                "name": "Note",
+                "anonymous": true,
+                "inputs": [
+                    { "name": "sig", "type": "bytes4", "indexed": true },
+                    { "name": "", "type": "uint256", "indexed": false }
+                ]
+            }
+        ]))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_function_fragments() {
+        use crate::ethereum::abi::AbiSource;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut abi_resolver = AbiResolver::new(AbiSource {
+            cache_dir: temp_dir.path().to_path_buf(),
+            resolution_order: vec!["manual".to_string()],
+            ..AbiSource::default()
+        });
+        let provider = RootProvider::new_http("http://127.0.0.1:1".parse().unwrap());
+        let address = Address::repeat_byte(0x11);
+        let abi = JsonAbi::parse([
+            "function balanceOf(address owner) view returns (uint256 balance)",
+            "function transfer(address to, uint256 amt) returns (bool)",
+        ])
+        .unwrap();
+        abi_resolver
+            .add_manual_abi(&format!("{:?}", address), None, abi)
+            .await
+            .unwrap();
+        macro_rules! resolve {
+            ($function_name:expr) => {
+                ContractManager::resolve_call_function(
+                    &mut abi_resolver,
+                    &provider,
+                    address,
+                    $function_name,
+                    BlockId::latest(),
+                    None,
+                )
+                .await
+            };
+        }
+
+        // A fragment with outputs is used as is; the ABI would have named the output
+        let function = resolve!("balanceOf(address)(uint256)").unwrap();
+        assert_eq!(function.outputs[0].name, "");
+        let function = resolve!("function balanceOf(address who) returns (uint256)").unwrap();
+        assert_eq!(function.inputs[0].name, "who");
+
+        // Without outputs the ABI entry is used, found by signature or by bare selector
+        let function = resolve!("balanceOf(address)").unwrap();
+        assert_eq!(function.outputs[0].name, "balance");
+        let function = resolve!("0x70a08231").unwrap();
+        assert_eq!(function.outputs[0].name, "balance");
+        let function = resolve!("0xa9059cbb").unwrap();
+        assert_eq!(function.signature(), "transfer(address,uint256)");
+
+        // Unknown functions error unless they were named by a fragment to fall back to
+        assert!(resolve!("approve").is_err());
+        assert!(resolve!("0x095ea7b3").is_err());
+        assert!(resolve!("transfer(address").is_err());
+        let function = resolve!("approve(address,uint256)").unwrap();
+        assert!(function.outputs.is_empty());
+
+        assert_eq!(
+            ContractManager::function_fragment("transfer").unwrap(),
+            None
+        );
+        assert_eq!(
+            ContractManager::function_fragment("0xa9059cbb").unwrap(),
+            None
+        );
+        assert!(ContractManager::function_fragment("transfer(address,").is_err());
+
+        // The ABI entry wins, and the fragment is the fallback
+        let transfer =
+            JsonAbi::parse(["function transfer(address to, uint256 amt) returns (bool)"]).unwrap();
+        let fragment = || ContractManager::function_fragment("transfer(address,uint256)").unwrap();
+        let function = ContractManager::select_function(
+            Ok(transfer),
+            fragment(),
+            "transfer(address,uint256)",
+            address,
+        )
+        .unwrap();
+        assert_eq!(function.outputs.len(), 1);
+
+        let function = ContractManager::select_function(
+            Err(anyhow!("not found")),
+            fragment(),
+            "transfer(address,uint256)",
+            address,
+        )
+        .unwrap();
+        assert!(function.outputs.is_empty());
+        let error =
+            ContractManager::select_function(Err(anyhow!("not found")), None, "transfer", address)
+                .unwrap_err();
+        assert!(error.to_string().contains("call it by fragment"));
+
+        // Without declared outputs the raw return data comes back as hex
+        let word = Bytes::from(U256::from(42).to_be_bytes::<32>());
+        assert_eq!(
+            ContractManager::decode_function_result(&function, &word, false).unwrap(),
+            json!(format!("0x{}2a", "0".repeat(62)))
+        );
+        assert_eq!(
+            ContractManager::decode_function_result(&function, &Bytes::new(), false).unwrap(),
+            Value::Null
+        );
+        let declared = resolve!("balanceOf(address)(uint256)").unwrap();
+        assert_eq!(
+            ContractManager::decode_function_result(&declared, &word, false).unwrap(),
+            json!("42")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pin_block() {
+        use crate::ethereum::test_rpc::{block, rpc_provider, BLOCK_HASH, BLOCK_NUMBER};
+
+        // Only "latest" and the block's hash can be looked up
+        let provider = rpc_provider(|method, params| match method {
+            "eth_getBlockByNumber" if params[0] == "latest" => Ok(block(json!([]))),
+            "eth_getBlockByHash" if params[0] == BLOCK_HASH => Ok(block(json!([]))),
+            _ => Err(format!("unexpected {} {}", method, params)),
+        })
+        .await;
+        let pin = |block: Value| {
+            let provider = provider.clone();
+            async move { ContractManager::pin_block(&provider, Some(&block)).await }
+        };
+
+        // Tags are pinned to the number of the block they point at
+        let (block, call_block) = pin(json!("latest")).await.unwrap();
+        assert_eq!(block, BlockId::number(BLOCK_NUMBER));
+        assert_eq!(call_block.number, Some(BLOCK_NUMBER));
+        assert_eq!(call_block.hash.as_deref(), Some(BLOCK_HASH));
+        assert_eq!(call_block.tag.as_deref(), Some("latest"));
+
+        let (block, call_block) = pin(json!(BLOCK_HASH)).await.unwrap();
+        assert_eq!(block, BlockId::hash(BLOCK_HASH.parse().unwrap()));
+        assert_eq!(call_block.number, Some(BLOCK_NUMBER));
+        assert_eq!(call_block.hash.as_deref(), Some(BLOCK_HASH));
+
+        // Numbers are already fixed and the pending block can't be, so neither is looked up
+        let (block, call_block) = pin(json!(17_000_000)).await.unwrap();
+        assert_eq!(block, BlockId::number(17_000_000));
+        assert_eq!(call_block.number, Some(17_000_000));
+        assert!(call_block.hash.is_none());
+
+        let (block, call_block) = pin(json!("pending")).await.unwrap();
+        assert_eq!(block, BlockId::pending());
+        assert_eq!(call_block.number, None);
+        assert_eq!(call_block.tag.as_deref(), Some("pending"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_block_call_result() {
+        let mut manager =
+            ContractManager::new(ProviderManager::new(crate::config::Config::default()).unwrap());
+        let function_call: FunctionCall = serde_json::from_value(json!({
+            "function_name": "totalSupply()(uint256)",
+            "parameters": [],
+            "block": "not a block"
+        }))
+        .unwrap();
+        let address = "0x000000000000000000000000000000000000dEaD";
+
+        // A bad block is reported like any other bad input, not as a tool error
+        let result = manager
+            .call_view_function(address, &function_call, None)
+            .await
+            .unwrap();
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("Invalid block"));
+        let result = manager
+            .simulate_transaction(address, &function_call, false, None)
+            .await
+            .unwrap();
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("Invalid block"));
+    }
+
+    #[tokio::test]
+    async fn test_read_storage_at_block_hash() {
+        use crate::ethereum::test_rpc::{block, rpc_url, BLOCK_HASH, BLOCK_NUMBER};
+
+        let url = rpc_url(|method, params| match method {
+            "eth_getBlockByHash" if params[0] == BLOCK_HASH => Ok(block(json!([]))),
+            "eth_getStorageAt" if params[2] == json!({ "blockHash": BLOCK_HASH }) => {
+                Ok(json!(format!("0x{:064x}", 42)))
+            }
+            _ => Err(format!("unexpected {} {}", method, params)),
+        })
+        .await;
+        let mut config = crate::config::Config::default();
+        for network in config.networks.values_mut() {
+            network.rpc_url = url.clone();
+        }
+        let mut manager = ContractManager::new(ProviderManager::new(config).unwrap());
+
+        let result = manager
+            .read_storage(
+                "0x000000000000000000000000000000000000dEaD",
+                Some("0x0"),
+                None,
+                None,
+                Some(&json!(BLOCK_HASH)),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result["value"], json!(format!("0x{:064x}", 42)));
+        assert_eq!(result["block"]["number"], json!(BLOCK_NUMBER));
+        assert_eq!(result["block"]["hash"], json!(BLOCK_HASH));
+    }
+
+    #[test]
+    fn test_decode_event_log() {
+        let abi = event_abi();
+        let from = Address::from_str("0x0000000000000000000000000000000000000001").unwrap();
+        let to = Address::from_str("0x0000000000000000000000000000000000000002").unwrap();
+        let topics = vec![
+            alloy::primitives::keccak256("Transfer(address,address,uint256)"),
+            from.into_word(),
+            to.into_word(),
+        ];
+        let data = DynSolValue::Uint(U256::from(42), 256).abi_encode();
+
+        let (event, args) = ContractManager::decode_event_log(&abi, &topics, &data).unwrap();
+        assert_eq!(event.signature(), "Transfer(address,address,uint256)");
+        assert_eq!(
+            args,
+            json!({ "from": format!("0x{:x}", from), "to": format!("0x{:x}", to), "value": "42" })
+        );
+
+        // Unknown signatures are left undecoded
+        let unknown = vec![B256::repeat_byte(0x11)];
+        assert!(ContractManager::decode_event_log(&abi, &unknown, &[]).is_none());
+    }
+
+    #[test]
+    fn test_decode_indexed_dynamic_and_anonymous_events() {
+        let abi = event_abi();
+        let label_hash = alloy::primitives::keccak256("alice");
+        let id = B256::repeat_byte(0x22);
+        let topics = vec![
+            alloy::primitives::keccak256("Registered(string,bytes32)"),
+            label_hash,
+            id,
+        ];
+
+        let (_, args) = ContractManager::decode_event_log(&abi, &topics, &[]).unwrap();
+        assert_eq!(
+            args["label"],
+            json!({ "hash": format!("0x{:x}", label_hash) })
+        );
+        assert_eq!(args["id"], json!(format!("0x{:x}", id)));
+
+        let selector = B256::right_padding_from(&[0xa9, 0x05, 0x9c, 0xbb]);
+        let data = DynSolValue::Uint(U256::from(7), 256).abi_encode();
+        let (event, args) = ContractManager::decode_event_log(&abi, &[selector], &data).unwrap();
+        assert_eq!(event.name, "Note");
+        assert_eq!(args, json!({ "sig": "0xa9059cbb", "1": "7" }));
+    }
+
+    #[test]
+    fn test_event_topic_filters() {
+        let abi = event_abi();
+        let to_a = Address::from_str("0x0000000000000000000000000000000000000002").unwrap();
+        let to_b = Address::from_str("0x0000000000000000000000000000000000000003").unwrap();
+
+        let event = ContractManager::resolve_event(Some(&abi), "Transfer").unwrap();
+        let by_signature =
+            ContractManager::resolve_event(Some(&abi), "Transfer(address,address,uint256)")
+                .unwrap();
+        assert_eq!(event, by_signature);
+
+        let topics = ContractManager::event_topic_filters(
+            &event,
+            Some(&json!({ "to": [format!("0x{:x}", to_a), format!("0x{:x}", to_b)] })),
+        )
+        .unwrap();
+        assert_eq!(topics[0], Topic::from(event.selector()));
+        assert!(topics[1].is_empty());
+        assert_eq!(
+            topics[2],
+            Topic::from(vec![to_a.into_word(), to_b.into_word()])
+        );
+
+        // Non-indexed arguments can't be filtered on
+        assert!(
+            ContractManager::event_topic_filters(&event, Some(&json!({ "value": 1 }))).is_err()
+        );
+
+        // Indexed strings are matched by their hash
+        let registered = ContractManager::resolve_event(Some(&abi), "Registered").unwrap();
+        let topics =
+            ContractManager::event_topic_filters(&registered, Some(&json!({ "label": "alice" })))
+                .unwrap();
+        assert_eq!(topics[1], Topic::from(keccak256("alice")));
+    }
+
+    #[test]
+    fn test_resolve_event_without_abi() {
+        assert!(ContractManager::resolve_event(None, "Transfer").is_err());
+
+        let event = ContractManager::resolve_event(
+            None,
+            "Transfer(address indexed from, address indexed to, uint256 value)",
+        )
+        .unwrap();
+        assert_eq!(event.signature(), "Transfer(address,address,uint256)");
+
+        let topics =
+            ContractManager::event_topic_filters(&event, Some(&json!({ "from": [] }))).unwrap_err();
+        assert!(topics.to_string().contains("No values"));
+    }
+
+    #[test]
+    fn test_aggregate3_results() {
+        let aggregate3 = Function::parse(AGGREGATE3_SIGNATURE).unwrap();
+        assert_eq!(
+            aggregate3.selector(),
+            Selector::from([0x82, 0xad, 0x56, 0xcb])
+        );
+
+        let result = |success: bool, data: Vec<u8>| {
+            DynSolValue::Tuple(vec![DynSolValue::Bool(success), DynSolValue::Bytes(data)])
+        };
+        let output = aggregate3
+            .abi_encode_output(&[DynSolValue::Array(vec![
+                result(true, vec![0x2a]),
+                result(false, Vec::new()),
+            ])])
+            .unwrap();
+        assert_eq!(
+            ContractManager::aggregate3_results(&aggregate3, &output).unwrap(),
+            vec![result(true, vec![0x2a]), result(false, Vec::new())]
+        );
+
+        // Output that isn't aggregate3's is an error rather than an empty batch
+        assert!(ContractManager::aggregate3_results(&aggregate3, &[0xde, 0xad]).is_err());
+    }
+
+    #[test]
+    fn test_batch_results_alignment() {
+        let manager =
+            ContractManager::new(ProviderManager::new(crate::config::Config::default()).unwrap());
+        let aggregate3 = Function::parse(AGGREGATE3_SIGNATURE).unwrap();
+        let batch_call = |function_name: &str| -> BatchCall {
+            serde_json::from_value(json!({
+                "contract_address": "0x1111111111111111111111111111111111111111",
+                "call": { "function_name": function_name, "parameters": [] },
+                "allow_failure": true
+            }))
+            .unwrap()
+        };
+        let calls = [
+            batch_call("totalSupply()(uint256)"),
+            batch_call("bogus"),
+            batch_call("decimals()(uint8)"),
+            batch_call("symbol()(string)"),
+        ];
+        let prepared = |function_name: &str| {
+            let function = Function::parse(function_name).unwrap();
+            Ok((Address::repeat_byte(0x11), function, Bytes::new()))
+        };
+        let prepared = vec![
+            prepared("totalSupply()(uint256)"),
+            Err("Invalid function name".to_string()),
+            prepared("decimals()(uint8)"),
+            prepared("symbol()(string)"),
+        ];
+
+        // The call that could not be prepared was not sent, so three results come back
+        let revert = Function::parse("function Error(string)")
+            .unwrap()
+            .abi_encode_input(&[DynSolValue::String("Paused".into())])
+            .unwrap();
+        let result = |success: bool, data: Vec<u8>| {
+            DynSolValue::Tuple(vec![DynSolValue::Bool(success), DynSolValue::Bytes(data)])
+        };
+        let output = aggregate3
+            .abi_encode_output(&[DynSolValue::Array(vec![
+                result(true, U256::from(1000).to_be_bytes::<32>().to_vec()),
+                result(true, U256::from(18).to_be_bytes::<32>().to_vec()),
+                result(false, revert),
+            ])])
+            .unwrap();
+
+        let returned = ContractManager::aggregate3_results(&aggregate3, &output).unwrap();
+        let results = manager
+            .batch_results(&calls, prepared.clone(), returned.clone())
+            .unwrap();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].result, Some(json!("1000")));
+        assert!(!results[1].success);
+        assert_eq!(results[1].error.as_deref(), Some("Invalid function name"));
+        assert_eq!(results[2].result, Some(json!("18")));
+        assert!(!results[3].success);
+        assert_eq!(
+            results[3].error.as_deref(),
+            Some("Execution reverted: Paused")
+        );
+
+        // Fewer results than prepared calls is an error, not a misalignment
+        let error = manager
+            .batch_results(&calls, prepared, returned[..2].to_vec())
+            .unwrap_err();
+        assert!(error.to_string().contains("fewer results"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_call_at_pinned_block() {
+        use crate::ethereum::test_rpc::{block, rpc_url, BLOCK_NUMBER};
+
+        // The multicall only answers at the block "latest" was pinned to
+        let url = rpc_url(|method, params| match method {
+            "eth_getBlockByNumber" if params[0] == "latest" => Ok(block(json!([]))),
+            "eth_call" if params[1] == "0x4d2" => {
+                let aggregate3 = Function::parse(AGGREGATE3_SIGNATURE).unwrap();
+                let output = aggregate3
+                    .abi_encode_output(&[DynSolValue::Array(vec![DynSolValue::Tuple(vec![
+                        DynSolValue::Bool(true),
+                        DynSolValue::Bytes(U256::from(42).to_be_bytes::<32>().to_vec()),
+                    ])])])
+                    .unwrap();
+                Ok(json!(Bytes::from(output)))
+            }
+            _ => Err(format!("unexpected {} {}", method, params)),
+        })
+        .await;
+        let mut config = crate::config::Config::default();
+        for network in config.networks.values_mut() {
+            network.rpc_url = url.clone();
+        }
+        let mut manager = ContractManager::new(ProviderManager::new(config).unwrap());
+        let calls: Vec<BatchCall> = serde_json::from_value(json!([{
+            "contract_address": "0x1111111111111111111111111111111111111111",
+            "call": { "function_name": "totalSupply()(uint256)", "parameters": [] },
+            "allow_failure": true
+        }]))
+        .unwrap();
+
+        let results = manager.batch_call(&calls, None, None).await.unwrap();
+        assert_eq!(results[0].result, Some(json!("42")));
+        let block = results[0].block.as_ref().unwrap();
+        assert_eq!(block.number, Some(BLOCK_NUMBER));
+        assert_eq!(block.tag.as_deref(), Some("latest"));
+    }
+
+    #[test]
+    fn test_event_cursor_round_trip() {
+        let event = event_abi().event("Transfer").unwrap()[0].clone();
+        let from = json!({ "from": "0x0000000000000000000000000000000000000001" });
+        let filter = |address: Address, indexed_args: Option<&Value>| {
+            let mut filter = Filter::new().address(address);
+            filter.topics = ContractManager::event_topic_filters(&event, indexed_args).unwrap();
+            filter
+        };
+        let token = Address::repeat_byte(0x11);
+
+        let cursor = ContractManager::format_event_cursor(
+            18_000_000,
+            3,
+            18_500_000,
+            &filter(token, Some(&from)),
+        );
+        assert_eq!(
+            ContractManager::parse_event_cursor(&cursor, &filter(token, Some(&from))).unwrap(),
+            (18_000_000, 3, 18_500_000)
+        );
+
+        // A cursor only resumes the query it came from
+        let error = ContractManager::parse_event_cursor(&cursor, &filter(token, None)).unwrap_err();
+        assert!(error.to_string().contains("different contract"));
+        let other = Address::repeat_byte(0x22);
+        assert!(ContractManager::parse_event_cursor(&cursor, &filter(other, Some(&from))).is_err());
+
+        let any = filter(token, None);
+        assert!(ContractManager::parse_event_cursor("18000000", &any).is_err());
+        assert!(ContractManager::parse_event_cursor("a:b:c:d", &any).is_err());
+        assert!(ContractManager::parse_event_cursor("1:2:3", &any).is_err());
+        assert!(ContractManager::parse_event_cursor("1:2:3:4:5", &any).is_err());
+    }
+}
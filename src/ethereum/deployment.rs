@@ -0,0 +1,407 @@
+use crate::ethereum::explorer::ExplorerApi;
+use alloy::{
+    consensus::Transaction,
+    eips::{BlockId, BlockNumberOrTag},
+    network::TransactionResponse,
+    primitives::{Address, B256},
+    providers::{Provider, RootProvider},
+    rpc::types::BlockTransactionsKind,
+    transports::http::{Client, Http},
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::str::FromStr;
+
+/// Where and by whom a contract was deployed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deployment {
+    pub block: u64,
+    pub transaction_hash: Option<B256>,
+    /// Account that created the contract: the deployer, or the factory for internal creations
+    pub creator: Option<Address>,
+}
+
+/// Find a contract's deployment, asking the explorer first and otherwise searching the chain
+pub async fn find_deployment(
+    provider: &RootProvider<Http<Client>>,
+    explorer: Option<&ExplorerApi>,
+    address: Address,
+) -> Result<Deployment> {
+    if let Some(explorer) = explorer {
+        match explorer_deployment(provider, explorer, address).await {
+            Ok(deployment) => return Ok(deployment),
+            Err(e) => tracing::debug!("Explorer has no creation data for {}: {}", address, e),
+        }
+    }
+
+    let block = find_deployment_block(provider, address).await?;
+    let (transaction_hash, creator) = match find_creation(provider, address, block).await {
+        Ok(Some((hash, creator))) => (Some(hash), Some(creator)),
+        Ok(None) => (None, None),
+        Err(e) => {
+            tracing::debug!("Creation transaction of {} not found: {}", address, e);
+            (None, None)
+        }
+    };
+
+    Ok(Deployment {
+        block,
+        transaction_hash,
+        creator,
+    })
+}
+
+/// Deployment from the explorer's `getcontractcreation` endpoint
+async fn explorer_deployment(
+    provider: &RootProvider<Http<Client>>,
+    explorer: &ExplorerApi,
+    address: Address,
+) -> Result<Deployment> {
+    let response = explorer
+        .get(
+            "contract",
+            "getcontractcreation",
+            &[("contractaddresses", &format!("{:?}", address))],
+        )
+        .await?;
+    let mut deployment = parse_contract_creation(&response, explorer.name())?;
+
+    // Not every explorer reports the block, but the receipt has it
+    if deployment.block == 0 {
+        if let Some(hash) = deployment.transaction_hash {
+            let receipt = provider
+                .get_transaction_receipt(hash)
+                .await
+                .map_err(|e| anyhow!("Failed to fetch creation receipt: {}", e))?
+                .ok_or_else(|| anyhow!("Creation transaction {} not found", hash))?;
+            deployment.block = receipt
+                .block_number
+                .ok_or_else(|| anyhow!("Creation transaction {} is pending", hash))?;
+        }
+    }
+    Ok(deployment)
+}
+
+/// Binary search for the first block with code at the address. Needs historical state, so
+/// pruned nodes fail with an error rather than giving a wrong answer.
+async fn find_deployment_block(
+    provider: &RootProvider<Http<Client>>,
+    address: Address,
+) -> Result<u64> {
+    let latest = provider
+        .get_block_number()
+        .await
+        .map_err(|e| anyhow!("Failed to fetch latest block: {}", e))?;
+    if !has_code_at(provider, address, latest).await? {
+        return Err(anyhow!("No contract at {:?}", address));
+    }
+    if has_code_at(provider, address, 0).await? {
+        return Ok(0);
+    }
+
+    // No code at `low`, code at `high`
+    let (mut low, mut high) = (0, latest);
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        if has_code_at(provider, address, middle).await? {
+            high = middle;
+        } else {
+            low = middle;
+        }
+    }
+    Ok(high)
+}
+
+async fn has_code_at(
+    provider: &RootProvider<Http<Client>>,
+    address: Address,
+    block: u64,
+) -> Result<bool> {
+    let code = provider
+        .get_code_at(address)
+        .block_id(BlockId::number(block))
+        .await
+        .map_err(|e| anyhow!("Failed to fetch bytecode at block {}: {}", block, e))?;
+    Ok(!code.is_empty())
+}
+
+/// Find the transaction that created the contract in its deployment block, and its creator.
+/// Direct deployments are matched by their CREATE address; contracts created by factories are
+/// found through `trace_block`, or `debug_traceBlockByNumber` on nodes without the trace API.
+async fn find_creation(
+    provider: &RootProvider<Http<Client>>,
+    address: Address,
+    block: u64,
+) -> Result<Option<(B256, Address)>> {
+    let transactions = provider
+        .get_block_by_number(block.into(), BlockTransactionsKind::Full)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch block {}: {}", block, e))?
+        .ok_or_else(|| anyhow!("Block {} not found", block))?
+        .transactions;
+
+    for tx in transactions.txns() {
+        let is_deployment = TransactionResponse::to(tx).is_none();
+        if is_deployment && tx.from().create(Transaction::nonce(tx)) == address {
+            return Ok(Some((tx.tx_hash(), tx.from())));
+        }
+    }
+
+    let block = BlockNumberOrTag::Number(block);
+    match provider
+        .raw_request::<_, Value>("trace_block".into(), (block,))
+        .await
+    {
+        Ok(traces) => return Ok(parity_creation(&traces, address)),
+        Err(e) => tracing::debug!("trace_block unavailable, trying debug traces: {}", e),
+    }
+
+    let traces: Value = provider
+        .raw_request(
+            "debug_traceBlockByNumber".into(),
+            (block, serde_json::json!({ "tracer": "callTracer" })),
+        )
+        .await
+        .map_err(|e| anyhow!("Failed to trace block {}: {}", block, e))?;
+    Ok(geth_creation(&traces, address))
+}
+
+/// Parse a `getcontractcreation` response; the block is 0 when the explorer omits it
+fn parse_contract_creation(response: &Value, explorer: &str) -> Result<Deployment> {
+    if response["status"] != "1" {
+        let message = response["result"]
+            .as_str()
+            .or_else(|| response["message"].as_str())
+            .unwrap_or("Unknown error");
+        return Err(anyhow!("{} API error: {}", explorer, message));
+    }
+
+    let entry = &response["result"][0];
+    let transaction_hash = entry["txHash"]
+        .as_str()
+        .and_then(|hash| B256::from_str(hash).ok())
+        .ok_or_else(|| anyhow!("{} returned no creation transaction", explorer))?;
+    let block = match &entry["blockNumber"] {
+        Value::String(number) => number.parse().unwrap_or_default(),
+        number => number.as_u64().unwrap_or_default(),
+    };
+
+    Ok(Deployment {
+        block,
+        transaction_hash: Some(transaction_hash),
+        creator: parse_address(&entry["contractCreator"]),
+    })
+}
+
+/// Creation of `address` in Parity-style `trace_block` output
+fn parity_creation(traces: &Value, address: Address) -> Option<(B256, Address)> {
+    traces.as_array()?.iter().find_map(|trace| {
+        if trace["type"] != "create" || parse_address(&trace["result"]["address"])? != address {
+            return None;
+        }
+        let hash = B256::from_str(trace["transactionHash"].as_str()?).ok()?;
+        Some((hash, parse_address(&trace["action"]["from"])?))
+    })
+}
+
+/// Creation of `address` in Geth `callTracer` block traces
+fn geth_creation(traces: &Value, address: Address) -> Option<(B256, Address)> {
+    traces.as_array()?.iter().find_map(|trace| {
+        let creator = create_frame(&trace["result"], address)?;
+        let hash = B256::from_str(trace["txHash"].as_str()?).ok()?;
+        Some((hash, creator))
+    })
+}
+
+/// Sender of the CREATE or CREATE2 frame that deployed `address`, searching nested calls
+fn create_frame(frame: &Value, address: Address) -> Option<Address> {
+    let is_create = matches!(frame["type"].as_str(), Some("CREATE" | "CREATE2"));
+    if is_create && parse_address(&frame["to"]) == Some(address) {
+        return parse_address(&frame["from"]);
+    }
+    frame["calls"]
+        .as_array()?
+        .iter()
+        .find_map(|call| create_frame(call, address))
+}
+
+fn parse_address(value: &Value) -> Option<Address> {
+    Address::from_str(value.as_str()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ethereum::test_rpc::{block, block_number, rpc_provider, BLOCK_HASH};
+    use serde_json::json;
+
+    const CONTRACT: &str = "0x5fbdb2315678afecb367f032d93f642f64180aa3";
+    const FACTORY: &str = "0xe7f1725e7734ce288f8367e1bb143e90bb3f0512";
+    const TX_HASH: &str = "0x0000000000000000000000000000000000000000000000000000000000000abc";
+    const DEPLOYER: &str = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+
+    fn legacy_transaction(to: Option<&str>, nonce: u64) -> Value {
+        json!({
+            "type": "0x0",
+            "hash": TX_HASH,
+            "nonce": format!("{:#x}", nonce),
+            "blockHash": BLOCK_HASH,
+            "blockNumber": "0x4d2",
+            "transactionIndex": "0x0",
+            "from": DEPLOYER,
+            "to": to,
+            "value": "0x0",
+            "gasPrice": "0x1",
+            "gas": "0x5208",
+            "input": "0x",
+            "chainId": "0x1",
+            "v": "0x25",
+            "r": "0x1",
+            "s": "0x1"
+        })
+    }
+
+    #[tokio::test]
+    async fn test_find_deployment_block() {
+        // The contract is deployed in block 1234 of 5000
+        let provider = rpc_provider(|method, params| match method {
+            "eth_blockNumber" => Ok(json!("0x1388")),
+            "eth_getCode" if block_number(&params[1]) >= 1234 => Ok(json!("0x6000")),
+            "eth_getCode" => Ok(json!("0x")),
+            _ => Err(format!("method {} not found", method)),
+        })
+        .await;
+        let contract = Address::from_str(CONTRACT).unwrap();
+        assert_eq!(
+            find_deployment_block(&provider, contract).await.unwrap(),
+            1234
+        );
+
+        let provider = rpc_provider(|method, _| match method {
+            "eth_blockNumber" => Ok(json!("0x1388")),
+            "eth_getCode" => Ok(json!("0x")),
+            _ => Err(format!("method {} not found", method)),
+        })
+        .await;
+        let error = find_deployment_block(&provider, contract)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("No contract"));
+
+        // Pruned nodes fail rather than giving a wrong block
+        let provider = rpc_provider(|method, params| match method {
+            "eth_blockNumber" => Ok(json!("0x1388")),
+            "eth_getCode" if block_number(&params[1]) == 5000 => Ok(json!("0x6000")),
+            "eth_getCode" => Err("missing trie node".to_string()),
+            _ => Err(format!("method {} not found", method)),
+        })
+        .await;
+        assert!(find_deployment_block(&provider, contract).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_find_creation() {
+        let deployer = Address::from_str(DEPLOYER).unwrap();
+        let factory = Address::from_str(FACTORY).unwrap();
+        let hash = B256::from_str(TX_HASH).unwrap();
+
+        // A direct deployment is matched by its CREATE address
+        let provider = rpc_provider(|method, _| match method {
+            "eth_getBlockByNumber" => Ok(block(json!([legacy_transaction(None, 7)]))),
+            _ => Err(format!("method {} not found", method)),
+        })
+        .await;
+        let created = deployer.create(7);
+        assert_eq!(
+            find_creation(&provider, created, 1234).await.unwrap(),
+            Some((hash, deployer))
+        );
+
+        // A factory creation is found in debug traces when trace_block is unavailable
+        let provider = rpc_provider(|method, _| match method {
+            "eth_getBlockByNumber" => Ok(block(json!([legacy_transaction(Some(FACTORY), 7)]))),
+            "debug_traceBlockByNumber" => Ok(json!([{
+                "txHash": TX_HASH,
+                "result": {
+                    "type": "CALL",
+                    "from": DEPLOYER,
+                    "to": FACTORY,
+                    "calls": [{ "type": "CREATE2", "from": FACTORY, "to": CONTRACT }]
+                }
+            }])),
+            _ => Err(format!("method {} not found", method)),
+        })
+        .await;
+        let contract = Address::from_str(CONTRACT).unwrap();
+        assert_eq!(
+            find_creation(&provider, contract, 1234).await.unwrap(),
+            Some((hash, factory))
+        );
+        assert_eq!(find_creation(&provider, created, 1234).await.unwrap(), None);
+
+        let provider = rpc_provider(|method, _| match method {
+            "eth_getBlockByNumber" => Ok(Value::Null),
+            _ => Err(format!("method {} not found", method)),
+        })
+        .await;
+        assert!(find_creation(&provider, contract, 1234).await.is_err());
+    }
+
+    #[test]
+    fn test_parse_contract_creation() {
+        let response = json!({
+            "status": "1",
+            "message": "OK",
+            "result": [{
+                "contractAddress": CONTRACT,
+                "contractCreator": FACTORY,
+                "txHash": TX_HASH,
+                "blockNumber": "19000000"
+            }]
+        });
+        let deployment = parse_contract_creation(&response, "Etherscan").unwrap();
+        assert_eq!(deployment.block, 19_000_000);
+        assert_eq!(deployment.creator, Address::from_str(FACTORY).ok());
+        assert_eq!(deployment.transaction_hash, B256::from_str(TX_HASH).ok());
+
+        let missing = json!({ "status": "0", "message": "No data found", "result": null });
+        assert!(parse_contract_creation(&missing, "Etherscan").is_err());
+    }
+
+    #[test]
+    fn test_trace_creations() {
+        let contract = Address::from_str(CONTRACT).unwrap();
+        let factory = Address::from_str(FACTORY).unwrap();
+        let hash = B256::from_str(TX_HASH).unwrap();
+
+        let parity = json!([
+            { "type": "call", "action": { "from": FACTORY }, "transactionHash": TX_HASH },
+            {
+                "type": "create",
+                "action": { "from": FACTORY },
+                "result": { "address": CONTRACT },
+                "transactionHash": TX_HASH
+            }
+        ]);
+        assert_eq!(parity_creation(&parity, contract), Some((hash, factory)));
+
+        // The creation is nested two calls deep in the factory transaction
+        let geth = json!([{
+            "txHash": TX_HASH,
+            "result": {
+                "type": "CALL",
+                "from": "0x0000000000000000000000000000000000000001",
+                "to": FACTORY,
+                "calls": [{
+                    "type": "DELEGATECALL",
+                    "from": FACTORY,
+                    "to": "0x0000000000000000000000000000000000000002",
+                    "calls": [{ "type": "CREATE2", "from": FACTORY, "to": CONTRACT }]
+                }]
+            }
+        }]);
+        assert_eq!(geth_creation(&geth, contract), Some((hash, factory)));
+        assert_eq!(geth_creation(&geth, factory), None);
+    }
+}
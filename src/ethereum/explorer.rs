@@ -0,0 +1,110 @@
+use crate::config::NetworkConfig;
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde_json::Value;
+
+/// Etherscan V2 endpoint, serving every chain Etherscan supports through a `chainid` parameter
+pub const ETHERSCAN_V2_URL: &str = "https://api.etherscan.io/v2/api";
+
+/// An Etherscan-compatible explorer API for one network
+#[derive(Debug, Clone)]
+pub struct ExplorerApi {
+    client: Client,
+    url: String,
+    chain_id: u64,
+    api_key: Option<String>,
+}
+
+impl ExplorerApi {
+    /// The network's `explorer_api_url` and `explorer_api_key`, defaulting to the Etherscan V2
+    /// endpoint and the given key
+    pub fn for_network(
+        client: Client,
+        network_config: &NetworkConfig,
+        default_api_key: Option<&str>,
+    ) -> Self {
+        Self {
+            client,
+            url: network_config
+                .explorer_api_url
+                .clone()
+                .unwrap_or_else(|| ETHERSCAN_V2_URL.to_string()),
+            chain_id: network_config.chain_id,
+            api_key: network_config
+                .explorer_api_key
+                .clone()
+                .or_else(|| default_api_key.map(String::from)),
+        }
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    /// Name used in error messages: Etherscan, or the host of a custom explorer
+    pub fn name(&self) -> &str {
+        if self.url == ETHERSCAN_V2_URL {
+            return "Etherscan";
+        }
+        let url = self.url.split("://").nth(1).unwrap_or(&self.url);
+        url.split('/').next().unwrap_or(url)
+    }
+
+    /// Query parameters for a request, including the chain ID and API key
+    fn params<'a>(
+        &'a self,
+        module: &'a str,
+        action: &'a str,
+        params: &[(&'a str, &'a str)],
+    ) -> Vec<(&'a str, String)> {
+        let mut query = vec![
+            ("chainid", self.chain_id.to_string()),
+            ("module", module.to_string()),
+            ("action", action.to_string()),
+        ];
+        query.extend(params.iter().map(|(k, v)| (*k, v.to_string())));
+        if let Some(api_key) = &self.api_key {
+            query.push(("apikey", api_key.clone()));
+        }
+        query
+    }
+
+    /// Send a request and return the response as is; callers check `status` and `result`
+    pub async fn get(&self, module: &str, action: &str, params: &[(&str, &str)]) -> Result<Value> {
+        self.client
+            .get(&self.url)
+            .query(&self.params(module, action, params))
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to fetch from {}: {}", self.name(), e))?
+            .json()
+            .await
+            .map_err(|e| anyhow!("Failed to parse {} response: {}", self.name(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_explorer_for_network() {
+        let mut network_config = Config::default().networks["polygon"].clone();
+
+        let explorer = ExplorerApi::for_network(Client::new(), &network_config, Some("global"));
+        assert_eq!(explorer.url, ETHERSCAN_V2_URL);
+        assert_eq!(explorer.name(), "Etherscan");
+        let params = explorer.params("contract", "getabi", &[("address", "0xabc")]);
+        assert!(params.contains(&("chainid", "137".to_string())));
+        assert!(params.contains(&("apikey", "global".to_string())));
+
+        // A network's own explorer and key take precedence
+        network_config.explorer_api_url = Some("https://explorer.example/api".to_string());
+        network_config.explorer_api_key = Some("own".to_string());
+        let explorer = ExplorerApi::for_network(Client::new(), &network_config, Some("global"));
+        assert_eq!(explorer.name(), "explorer.example");
+        let params = explorer.params("contract", "getabi", &[]);
+        assert!(params.contains(&("apikey", "own".to_string())));
+    }
+}
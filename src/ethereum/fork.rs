@@ -0,0 +1,707 @@
+use crate::ethereum::{
+    overrides::{Erc20Mapping, MappingSlot},
+    CallBlock,
+};
+use alloy::{
+    eips::BlockId,
+    json_abi::JsonAbi,
+    primitives::{Address, Bytes, Log, B256, U256},
+    providers::{Provider, RootProvider},
+    rpc::types::{state::StateOverride, BlockTransactionsKind},
+    transports::http::{Client, Http},
+};
+use anyhow::{anyhow, Result};
+use revm::{
+    db::CacheDB,
+    primitives::{
+        AccountInfo, BlobExcessGasAndPrice, BlockEnv, Bytecode, Env, ExecutionResult, SpecId,
+        TxEnv, TxKind,
+    },
+    DatabaseRef, Evm,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+/// State a fork session read from the network, with the ABIs and ERC-20 mapping slots it
+/// resolved. Saved to a file, it lets the session be created again without the network.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForkRecording {
+    pub chain_id: u64,
+    pub block: ForkBlock,
+    #[serde(default)]
+    accounts: BTreeMap<Address, RecordedAccount>,
+    #[serde(default)]
+    storage: BTreeMap<Address, BTreeMap<U256, U256>>,
+    #[serde(default)]
+    block_hashes: BTreeMap<u64, B256>,
+    #[serde(default)]
+    abis: BTreeMap<Address, JsonAbi>,
+    /// Keyed by token and mapping name, e.g. `0x..._balance`
+    #[serde(default)]
+    erc20_mappings: BTreeMap<String, MappingSlot>,
+}
+
+/// Header fields of the block a session is forked from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForkBlock {
+    pub number: u64,
+    pub hash: B256,
+    pub timestamp: u64,
+    pub gas_limit: u64,
+    pub base_fee: Option<u64>,
+    pub coinbase: Address,
+    pub difficulty: U256,
+    pub prevrandao: B256,
+    pub excess_blob_gas: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedAccount {
+    balance: U256,
+    nonce: u64,
+    code: Bytes,
+}
+
+/// A transaction or call run on a fork; the sender is not checked, so any account can send
+#[derive(Debug, Clone)]
+pub struct ForkTransaction {
+    pub from: Address,
+    pub to: Address,
+    pub data: Bytes,
+    pub value: U256,
+    /// Defaults to the block gas limit
+    pub gas_limit: Option<u64>,
+    /// Defaults to zero, so that senders need no ETH for gas
+    pub gas_price: Option<U256>,
+}
+
+/// Outcome of a transaction or call run on a fork
+#[derive(Debug, Clone)]
+pub struct ForkExecution {
+    pub success: bool,
+    pub gas_used: u64,
+    /// Return data, or revert data when the transaction reverted
+    pub output: Bytes,
+    pub logs: Vec<Log>,
+    /// Why execution halted, when it failed without reverting (out of gas, invalid opcode, ...)
+    pub halt_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ForkSessionInfo {
+    pub session_id: String,
+    pub network: Option<String>,
+    pub chain_id: u64,
+    pub block: CallBlock,
+    /// Transactions applied so far
+    pub transactions: usize,
+    /// Whether state missing from the recording is an error instead of being fetched
+    pub offline: bool,
+    /// Accounts and storage slots read from the network or the recording so far
+    pub accounts_loaded: usize,
+    pub storage_slots_loaded: usize,
+}
+
+/// State of the fork block, fetched from the provider the first time it's read and recorded
+#[derive(Debug)]
+struct ForkDb {
+    /// None when the session only reads its recording
+    provider: Option<RootProvider<Http<Client>>>,
+    recording: RwLock<ForkRecording>,
+}
+
+/// An in-process EVM forked from a block. Transactions applied to the session build on each
+/// other; its state lives in memory until the session is dropped.
+#[derive(Debug)]
+pub struct ForkSession {
+    pub network: Option<String>,
+    db: CacheDB<ForkDb>,
+    transactions: usize,
+}
+
+impl ForkBlock {
+    /// Fetch the header of a mined block
+    pub async fn fetch(provider: &RootProvider<Http<Client>>, block: BlockId) -> Result<Self> {
+        if block == BlockId::pending() {
+            return Err(anyhow!(
+                "A fork session needs a mined block, not the pending block"
+            ));
+        }
+        let header = match block {
+            BlockId::Hash(hash) => {
+                provider
+                    .get_block_by_hash(hash.block_hash, BlockTransactionsKind::Hashes)
+                    .await
+            }
+            BlockId::Number(number) => {
+                provider
+                    .get_block_by_number(number, BlockTransactionsKind::Hashes)
+                    .await
+            }
+        }
+        .map_err(|e| anyhow!("Failed to fetch block {}: {}", block, e))?
+        .ok_or_else(|| anyhow!("Block {} not found", block))?
+        .header;
+
+        Ok(Self {
+            number: header.number,
+            hash: header.hash,
+            timestamp: header.timestamp,
+            gas_limit: header.gas_limit,
+            base_fee: header.base_fee_per_gas,
+            coinbase: header.beneficiary,
+            difficulty: header.difficulty,
+            prevrandao: header.mix_hash,
+            excess_blob_gas: header.excess_blob_gas,
+        })
+    }
+}
+
+impl ForkRecording {
+    pub fn new(chain_id: u64, block: ForkBlock) -> Self {
+        Self {
+            chain_id,
+            block,
+            accounts: BTreeMap::new(),
+            storage: BTreeMap::new(),
+            block_hashes: BTreeMap::new(),
+            abis: BTreeMap::new(),
+            erc20_mappings: BTreeMap::new(),
+        }
+    }
+}
+
+impl ForkDb {
+    /// Provider to fetch state missing from the recording with
+    fn provider(&self, what: impl FnOnce() -> String) -> Result<&RootProvider<Http<Client>>> {
+        self.provider.as_ref().ok_or_else(|| {
+            anyhow!(
+                "{} is not in the recording, and the session is offline",
+                what()
+            )
+        })
+    }
+
+    fn block(&self) -> BlockId {
+        BlockId::number(self.recording.read().unwrap().block.number)
+    }
+}
+
+impl DatabaseRef for ForkDb {
+    type Error = anyhow::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>> {
+        let recorded = self
+            .recording
+            .read()
+            .unwrap()
+            .accounts
+            .get(&address)
+            .cloned();
+        let account = match recorded {
+            Some(account) => account,
+            None => {
+                let provider = self.provider(|| format!("Account {:?}", address))?;
+                let block = self.block();
+                let (balance, nonce, code) = block_on(async {
+                    tokio::try_join!(
+                        provider.get_balance(address).block_id(block),
+                        provider.get_transaction_count(address).block_id(block),
+                        provider.get_code_at(address).block_id(block),
+                    )
+                })
+                .map_err(|e| anyhow!("Failed to fetch account {:?}: {}", address, e))?;
+                let account = RecordedAccount {
+                    balance,
+                    nonce,
+                    code,
+                };
+                let mut recording = self.recording.write().unwrap();
+                recording.accounts.insert(address, account.clone());
+                account
+            }
+        };
+
+        let code = Bytecode::new_raw(account.code);
+        Ok(Some(AccountInfo::new(
+            account.balance,
+            account.nonce,
+            code.hash_slow(),
+            code,
+        )))
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode> {
+        // Code is loaded together with its account, so any hash asked for is unknown
+        Err(anyhow!("No contract code with hash {}", code_hash))
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256> {
+        let recorded = self
+            .recording
+            .read()
+            .unwrap()
+            .storage
+            .get(&address)
+            .and_then(|slots| slots.get(&index).copied());
+        if let Some(value) = recorded {
+            return Ok(value);
+        }
+
+        let provider = self.provider(|| format!("Storage slot {:#x} of {:?}", index, address))?;
+        let block = self.block();
+        let value = block_on(async {
+            provider
+                .get_storage_at(address, index)
+                .block_id(block)
+                .await
+        })
+        .map_err(|e| anyhow!("Failed to fetch slot {:#x} of {:?}: {}", index, address, e))?;
+        let mut recording = self.recording.write().unwrap();
+        recording
+            .storage
+            .entry(address)
+            .or_default()
+            .insert(index, value);
+        Ok(value)
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256> {
+        let recorded = self
+            .recording
+            .read()
+            .unwrap()
+            .block_hashes
+            .get(&number)
+            .copied();
+        match recorded {
+            Some(hash) => Ok(hash),
+            None => {
+                let provider = self.provider(|| format!("Hash of block {}", number))?;
+                let block = block_on(
+                    provider.get_block_by_number(number.into(), BlockTransactionsKind::Hashes),
+                )
+                .map_err(|e| anyhow!("Failed to fetch block {}: {}", number, e))?
+                .ok_or_else(|| anyhow!("Block {} not found", number))?;
+                let mut recording = self.recording.write().unwrap();
+                recording.block_hashes.insert(number, block.header.hash);
+                Ok(block.header.hash)
+            }
+        }
+    }
+}
+
+impl ForkSession {
+    /// Start a session on a recording. With a provider, state missing from the recording is
+    /// fetched from it and added to the recording; without one, reading it is an error.
+    pub fn new(
+        recording: ForkRecording,
+        provider: Option<RootProvider<Http<Client>>>,
+        network: Option<String>,
+    ) -> Self {
+        let db = ForkDb {
+            provider,
+            recording: RwLock::new(recording),
+        };
+        Self {
+            network,
+            db: CacheDB::new(db),
+            transactions: 0,
+        }
+    }
+
+    pub fn recording(&self) -> ForkRecording {
+        self.db.db.recording.read().unwrap().clone()
+    }
+
+    pub fn block(&self) -> ForkBlock {
+        self.db.db.recording.read().unwrap().block.clone()
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.db.db.provider.is_none()
+    }
+
+    pub fn abi(&self, address: Address) -> Option<JsonAbi> {
+        self.db
+            .db
+            .recording
+            .read()
+            .unwrap()
+            .abis
+            .get(&address)
+            .cloned()
+    }
+
+    pub fn record_abi(&self, address: Address, abi: JsonAbi) {
+        let mut recording = self.db.db.recording.write().unwrap();
+        recording.abis.insert(address, abi);
+    }
+
+    pub fn erc20_mapping(&self, token: Address, mapping: Erc20Mapping) -> Option<MappingSlot> {
+        let key = format!("{:?}_{}", token, mapping.name());
+        let recording = self.db.db.recording.read().unwrap();
+        recording.erc20_mappings.get(&key).copied()
+    }
+
+    pub fn record_erc20_mapping(&self, token: Address, mapping: Erc20Mapping, slot: MappingSlot) {
+        let key = format!("{:?}_{}", token, mapping.name());
+        let mut recording = self.db.db.recording.write().unwrap();
+        recording.erc20_mappings.insert(key, slot);
+    }
+
+    pub fn info(&self, session_id: &str) -> ForkSessionInfo {
+        let recording = self.db.db.recording.read().unwrap();
+        ForkSessionInfo {
+            session_id: session_id.to_string(),
+            network: self.network.clone(),
+            chain_id: recording.chain_id,
+            block: CallBlock {
+                number: Some(recording.block.number),
+                hash: Some(format!("{:?}", recording.block.hash)),
+                tag: None,
+            },
+            transactions: self.transactions,
+            offline: self.is_offline(),
+            accounts_loaded: recording.accounts.len(),
+            storage_slots_loaded: recording.storage.values().map(|slots| slots.len()).sum(),
+        }
+    }
+
+    /// Write state overrides into the session, where they stay for later transactions
+    pub fn apply_overrides(&mut self, state_override: &StateOverride) -> Result<()> {
+        for (address, account) in state_override.iter() {
+            let address = *address;
+            let mut info = self.db.basic_ref(address)?.unwrap_or_default();
+            if let Some(balance) = account.balance {
+                info.balance = balance;
+            }
+            if let Some(nonce) = account.nonce {
+                info.nonce = nonce;
+            }
+            if let Some(code) = &account.code {
+                let code = Bytecode::new_raw(code.clone());
+                info.code_hash = code.hash_slow();
+                info.code = Some(code);
+            }
+            self.db.insert_account_info(address, info);
+
+            if let Some(state) = &account.state {
+                let slots = state
+                    .iter()
+                    .map(|(slot, value)| {
+                        (U256::from_be_bytes(slot.0), U256::from_be_bytes(value.0))
+                    })
+                    .collect();
+                self.db.replace_account_storage(address, slots)?;
+            }
+            if let Some(state_diff) = &account.state_diff {
+                for (slot, value) in state_diff {
+                    self.db.insert_account_storage(
+                        address,
+                        U256::from_be_bytes(slot.0),
+                        U256::from_be_bytes(value.0),
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Run a transaction on the session's state. Its changes are kept when `commit` is set, and
+    /// thrown away otherwise, as for a read-only call.
+    pub fn execute(
+        &mut self,
+        transaction: &ForkTransaction,
+        commit: bool,
+    ) -> Result<ForkExecution> {
+        let env = self.env(transaction);
+        let mut evm = Evm::builder()
+            .with_db(&mut self.db)
+            .with_env(env)
+            .with_spec_id(SpecId::CANCUN)
+            .build();
+        let result = if commit {
+            evm.transact_commit()
+        } else {
+            evm.transact().map(|result| result.result)
+        }
+        .map_err(|e| anyhow!("Fork execution failed: {}", e))?;
+        drop(evm);
+        if commit {
+            self.transactions += 1;
+        }
+
+        Ok(match result {
+            ExecutionResult::Success {
+                gas_used,
+                logs,
+                output,
+                ..
+            } => ForkExecution {
+                success: true,
+                gas_used,
+                output: output.into_data(),
+                logs,
+                halt_reason: None,
+            },
+            ExecutionResult::Revert { gas_used, output } => ForkExecution {
+                success: false,
+                gas_used,
+                output,
+                logs: Vec::new(),
+                halt_reason: None,
+            },
+            ExecutionResult::Halt { reason, gas_used } => ForkExecution {
+                success: false,
+                gas_used,
+                output: Bytes::new(),
+                logs: Vec::new(),
+                halt_reason: Some(format!("{:?}", reason)),
+            },
+        })
+    }
+
+    fn env(&self, transaction: &ForkTransaction) -> Box<Env> {
+        let recording = self.db.db.recording.read().unwrap();
+        let block = &recording.block;
+        let mut env = Env::default();
+        env.cfg.chain_id = recording.chain_id;
+        // As in eth_call: gas may be priced below the base fee, and contracts may send
+        env.cfg.disable_base_fee = true;
+        env.cfg.disable_eip3607 = true;
+        env.block = BlockEnv {
+            number: U256::from(block.number),
+            coinbase: block.coinbase,
+            timestamp: U256::from(block.timestamp),
+            gas_limit: U256::from(block.gas_limit),
+            basefee: U256::from(block.base_fee.unwrap_or_default()),
+            difficulty: block.difficulty,
+            prevrandao: Some(block.prevrandao),
+            blob_excess_gas_and_price: Some(BlobExcessGasAndPrice::new(
+                block.excess_blob_gas.unwrap_or_default(),
+                false,
+            )),
+        };
+        env.tx = TxEnv {
+            caller: transaction.from,
+            gas_limit: transaction.gas_limit.unwrap_or(block.gas_limit),
+            gas_price: transaction.gas_price.unwrap_or_default(),
+            transact_to: TxKind::Call(transaction.to),
+            value: transaction.value,
+            data: transaction.data.clone(),
+            ..Default::default()
+        };
+        Box::new(env)
+    }
+}
+
+/// Path of a recording in the recordings directory. Only a plain file name is accepted, so that
+/// recordings can't be read from or written to anywhere else.
+pub fn recording_path(dir: &Path, name: &str) -> Result<PathBuf> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(file_name)), None) => Ok(dir.join(file_name)),
+        _ => Err(anyhow!(
+            "Invalid recording name '{}': give a file name, without directories",
+            name
+        )),
+    }
+}
+
+/// Wait for a future from the synchronous EVM, whatever runtime it runs in
+fn block_on<F>(future: F) -> F::Output
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    let runtime = || {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to start a runtime")
+    };
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() != RuntimeFlavor::CurrentThread => {
+            tokio::task::block_in_place(move || handle.block_on(future))
+        }
+        // A current-thread runtime can't block in place, so wait on another thread
+        Ok(_) => std::thread::scope(|scope| {
+            scope
+                .spawn(move || runtime().block_on(future))
+                .join()
+                .expect("Fork state fetch panicked")
+        }),
+        Err(_) => runtime().block_on(future),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ethereum::overrides::MappingLayout;
+    use alloy::rpc::types::state::AccountOverride;
+
+    const COUNTER: &str = "0x0000000000000000000000000000000000001234";
+    const SENDER: &str = "0x00000000000000000000000000000000000000aa";
+
+    fn recording() -> ForkRecording {
+        let block = ForkBlock {
+            number: 100,
+            hash: B256::repeat_byte(1),
+            timestamp: 1_700_000_000,
+            gas_limit: 30_000_000,
+            base_fee: Some(1_000_000_000),
+            coinbase: SENDER.parse().unwrap(),
+            difficulty: U256::ZERO,
+            prevrandao: B256::ZERO,
+            excess_blob_gas: Some(0),
+        };
+        let mut recording = ForkRecording::new(1, block);
+        // Increments slot 0 and returns the new value
+        recording.accounts.insert(
+            COUNTER.parse().unwrap(),
+            RecordedAccount {
+                balance: U256::ZERO,
+                nonce: 1,
+                code: "0x6000546001018060005560005260206000f3".parse().unwrap(),
+            },
+        );
+        recording.accounts.insert(
+            SENDER.parse().unwrap(),
+            RecordedAccount {
+                balance: U256::ZERO,
+                nonce: 0,
+                code: Bytes::new(),
+            },
+        );
+        recording.storage.insert(
+            COUNTER.parse().unwrap(),
+            BTreeMap::from([(U256::ZERO, U256::from(5))]),
+        );
+        recording
+    }
+
+    fn increment() -> ForkTransaction {
+        ForkTransaction {
+            from: SENDER.parse().unwrap(),
+            to: COUNTER.parse().unwrap(),
+            data: Bytes::new(),
+            value: U256::ZERO,
+            gas_limit: None,
+            gas_price: None,
+        }
+    }
+
+    #[test]
+    fn test_transactions_build_on_each_other() {
+        let mut session = ForkSession::new(recording(), None, None);
+        let count = |execution: ForkExecution| {
+            assert!(execution.success);
+            U256::from_be_slice(&execution.output)
+        };
+
+        assert_eq!(
+            count(session.execute(&increment(), true).unwrap()),
+            U256::from(6)
+        );
+        assert_eq!(
+            count(session.execute(&increment(), true).unwrap()),
+            U256::from(7)
+        );
+        // A call sees the state but leaves it alone
+        assert_eq!(
+            count(session.execute(&increment(), false).unwrap()),
+            U256::from(8)
+        );
+        assert_eq!(
+            count(session.execute(&increment(), true).unwrap()),
+            U256::from(8)
+        );
+
+        let info = session.info("fork-1");
+        assert_eq!(info.transactions, 3);
+        assert!(info.offline);
+        // The recording holds what was read from the block, not what the session wrote
+        let counter: Address = COUNTER.parse().unwrap();
+        assert_eq!(
+            session.recording().storage[&counter][&U256::ZERO],
+            U256::from(5)
+        );
+
+        let mut state_override = StateOverride::default();
+        state_override.insert(
+            counter,
+            AccountOverride {
+                state_diff: Some(
+                    [(B256::ZERO, B256::with_last_byte(41))]
+                        .into_iter()
+                        .collect(),
+                ),
+                ..Default::default()
+            },
+        );
+        session.apply_overrides(&state_override).unwrap();
+        assert_eq!(
+            count(session.execute(&increment(), true).unwrap()),
+            U256::from(42)
+        );
+    }
+
+    #[test]
+    fn test_recording_path() {
+        let dir = Path::new("/var/cache/recordings");
+        assert_eq!(
+            recording_path(dir, "swap.json").unwrap(),
+            dir.join("swap.json")
+        );
+        for name in [
+            "",
+            ".",
+            "..",
+            "../swap.json",
+            "/etc/passwd",
+            "nested/swap.json",
+        ] {
+            assert!(recording_path(dir, name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_offline_session() {
+        let mut session = ForkSession::new(recording(), None, None);
+        let mut transaction = increment();
+        transaction.from = Address::repeat_byte(0xbb);
+        let error = session.execute(&transaction, true).unwrap_err().to_string();
+        assert!(error.contains("is not in the recording"), "{}", error);
+
+        // ABIs and ERC-20 mapping slots resolved online are kept for offline sessions
+        let token: Address = COUNTER.parse().unwrap();
+        let abi = JsonAbi::parse(["function increment() returns (uint256)"]).unwrap();
+        let balances = MappingSlot {
+            slot: U256::from(9),
+            layout: MappingLayout::Solidity,
+        };
+        session.record_abi(token, abi.clone());
+        session.record_erc20_mapping(token, Erc20Mapping::Balance { holder: token }, balances);
+
+        // Recordings survive a round trip through JSON, storage keys included
+        let json = serde_json::to_string(&session.recording()).unwrap();
+        let mut session = ForkSession::new(serde_json::from_str(&json).unwrap(), None, None);
+        let execution = session.execute(&increment(), true).unwrap();
+        assert_eq!(U256::from_be_slice(&execution.output), U256::from(6));
+        assert_eq!(session.abi(token), Some(abi));
+        let holder = Address::repeat_byte(0xcc);
+        let mapping = Erc20Mapping::Balance { holder };
+        assert_eq!(session.erc20_mapping(token, mapping), Some(balances));
+        let mapping = Erc20Mapping::Allowance {
+            owner: holder,
+            spender: holder,
+        };
+        assert_eq!(session.erc20_mapping(token, mapping), None);
+    }
+}
@@ -1,6 +1,20 @@
 pub mod abi;
+pub mod abi_provider;
+pub mod artifacts;
 pub mod contract;
+pub mod deployment;
+pub mod explorer;
+pub mod fork;
+pub mod overrides;
 pub mod provider;
+pub mod proxy;
+pub mod selectors;
+pub mod source;
+pub mod standards;
+pub mod storage;
+#[cfg(test)]
+pub mod test_rpc;
+pub mod trace;
 pub mod utils;
 
 use serde::{Deserialize, Serialize};
@@ -12,8 +26,24 @@ pub struct ContractInfo {
     pub abi: serde_json::Value,
     pub bytecode: Option<String>,
     pub deployment_block: Option<u64>,
+    /// Account that created the contract: the deployer, or the factory for internal creations
     pub creator: Option<String>,
+    pub creation_transaction: Option<String>,
     pub verified: bool,
+    /// ABI provider that supplied `abi` (etherscan, sourcify, blockscout, artifacts, manual), or
+    /// `inferred` when it was built from the selectors in the bytecode
+    pub abi_provider: Option<String>,
+    /// Solidity compiler metadata, when the ABI provider has it
+    pub metadata: Option<serde_json::Value>,
+    /// Proxy type and targets, when the contract is a proxy; `abi` then includes the
+    /// implementation ABI
+    pub proxy: Option<proxy::ProxyInfo>,
+    /// Compiler settings, license and source files of a verified contract
+    pub source: Option<source::SourceInfo>,
+    /// Selectors in the bytecode that no known signature matched, when `abi` is inferred
+    pub unknown_selectors: Option<Vec<String>>,
+    /// Standards the contract implements, from ERC-165 probes or its functions
+    pub standards: Vec<standards::DetectedStandard>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,9 +67,39 @@ pub struct EventInfo {
     pub block_number: u64,
     pub transaction_hash: String,
     pub log_index: u64,
+    pub event_name: Option<String>,
+    pub signature: Option<String>,
     pub decoded: Option<serde_json::Value>,
 }
 
+/// Narrows event retrieval to one event and, optionally, to indexed argument values
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventFilter {
+    /// Event name (`Transfer`) or signature (`Transfer(address,address,uint256)`)
+    pub event: Option<String>,
+    /// Indexed argument values keyed by argument name; an array of values matches any of them
+    pub indexed_args: Option<serde_json::Value>,
+}
+
+/// Page size and resume point for event retrieval
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventPagination {
+    /// Maximum number of events to return
+    pub limit: Option<usize>,
+    /// Cursor returned by a previous page
+    pub cursor: Option<String>,
+}
+
+/// One page of events together with the block range it covers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventPage {
+    pub events: Vec<EventInfo>,
+    pub from_block: u64,
+    pub to_block: u64,
+    /// Pass back as `cursor` to fetch the next page; `None` once the range is exhausted
+    pub next_cursor: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionCall {
     pub function_name: String,
@@ -48,6 +108,37 @@ pub struct FunctionCall {
     pub gas_limit: Option<u64>,
     pub gas_price: Option<String>,
     pub value: Option<String>,
+    /// Annotate decoded return values with their Solidity types
+    #[serde(default)]
+    pub include_types: bool,
+    /// Block to run against: a number, a block hash, or a latest, pending, safe, finalized or
+    /// earliest tag; defaults to latest
+    #[serde(default)]
+    pub block: Option<serde_json::Value>,
+    /// State overrides by address: balance, nonce, code, state, state_diff, and the
+    /// erc20_balances and erc20_allowances shorthands
+    #[serde(default)]
+    pub state_overrides: Option<serde_json::Value>,
+}
+
+/// Function and arguments of a read-only call in a batch. The batch shares one block and
+/// sender, so there is nothing else to set per call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewCall {
+    pub function_name: String,
+    pub parameters: serde_json::Value,
+    /// Annotate decoded return values with their Solidity types
+    #[serde(default)]
+    pub include_types: bool,
+}
+
+/// One read-only call of a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchCall {
+    pub contract_address: String,
+    pub call: ViewCall,
+    /// Report a failure of this call in its own result instead of failing the whole batch
+    pub allow_failure: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,4 +148,19 @@ pub struct CallResult {
     pub error: Option<String>,
     pub gas_used: Option<u64>,
     pub transaction_hash: Option<String>,
+    /// Block the call ran against
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block: Option<CallBlock>,
+}
+
+/// A block that a call, estimate or simulation ran against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallBlock {
+    /// Block number; unknown for the pending block on some nodes
+    pub number: Option<u64>,
+    /// Block hash; not looked up for blocks requested by number, nor for the pending block
+    pub hash: Option<String>,
+    /// Tag the block was requested by, when it was
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
 }
@@ -0,0 +1,405 @@
+use crate::ethereum::{storage::StorageLayout, utils};
+use alloy::{
+    eips::BlockId,
+    primitives::{keccak256, map::B256HashMap, Address, Bytes, B256, U256},
+    providers::{Provider, RootProvider},
+    rpc::types::{
+        state::{AccountOverride, StateOverride},
+        TransactionRequest,
+    },
+    transports::http::{Client, Http},
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Highest mapping slot probed when looking for an ERC-20 balance or allowance mapping
+const MAX_PROBED_SLOT: u64 = 20;
+
+/// `balanceOf(address)`
+const BALANCE_OF_SELECTOR: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+/// `allowance(address,address)`
+const ALLOWANCE_SELECTOR: [u8; 4] = [0xdd, 0x62, 0xed, 0x3e];
+
+/// Names of the balance and allowance mappings in common ERC-20 implementations
+const BALANCE_VARIABLES: &[&str] = &["_balances", "balances", "balanceOf", "_balanceOf"];
+const ALLOWANCE_VARIABLES: &[&str] = &["_allowances", "allowances", "allowance", "_allowance"];
+
+/// How a compiler hashes mapping keys: Solidity as `keccak256(key ++ slot)`, Vyper as
+/// `keccak256(slot ++ key)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MappingLayout {
+    Solidity,
+    Vyper,
+}
+
+impl MappingLayout {
+    /// Slot of `mapping[key]` for a mapping at `slot`
+    pub fn entry(self, slot: U256, key: B256) -> U256 {
+        let slot = B256::from(slot);
+        let (first, second) = match self {
+            MappingLayout::Solidity => (key, slot),
+            MappingLayout::Vyper => (slot, key),
+        };
+        keccak256([first.as_slice(), second.as_slice()].concat()).into()
+    }
+}
+
+/// Where an ERC-20 token keeps a mapping
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MappingSlot {
+    pub slot: U256,
+    pub layout: MappingLayout,
+}
+
+/// An entry of an ERC-20 balance or allowance mapping
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Erc20Mapping {
+    Balance { holder: Address },
+    Allowance { owner: Address, spender: Address },
+}
+
+impl Erc20Mapping {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Erc20Mapping::Balance { .. } => "balance",
+            Erc20Mapping::Allowance { .. } => "allowance",
+        }
+    }
+
+    fn variables(&self) -> &'static [&'static str] {
+        match self {
+            Erc20Mapping::Balance { .. } => BALANCE_VARIABLES,
+            Erc20Mapping::Allowance { .. } => ALLOWANCE_VARIABLES,
+        }
+    }
+
+    /// Calldata of the view function that reads this entry
+    fn calldata(&self) -> Bytes {
+        let (selector, keys) = match self {
+            Erc20Mapping::Balance { holder } => (BALANCE_OF_SELECTOR, vec![*holder]),
+            Erc20Mapping::Allowance { owner, spender } => {
+                (ALLOWANCE_SELECTOR, vec![*owner, *spender])
+            }
+        };
+        let mut calldata = selector.to_vec();
+        for key in keys {
+            calldata.extend_from_slice(key.into_word().as_slice());
+        }
+        calldata.into()
+    }
+
+    /// Storage slot of this entry in a mapping at `mapping`; allowances are nested by owner,
+    /// then spender
+    pub fn slot(&self, mapping: MappingSlot) -> U256 {
+        match self {
+            Erc20Mapping::Balance { holder } => {
+                mapping.layout.entry(mapping.slot, holder.into_word())
+            }
+            Erc20Mapping::Allowance { owner, spender } => {
+                let inner = mapping.layout.entry(mapping.slot, owner.into_word());
+                mapping.layout.entry(inner, spender.into_word())
+            }
+        }
+    }
+}
+
+/// Find the balance or allowance mapping of an ERC-20 token by writing a marker to candidate
+/// slots through state overrides and checking which one the token reads back. The slot from the
+/// storage layout is tried first when known, then the first slots in Solidity and Vyper order.
+pub async fn find_erc20_mapping(
+    provider: &RootProvider<Http<Client>>,
+    token: Address,
+    mapping: Erc20Mapping,
+    layout: Option<&StorageLayout>,
+    block: BlockId,
+) -> Result<MappingSlot> {
+    let marker = keccak256("erc20 mapping probe");
+    let from_layout = layout.and_then(|layout| {
+        mapping
+            .variables()
+            .iter()
+            .find_map(|name| layout.variable_slot(name))
+    });
+    let candidates = from_layout
+        .map(|slot| MappingSlot {
+            slot,
+            layout: MappingLayout::Solidity,
+        })
+        .into_iter()
+        .chain((0..=MAX_PROBED_SLOT).flat_map(|slot| {
+            [MappingLayout::Solidity, MappingLayout::Vyper].map(|layout| MappingSlot {
+                slot: U256::from(slot),
+                layout,
+            })
+        }));
+
+    let request = TransactionRequest::default()
+        .to(token)
+        .input(mapping.calldata().into());
+    for candidate in candidates {
+        let mut state_diff = B256HashMap::default();
+        state_diff.insert(mapping.slot(candidate).into(), marker);
+        let mut overrides = StateOverride::default();
+        overrides.insert(
+            token,
+            AccountOverride {
+                state_diff: Some(state_diff),
+                ..Default::default()
+            },
+        );
+
+        match provider
+            .call(&request)
+            .block(block)
+            .overrides(&overrides)
+            .await
+        {
+            Ok(output) if output.as_ref() == marker.as_slice() => return Ok(candidate),
+            Ok(_) => {}
+            Err(e) => {
+                return Err(anyhow!(
+                    "Failed to probe the storage of {:?}: {}",
+                    token,
+                    utils::interpret_rpc_error(&e.to_string())
+                ))
+            }
+        }
+    }
+
+    Err(anyhow!(
+        "Could not find the {} mapping of token {:?} in slots 0-{}. It may keep balances in a \
+         packed struct or namespaced storage; override the slot with state_diff instead",
+        mapping.name(),
+        token,
+        MAX_PROBED_SLOT
+    ))
+}
+
+/// Overrides of one account, as given to the tools
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AccountOverrideInput {
+    balance: Option<Value>,
+    nonce: Option<u64>,
+    code: Option<String>,
+    /// Replaces the whole storage of the account
+    state: Option<HashMap<String, Value>>,
+    /// Replaces individual storage slots
+    #[serde(alias = "stateDiff")]
+    state_diff: Option<HashMap<String, Value>>,
+    /// ERC-20 balances of this token, by holder
+    erc20_balances: Option<HashMap<String, Value>>,
+    /// ERC-20 allowances of this token, by owner and then spender
+    erc20_allowances: Option<HashMap<String, HashMap<String, Value>>>,
+}
+
+/// An ERC-20 balance or allowance to set, once the token's mapping is found
+#[derive(Debug, Clone, PartialEq)]
+pub struct Erc20Override {
+    pub token: Address,
+    pub mapping: Erc20Mapping,
+    pub amount: U256,
+}
+
+/// Parse state overrides given as
+/// `{address: {balance, nonce, code, state, state_diff, erc20_balances, erc20_allowances}}`.
+/// The ERC-20 entries are returned separately, to be written with [`set_erc20_slot`] once the
+/// token's mappings are found.
+pub fn parse_state_override(value: &Value) -> Result<(StateOverride, Vec<Erc20Override>)> {
+    let accounts: HashMap<String, AccountOverrideInput> = serde_json::from_value(value.clone())
+        .map_err(|e| anyhow!("Invalid state overrides: {}", e))?;
+
+    let mut overrides = StateOverride::default();
+    let mut erc20 = Vec::new();
+    for (address, input) in accounts {
+        let address = parse_address(&address)?;
+        let account = AccountOverride {
+            balance: input.balance.as_ref().map(parse_amount).transpose()?,
+            nonce: input.nonce,
+            code: input
+                .code
+                .map(|code| {
+                    Bytes::from_str(&code)
+                        .map_err(|e| anyhow!("Invalid code for {:?}: {}", address, e))
+                })
+                .transpose()?,
+            state: input.state.as_ref().map(parse_slots).transpose()?,
+            state_diff: input.state_diff.as_ref().map(parse_slots).transpose()?,
+            ..Default::default()
+        };
+        if account.state.is_some() && account.state_diff.is_some() {
+            return Err(anyhow!(
+                "Override of {:?} sets both state and state_diff; use one of them",
+                address
+            ));
+        }
+        overrides.insert(address, account);
+
+        for (holder, amount) in input.erc20_balances.unwrap_or_default() {
+            erc20.push(Erc20Override {
+                token: address,
+                mapping: Erc20Mapping::Balance {
+                    holder: parse_address(&holder)?,
+                },
+                amount: parse_amount(&amount)?,
+            });
+        }
+        for (owner, spenders) in input.erc20_allowances.unwrap_or_default() {
+            let owner = parse_address(&owner)?;
+            for (spender, amount) in spenders {
+                erc20.push(Erc20Override {
+                    token: address,
+                    mapping: Erc20Mapping::Allowance {
+                        owner,
+                        spender: parse_address(&spender)?,
+                    },
+                    amount: parse_amount(&amount)?,
+                });
+            }
+        }
+    }
+    Ok((overrides, erc20))
+}
+
+/// Write an ERC-20 balance or allowance into the overrides of its token, alongside any other
+/// storage overrides of the token
+pub fn set_erc20_slot(overrides: &mut StateOverride, entry: &Erc20Override, mapping: MappingSlot) {
+    let account = overrides.entry(entry.token).or_default();
+    let slots = match &mut account.state {
+        Some(state) => state,
+        None => account.state_diff.get_or_insert_with(Default::default),
+    };
+    slots.insert(entry.mapping.slot(mapping).into(), entry.amount.into());
+}
+
+fn parse_address(address: &str) -> Result<Address> {
+    utils::validate_address(address)
+        .map_err(|e| anyhow!("Invalid address in state overrides: {}", e))
+}
+
+/// Parse storage slots and values, each a decimal or `0x` hex number
+fn parse_slots(slots: &HashMap<String, Value>) -> Result<B256HashMap<B256>> {
+    slots
+        .iter()
+        .map(|(slot, value)| {
+            let slot = U256::from_str(slot.trim())
+                .map_err(|e| anyhow!("Invalid storage slot '{}': {}", slot, e))?;
+            Ok((slot.into(), parse_amount(value)?.into()))
+        })
+        .collect()
+}
+
+/// Parse an amount: a JSON number, a decimal or `0x` hex string, or `max`
+fn parse_amount(value: &Value) -> Result<U256> {
+    match value {
+        Value::Number(number) => number
+            .as_u64()
+            .map(U256::from)
+            .ok_or_else(|| anyhow!("Invalid amount {}: use a non-negative integer", number)),
+        Value::String(amount) if amount.trim().eq_ignore_ascii_case("max") => Ok(U256::MAX),
+        Value::String(amount) => utils::validate_hex_value(amount.trim()),
+        _ => Err(anyhow!(
+            "Invalid amount {}: use a number or a decimal or hex string",
+            value
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{address, b256};
+    use serde_json::json;
+
+    const HOLDER: Address = address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+    const ROUTER: Address = address!("E592427A0AEce92De3Edee1F18E0157C05861564");
+
+    #[test]
+    fn test_erc20_slots() {
+        // WETH keeps balanceOf at slot 3 and allowance at slot 4
+        let balance = Erc20Mapping::Balance { holder: HOLDER };
+        let slot = balance.slot(MappingSlot {
+            slot: U256::from(3),
+            layout: MappingLayout::Solidity,
+        });
+        let mut preimage = HOLDER.into_word().to_vec();
+        preimage.extend_from_slice(&U256::from(3).to_be_bytes::<32>());
+        assert_eq!(slot, U256::from_be_bytes(keccak256(&preimage).0));
+
+        // Vyper puts the slot first
+        let vyper = balance.slot(MappingSlot {
+            slot: U256::from(3),
+            layout: MappingLayout::Vyper,
+        });
+        assert_ne!(vyper, slot);
+
+        let allowance = Erc20Mapping::Allowance {
+            owner: HOLDER,
+            spender: ROUTER,
+        };
+        let mapping = MappingSlot {
+            slot: U256::from(4),
+            layout: MappingLayout::Solidity,
+        };
+        let inner = MappingLayout::Solidity.entry(U256::from(4), HOLDER.into_word());
+        assert_eq!(
+            allowance.slot(mapping),
+            MappingLayout::Solidity.entry(inner, ROUTER.into_word())
+        );
+        assert_eq!(
+            allowance.calldata().len(),
+            4 + 64,
+            "allowance(address,address) takes two words"
+        );
+    }
+
+    #[test]
+    fn test_parse_state_override() {
+        let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        let (mut overrides, erc20) = parse_state_override(&json!({
+            HOLDER.to_string(): { "balance": "0xde0b6b3a7640000", "nonce": 7 },
+            weth.to_string(): {
+                "stateDiff": { "0x0": "1" },
+                "erc20_balances": { HOLDER.to_string(): "10000000000000000000" },
+                "erc20_allowances": { HOLDER.to_string(): { ROUTER.to_string(): "max" } }
+            }
+        }))
+        .unwrap();
+
+        let holder = &overrides[&HOLDER];
+        assert_eq!(holder.balance, Some(U256::from(10).pow(U256::from(18))));
+        assert_eq!(holder.nonce, Some(7));
+        assert_eq!(erc20.len(), 2);
+        let approval = erc20
+            .iter()
+            .find(|entry| matches!(entry.mapping, Erc20Mapping::Allowance { .. }))
+            .unwrap();
+        assert_eq!(approval.amount, U256::MAX);
+
+        let mapping = MappingSlot {
+            slot: U256::from(4),
+            layout: MappingLayout::Solidity,
+        };
+        set_erc20_slot(&mut overrides, approval, mapping);
+        let state_diff = overrides[&weth].state_diff.as_ref().unwrap();
+        assert_eq!(state_diff.len(), 2);
+        assert_eq!(
+            state_diff[&B256::from(approval.mapping.slot(mapping))],
+            B256::from(U256::MAX)
+        );
+        assert_eq!(
+            state_diff[&B256::ZERO],
+            b256!("0000000000000000000000000000000000000000000000000000000000000001")
+        );
+
+        assert!(parse_state_override(&json!({ HOLDER.to_string(): { "balanse": 1 } })).is_err());
+        assert!(parse_state_override(&json!({
+            HOLDER.to_string(): { "state": {}, "state_diff": {} }
+        }))
+        .is_err());
+    }
+}
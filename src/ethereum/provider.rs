@@ -46,6 +46,10 @@ impl ProviderManager {
             .ok_or_else(|| anyhow!("Network '{}' not found", network_name))
     }
 
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
     pub fn get_network_config(&self, network: Option<&str>) -> Result<&NetworkConfig> {
         let network_name = network.unwrap_or(&self.config.default_network);
         self.config
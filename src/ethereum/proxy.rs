@@ -0,0 +1,417 @@
+use alloy::{
+    dyn_abi::{DynSolType, DynSolValue},
+    eips::BlockId,
+    json_abi::JsonAbi,
+    primitives::{b256, bytes, keccak256, Address, Bytes, B256, U256},
+    providers::{Provider, RootProvider},
+    rpc::types::TransactionRequest,
+    transports::http::{Client, Http},
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// EIP-1967 implementation slot, `keccak256("eip1967.proxy.implementation") - 1`
+const EIP1967_IMPLEMENTATION_SLOT: B256 =
+    b256!("360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc");
+/// EIP-1967 beacon slot, `keccak256("eip1967.proxy.beacon") - 1`
+const EIP1967_BEACON_SLOT: B256 =
+    b256!("a3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50");
+/// EIP-1967 admin slot, `keccak256("eip1967.proxy.admin") - 1`
+const EIP1967_ADMIN_SLOT: B256 =
+    b256!("b53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103");
+/// EIP-1822 (UUPS) logic slot, `keccak256("PROXIABLE")`
+const EIP1822_PROXIABLE_SLOT: B256 =
+    b256!("c5f16f0fcc639fa48a6947836d9850f504798523bf8c9a3a87d5876cf622bcf7");
+
+/// EIP-1167 minimal proxy runtime code around the 20-byte implementation address
+const EIP1167_PREFIX: Bytes = bytes!("363d3d373d3d3d363d73");
+const EIP1167_SUFFIX: Bytes = bytes!("5af43d82803e903d91602b57fd5bf3");
+
+/// `implementation()` on an EIP-1967 beacon
+const BEACON_IMPLEMENTATION_SELECTOR: [u8; 4] = [0x5c, 0x60, 0xda, 0x1b];
+/// `facetAddresses()` on an EIP-2535 diamond loupe
+const FACET_ADDRESSES_SELECTOR: [u8; 4] = [0x52, 0xef, 0x6b, 0x2c];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProxyType {
+    #[serde(rename = "eip1967")]
+    Eip1967,
+    #[serde(rename = "eip1967_beacon")]
+    Eip1967Beacon,
+    #[serde(rename = "eip1167")]
+    Eip1167,
+    #[serde(rename = "eip1822")]
+    Eip1822,
+    #[serde(rename = "openzeppelin_legacy")]
+    OpenZeppelinLegacy,
+    #[serde(rename = "eip2535_diamond")]
+    Eip2535Diamond,
+}
+
+/// What a proxy delegates to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyInfo {
+    pub proxy_type: ProxyType,
+    /// Logic contract; `None` for diamonds, which delegate to `facets`
+    pub implementation: Option<String>,
+    pub admin: Option<String>,
+    pub beacon: Option<String>,
+    pub facets: Vec<String>,
+}
+
+impl ProxyInfo {
+    fn new(proxy_type: ProxyType, implementation: Address) -> Self {
+        Self {
+            proxy_type,
+            implementation: Some(format!("{:?}", implementation)),
+            admin: None,
+            beacon: None,
+            facets: Vec::new(),
+        }
+    }
+
+    /// Whether the proxy can be pointed elsewhere without changing its code. Only EIP-1167
+    /// clones have their implementation baked into the bytecode; every other kind keeps it in
+    /// storage or behind a call that an upgrade changes.
+    pub fn is_upgradeable(&self) -> bool {
+        self.proxy_type != ProxyType::Eip1167
+    }
+
+    /// Contracts whose ABIs make up the proxy's interface
+    pub fn implementation_addresses(&self) -> Vec<String> {
+        match &self.implementation {
+            Some(implementation) => vec![implementation.clone()],
+            None => self.facets.clone(),
+        }
+    }
+}
+
+/// Detect whether a contract is a proxy, and what it delegates to at `block`, given its runtime
+/// code at that block
+pub async fn detect_proxy(
+    provider: &RootProvider<Http<Client>>,
+    address: Address,
+    code: &[u8],
+    block: BlockId,
+) -> Result<Option<ProxyInfo>> {
+    if let Some(implementation) = parse_minimal_proxy(code) {
+        return Ok(Some(ProxyInfo::new(ProxyType::Eip1167, implementation)));
+    }
+
+    let (implementation, beacon, admin) = tokio::try_join!(
+        read_address_slot(provider, address, EIP1967_IMPLEMENTATION_SLOT, block),
+        read_address_slot(provider, address, EIP1967_BEACON_SLOT, block),
+        read_address_slot(provider, address, EIP1967_ADMIN_SLOT, block),
+    )?;
+
+    if let Some(implementation) = implementation {
+        return Ok(Some(ProxyInfo {
+            admin: admin.map(|a| format!("{:?}", a)),
+            ..ProxyInfo::new(ProxyType::Eip1967, implementation)
+        }));
+    }
+
+    if let Some(beacon) = beacon {
+        let implementation =
+            call_for_address(provider, beacon, BEACON_IMPLEMENTATION_SELECTOR, block)
+                .await?
+                .ok_or_else(|| anyhow!("Beacon {:?} returned no implementation", beacon))?;
+        return Ok(Some(ProxyInfo {
+            admin: admin.map(|a| format!("{:?}", a)),
+            beacon: Some(format!("{:?}", beacon)),
+            ..ProxyInfo::new(ProxyType::Eip1967Beacon, implementation)
+        }));
+    }
+
+    // Older proxy standards, only checked once EIP-1967 has been ruled out
+    let (proxiable, legacy_implementation, legacy_admin) = tokio::try_join!(
+        read_address_slot(provider, address, EIP1822_PROXIABLE_SLOT, block),
+        read_address_slot(
+            provider,
+            address,
+            keccak256("org.zeppelinos.proxy.implementation"),
+            block
+        ),
+        read_address_slot(
+            provider,
+            address,
+            keccak256("org.zeppelinos.proxy.admin"),
+            block
+        ),
+    )?;
+
+    if let Some(implementation) = proxiable {
+        return Ok(Some(ProxyInfo::new(ProxyType::Eip1822, implementation)));
+    }
+
+    if let Some(implementation) = legacy_implementation {
+        return Ok(Some(ProxyInfo {
+            admin: legacy_admin.map(|a| format!("{:?}", a)),
+            ..ProxyInfo::new(ProxyType::OpenZeppelinLegacy, implementation)
+        }));
+    }
+
+    let facets = diamond_facets(provider, address, block).await;
+    if !facets.is_empty() {
+        return Ok(Some(ProxyInfo {
+            proxy_type: ProxyType::Eip2535Diamond,
+            implementation: None,
+            admin: None,
+            beacon: None,
+            facets: facets.iter().map(|f| format!("{:?}", f)).collect(),
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Read a storage slot that holds an address, returning `None` when it is empty
+async fn read_address_slot(
+    provider: &RootProvider<Http<Client>>,
+    address: Address,
+    slot: B256,
+    block: BlockId,
+) -> Result<Option<Address>> {
+    let value = provider
+        .get_storage_at(address, slot.into())
+        .block_id(block)
+        .await
+        .map_err(|e| anyhow!("Failed to read storage slot {}: {}", slot, e))?;
+    Ok(slot_address(value))
+}
+
+/// Interpret a storage word as an address; words with non-zero upper bytes are not addresses
+fn slot_address(value: U256) -> Option<Address> {
+    let word = B256::from(value);
+    if value.is_zero() || word[..12].iter().any(|b| *b != 0) {
+        return None;
+    }
+    Some(Address::from_word(word))
+}
+
+/// Extract the implementation address from EIP-1167 minimal proxy runtime code
+fn parse_minimal_proxy(code: &[u8]) -> Option<Address> {
+    let address_end = EIP1167_PREFIX.len() + 20;
+    if code.len() != address_end + EIP1167_SUFFIX.len()
+        || !code.starts_with(&EIP1167_PREFIX)
+        || !code.ends_with(&EIP1167_SUFFIX)
+    {
+        return None;
+    }
+    Some(Address::from_slice(
+        &code[EIP1167_PREFIX.len()..address_end],
+    ))
+}
+
+/// Call a no-argument function that returns an address
+async fn call_for_address(
+    provider: &RootProvider<Http<Client>>,
+    address: Address,
+    selector: [u8; 4],
+    block: BlockId,
+) -> Result<Option<Address>> {
+    let request = TransactionRequest::default()
+        .to(address)
+        .input(Bytes::from(selector.to_vec()).into());
+    let result = provider
+        .call(&request)
+        .block(block)
+        .await
+        .map_err(|e| anyhow!("Call to {:?} failed: {}", address, e))?;
+
+    match DynSolType::Address.abi_decode(&result) {
+        Ok(DynSolValue::Address(address)) if !address.is_zero() => Ok(Some(address)),
+        _ => Ok(None),
+    }
+}
+
+/// Facet addresses of an EIP-2535 diamond; empty when the contract has no diamond loupe
+async fn diamond_facets(
+    provider: &RootProvider<Http<Client>>,
+    address: Address,
+    block: BlockId,
+) -> Vec<Address> {
+    let request = TransactionRequest::default()
+        .to(address)
+        .input(Bytes::from(FACET_ADDRESSES_SELECTOR.to_vec()).into());
+    let Ok(result) = provider.call(&request).block(block).await else {
+        return Vec::new();
+    };
+
+    let facets_type = DynSolType::Array(Box::new(DynSolType::Address));
+    match facets_type.abi_decode(&result) {
+        Ok(DynSolValue::Array(values)) => {
+            let mut facets = Vec::new();
+            for value in values {
+                if let DynSolValue::Address(facet) = value {
+                    if !facet.is_zero() && facet != address && !facets.contains(&facet) {
+                        facets.push(facet);
+                    }
+                }
+            }
+            facets
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Add the functions, events and errors of `other` that `target` doesn't already have
+///
+/// Items are matched by selector, so on a clash the entry already in `target` wins.
+pub fn merge_abi(target: &mut JsonAbi, other: &JsonAbi) {
+    for function in other.functions() {
+        if !target
+            .functions()
+            .any(|f| f.selector() == function.selector())
+        {
+            target
+                .functions
+                .entry(function.name.clone())
+                .or_default()
+                .push(function.clone());
+        }
+    }
+    for event in other.events() {
+        if !target.events().any(|e| e.selector() == event.selector()) {
+            target
+                .events
+                .entry(event.name.clone())
+                .or_default()
+                .push(event.clone());
+        }
+    }
+    for error in other.errors() {
+        if !target.errors().any(|e| e.selector() == error.selector()) {
+            target
+                .errors
+                .entry(error.name.clone())
+                .or_default()
+                .push(error.clone());
+        }
+    }
+    if target.fallback.is_none() {
+        target.fallback = other.fallback;
+    }
+    if target.receive.is_none() {
+        target.receive = other.receive;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot_constants() {
+        let minus_one =
+            |label: &str| B256::from(U256::from_be_bytes(keccak256(label).0) - U256::from(1));
+
+        assert_eq!(
+            EIP1967_IMPLEMENTATION_SLOT,
+            minus_one("eip1967.proxy.implementation")
+        );
+        assert_eq!(EIP1967_BEACON_SLOT, minus_one("eip1967.proxy.beacon"));
+        assert_eq!(EIP1967_ADMIN_SLOT, minus_one("eip1967.proxy.admin"));
+        assert_eq!(EIP1822_PROXIABLE_SLOT, keccak256("PROXIABLE"));
+    }
+
+    #[test]
+    fn test_parse_minimal_proxy() {
+        let implementation: Address = "0xbebebebebebebebebebebebebebebebebebebebe"
+            .parse()
+            .unwrap();
+        let code = [
+            EIP1167_PREFIX.as_ref(),
+            implementation.as_slice(),
+            EIP1167_SUFFIX.as_ref(),
+        ]
+        .concat();
+
+        assert_eq!(parse_minimal_proxy(&code), Some(implementation));
+        assert_eq!(parse_minimal_proxy(&code[..code.len() - 1]), None);
+        assert_eq!(parse_minimal_proxy(&[]), None);
+    }
+
+    #[test]
+    fn test_slot_address() {
+        let implementation: Address = "0x43506849d7c04f9138d1a2050bbf3a0c054402dd"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            slot_address(U256::from_be_bytes(implementation.into_word().0)),
+            Some(implementation)
+        );
+        assert_eq!(slot_address(U256::ZERO), None);
+        assert_eq!(slot_address(U256::MAX), None);
+    }
+
+    #[tokio::test]
+    async fn test_detect_proxy_at_block() {
+        use crate::ethereum::test_rpc::rpc_provider;
+
+        // The proxy pointed at 0xbebe... in block 1234 and was upgraded to 0xcafe... after
+        let provider = rpc_provider(|method, params| {
+            let word = |address: &str| Ok(format!("0x{:0>64}", address).into());
+            match method {
+                "eth_getStorageAt"
+                    if params[1] == format!("{:#x}", EIP1967_IMPLEMENTATION_SLOT) =>
+                {
+                    match params[2].as_str() {
+                        Some("0x4d2") => word("bebebebebebebebebebebebebebebebebebebebe"),
+                        _ => word("cafecafecafecafecafecafecafecafecafecafe"),
+                    }
+                }
+                "eth_getStorageAt" => word("0"),
+                _ => Err(format!("unexpected {}", method)),
+            }
+        })
+        .await;
+
+        let proxy = detect_proxy(&provider, Address::ZERO, &[0x60], BlockId::number(1234))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(proxy.proxy_type, ProxyType::Eip1967);
+        assert_eq!(
+            proxy.implementation.as_deref(),
+            Some("0xbebebebebebebebebebebebebebebebebebebebe")
+        );
+        let proxy = detect_proxy(&provider, Address::ZERO, &[0x60], BlockId::latest())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            proxy.implementation.as_deref(),
+            Some("0xcafecafecafecafecafecafecafecafecafecafe")
+        );
+    }
+
+    #[test]
+    fn test_merge_abi() {
+        let mut proxy = JsonAbi::parse([
+            "function upgradeTo(address newImplementation)",
+            "function implementation() view returns (address)",
+            "event Upgraded(address indexed implementation)",
+        ])
+        .unwrap();
+        let implementation = JsonAbi::parse([
+            "function balanceOf(address owner) view returns (uint256)",
+            "function implementation() view returns (address impl)",
+            "event Transfer(address indexed from, address indexed to, uint256 value)",
+            "error Unauthorized()",
+        ])
+        .unwrap();
+
+        merge_abi(&mut proxy, &implementation);
+
+        assert_eq!(proxy.functions().count(), 3);
+        assert_eq!(proxy.events().count(), 2);
+        assert_eq!(proxy.errors().count(), 1);
+        // The existing entry wins on a selector clash
+        assert_eq!(
+            proxy.function("implementation").unwrap()[0].outputs[0].name,
+            ""
+        );
+    }
+}
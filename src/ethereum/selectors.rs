@@ -0,0 +1,212 @@
+use alloy::{
+    json_abi::{Function, JsonAbi},
+    primitives::Selector,
+};
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::warn;
+
+/// Signatures shipped with the server
+const BUILTIN_SIGNATURES: &str = include_str!("signatures.txt");
+
+const PUSH1: u8 = 0x60;
+const PUSH4: u8 = 0x63;
+const PUSH32: u8 = 0x7f;
+const DUP1: u8 = 0x80;
+const DUP16: u8 = 0x8f;
+const EQ: u8 = 0x14;
+const XOR: u8 = 0x18;
+const JUMPI: u8 = 0x57;
+
+/// Function selectors dispatched on in runtime bytecode, in order of appearance.
+///
+/// Matches the dispatcher pattern `PUSH4 selector [DUPn] EQ PUSHn dest JUMPI` that solc emits,
+/// and the `XOR` variant Vyper uses to jump past functions that don't match. Selectors with
+/// leading zero bytes are pushed with a shorter PUSH, e.g. `PUSH3 0xfdd58e` for `0x00fdd58e`.
+pub fn extract_selectors(code: &[u8]) -> Vec<Selector> {
+    let instructions = instructions(code);
+    let mut selectors: Vec<Selector> = Vec::new();
+
+    for (i, (opcode, data)) in instructions.iter().enumerate() {
+        if !(PUSH1..=PUSH4).contains(opcode) || data.len() != (opcode - PUSH1 + 1) as usize {
+            continue;
+        }
+        let mut rest = instructions[i + 1..].iter().map(|(opcode, _)| *opcode);
+        let mut next = rest.next();
+        if next.is_some_and(|op| (DUP1..=DUP16).contains(&op)) {
+            next = rest.next();
+        }
+        let compares = matches!(next, Some(EQ | XOR));
+        let jumps = rest.next().is_some_and(|op| (PUSH1..=PUSH4).contains(&op))
+            && rest.next() == Some(JUMPI);
+
+        let mut padded = [0; 4];
+        padded[4 - data.len()..].copy_from_slice(data);
+        let selector = Selector::from(padded);
+        if compares && jumps && !selectors.contains(&selector) {
+            selectors.push(selector);
+        }
+    }
+    selectors
+}
+
+/// Split bytecode into opcodes with their push data
+fn instructions(code: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut instructions = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = code[pc];
+        let size = if (PUSH1..=PUSH32).contains(&opcode) {
+            (opcode - PUSH1 + 1) as usize
+        } else {
+            0
+        };
+        let data = &code[(pc + 1).min(code.len())..(pc + 1 + size).min(code.len())];
+        instructions.push((opcode, data));
+        pc += 1 + size;
+    }
+    instructions
+}
+
+/// Known function signatures by selector
+#[derive(Debug, Default)]
+pub struct SignatureDb {
+    functions: HashMap<Selector, Function>,
+}
+
+impl SignatureDb {
+    /// The built-in signatures plus those in `files`; a file's entries win over built-in ones
+    pub fn load(files: &[impl AsRef<Path>]) -> Self {
+        let mut db = Self::default();
+        db.add_signatures(BUILTIN_SIGNATURES);
+        for file in files {
+            let file = file.as_ref();
+            match std::fs::read_to_string(file) {
+                Ok(content) => {
+                    let count = db.add_signatures(&content);
+                    tracing::debug!("Loaded {} signatures from {:?}", count, file);
+                }
+                Err(e) => warn!("Skipping signature file {:?}: {}", file, e),
+            }
+        }
+        db
+    }
+
+    /// Add one human-readable signature per line, skipping blank lines and `#` comments.
+    /// Invalid lines are skipped with a warning; returns how many signatures were added.
+    fn add_signatures(&mut self, content: &str) -> usize {
+        let mut count = 0;
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match Function::parse(line) {
+                Ok(function) => {
+                    self.functions.insert(function.selector(), function);
+                    count += 1;
+                }
+                Err(e) => warn!("Skipping invalid signature '{}': {}", line, e),
+            }
+        }
+        count
+    }
+
+    pub fn get(&self, selector: &Selector) -> Option<&Function> {
+        self.functions.get(selector)
+    }
+
+    /// Build an ABI from the selectors in runtime bytecode, returning it with the selectors
+    /// that matched no known signature
+    pub fn infer_abi(&self, code: &[u8]) -> (JsonAbi, Vec<Selector>) {
+        let mut abi = JsonAbi::new();
+        let mut unknown = Vec::new();
+        for selector in extract_selectors(code) {
+            match self.get(&selector) {
+                Some(function) => abi
+                    .functions
+                    .entry(function.name.clone())
+                    .or_default()
+                    .push(function.clone()),
+                None => unknown.push(selector),
+            }
+        }
+        (abi, unknown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::hex;
+
+    #[test]
+    fn test_extract_selectors() {
+        // solc dispatcher: balanceOf and transfer, plus a binary-search pivot compared with GT
+        let code = hex::decode(concat!(
+            "6080604052348015600f57600080fd5b50",
+            "60043610603f5760003560e01c",
+            "80637fffffff11602a57",
+            "806370a08231146044578063a9059cbb14604957",
+            "5b600080fd"
+        ))
+        .unwrap();
+        let selectors = extract_selectors(&code);
+        assert_eq!(
+            selectors,
+            [
+                Selector::from(hex!("70a08231")),
+                Selector::from(hex!("a9059cbb"))
+            ]
+        );
+
+        // A PUSH4 inside push data is not an instruction
+        assert!(extract_selectors(&hex!("7f63aabbccdd14600057")).is_empty());
+
+        // solc pushes selectors with leading zero bytes with a shorter PUSH: ERC-1155
+        // balanceOf, 0x00fdd58e, is a PUSH3
+        let code = hex!("8062fdd58e14604457806301ffc9a714604957");
+        assert_eq!(
+            extract_selectors(&code),
+            [
+                Selector::from(hex!("00fdd58e")),
+                Selector::from(hex!("01ffc9a7"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_infer_abi() {
+        let db = SignatureDb::load(&[] as &[&Path]);
+        assert!(db.get(&Selector::from(hex!("70a08231"))).is_some());
+
+        let code = hex!("806370a082311460445780631234567814604957");
+        let (abi, unknown) = db.infer_abi(&code);
+        let balance_of = &abi.functions["balanceOf"][0];
+        assert_eq!(balance_of.outputs.len(), 1);
+        assert_eq!(unknown, [Selector::from(hex!("12345678"))]);
+    }
+
+    #[test]
+    fn test_builtin_signatures_parse() {
+        let mut db = SignatureDb::default();
+        let lines = BUILTIN_SIGNATURES
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .count();
+        assert_eq!(db.add_signatures(BUILTIN_SIGNATURES), lines);
+        assert_eq!(lines, db.functions.len());
+    }
+
+    #[test]
+    fn test_invalid_signatures_are_skipped() {
+        let mut db = SignatureDb::default();
+        let count = db.add_signatures(concat!(
+            "function totalSupply() view returns (uint256)\n",
+            "function broken(uint256\n",
+            "function decimals() view returns (uint8)\n"
+        ));
+        assert_eq!(count, 2);
+        assert!(db.get(&Selector::from(hex!("313ce567"))).is_some());
+    }
+}
@@ -0,0 +1,304 @@
+use crate::ethereum::explorer::ExplorerApi;
+use anyhow::{anyhow, Result};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Contract name and compiler settings of a verified contract
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SourceInfo {
+    pub contract_name: Option<String>,
+    pub compiler_version: Option<String>,
+    pub optimizer_enabled: Option<bool>,
+    pub optimizer_runs: Option<u64>,
+    pub evm_version: Option<String>,
+    /// SPDX license identifier
+    pub license: Option<String>,
+    /// Where the source was found: the explorer (`Etherscan`, or the host of the network's
+    /// explorer API), sourcify or metadata
+    pub provider: String,
+    /// Paths of the source files, for `get_contract_source`
+    pub files: Vec<String>,
+}
+
+/// Verified source code of a contract
+#[derive(Debug, Clone)]
+pub struct SourceCode {
+    pub info: SourceInfo,
+    /// File contents by path
+    pub files: BTreeMap<String, String>,
+}
+
+impl SourceCode {
+    fn new(mut info: SourceInfo, files: BTreeMap<String, String>) -> Self {
+        info.files = files.keys().cloned().collect();
+        Self { info, files }
+    }
+
+    /// Find a file by its full path or, when unambiguous, by the end of its path
+    pub fn file(&self, name: &str) -> Result<(&str, &str)> {
+        if let Some((path, content)) = self.files.get_key_value(name) {
+            return Ok((path, content));
+        }
+
+        let suffix = format!("/{}", name.trim_start_matches('/'));
+        let matches: Vec<(&String, &String)> = self
+            .files
+            .iter()
+            .filter(|(path, _)| path.ends_with(&suffix))
+            .collect();
+        match matches.as_slice() {
+            [(path, content)] => Ok((path, content)),
+            [] => Err(anyhow!(
+                "No source file '{}'. Available files: {}",
+                name,
+                self.info.files.join(", ")
+            )),
+            _ => Err(anyhow!(
+                "'{}' matches several source files: {}",
+                name,
+                matches
+                    .iter()
+                    .map(|(path, _)| path.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+}
+
+impl SourceInfo {
+    /// Read the contract name and compiler settings from Solidity compiler metadata
+    pub fn from_metadata(metadata: &Value, provider: &str) -> Self {
+        let settings = &metadata["settings"];
+        let target = settings["compilationTarget"]
+            .as_object()
+            .and_then(|target| target.iter().next());
+
+        Self {
+            contract_name: target.and_then(|(_, name)| name.as_str()).map(String::from),
+            compiler_version: metadata["compiler"]["version"].as_str().map(String::from),
+            optimizer_enabled: settings["optimizer"]["enabled"].as_bool(),
+            optimizer_runs: settings["optimizer"]["runs"].as_u64(),
+            evm_version: settings["evmVersion"].as_str().map(String::from),
+            license: target
+                .and_then(|(path, _)| metadata["sources"][path]["license"].as_str())
+                .map(String::from),
+            provider: provider.to_string(),
+            files: metadata["sources"]
+                .as_object()
+                .map(|sources| sources.keys().cloned().collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl ExplorerApi {
+    /// Verified source code from the explorer's `getsourcecode` endpoint
+    pub async fn source_code(&self, address: &str) -> Result<SourceCode> {
+        let response = self
+            .get("contract", "getsourcecode", &[("address", address)])
+            .await?;
+        parse_explorer_source(&response, self.name())
+    }
+}
+
+/// Verified source code from Sourcify
+pub async fn fetch_sourcify_source(
+    client: &Client,
+    base_url: &str,
+    chain_id: u64,
+    address: &str,
+) -> Result<SourceCode> {
+    let url = format!(
+        "{}/v2/contract/{}/{}?fields=sources,metadata",
+        base_url.trim_end_matches('/'),
+        chain_id,
+        address
+    );
+
+    let response = client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| anyhow!("Failed to fetch from Sourcify: {}", e))?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Err(anyhow!(
+            "Contract is not verified on Sourcify (404 not found)"
+        ));
+    }
+    if !response.status().is_success() {
+        return Err(anyhow!("Sourcify API error: HTTP {}", response.status()));
+    }
+
+    let response: Value = response
+        .json()
+        .await
+        .map_err(|e| anyhow!("Failed to parse Sourcify response: {}", e))?;
+    parse_sourcify_source(&response)
+}
+
+/// Parse an Etherscan-style `getsourcecode` response
+fn parse_explorer_source(response: &Value, explorer: &str) -> Result<SourceCode> {
+    if response["status"] != "1" {
+        let message = response["result"]
+            .as_str()
+            .or_else(|| response["message"].as_str())
+            .unwrap_or("Unknown error");
+        return Err(anyhow!("{} API error: {}", explorer, message));
+    }
+
+    let entry = &response["result"][0];
+    let source = entry["SourceCode"].as_str().unwrap_or_default().trim();
+    if source.is_empty() {
+        return Err(anyhow!(
+            "Contract source code is not verified on {}",
+            explorer
+        ));
+    }
+
+    let field = |name: &str| {
+        entry[name]
+            .as_str()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(String::from)
+    };
+    let contract_name = field("ContractName");
+    let compiler_version = field("CompilerVersion");
+    let extension = match &compiler_version {
+        Some(version) if version.starts_with("vyper") => "vy",
+        _ => "sol",
+    };
+    let files = parse_explorer_files(source, contract_name.as_deref(), extension)?;
+
+    let info = SourceInfo {
+        contract_name,
+        compiler_version,
+        optimizer_enabled: field("OptimizationUsed").map(|used| used == "1"),
+        optimizer_runs: field("Runs").and_then(|runs| runs.parse().ok()),
+        evm_version: field("EVMVersion").filter(|v| !v.eq_ignore_ascii_case("default")),
+        license: field("LicenseType").filter(|l| !l.eq_ignore_ascii_case("none")),
+        provider: explorer.to_string(),
+        files: Vec::new(),
+    };
+    Ok(SourceCode::new(info, files))
+}
+
+/// Split the `SourceCode` field into files. Explorers return either a single flattened file,
+/// a `{path: {content}}` object, or Solidity standard JSON input wrapped in double braces.
+fn parse_explorer_files(
+    source: &str,
+    contract_name: Option<&str>,
+    extension: &str,
+) -> Result<BTreeMap<String, String>> {
+    let json = source
+        .strip_prefix("{{")
+        .and_then(|s| s.strip_suffix("}}"))
+        .map(|inner| format!("{{{}}}", inner));
+    let json = json.as_deref().unwrap_or(source);
+
+    if json.starts_with('{') {
+        let value: Value = serde_json::from_str(json)
+            .map_err(|e| anyhow!("Failed to parse multi-file source: {}", e))?;
+        let sources = value.get("sources").unwrap_or(&value);
+        return parse_source_map(sources);
+    }
+
+    let name = format!("{}.{}", contract_name.unwrap_or("Contract"), extension);
+    Ok(BTreeMap::from([(name, source.to_string())]))
+}
+
+/// Parse a Sourcify v2 contract lookup with the `sources` and `metadata` fields
+fn parse_sourcify_source(response: &Value) -> Result<SourceCode> {
+    if response["sources"].is_null() {
+        return Err(anyhow!("Contract is not verified on Sourcify"));
+    }
+    let files = parse_source_map(&response["sources"])?;
+    let info = SourceInfo::from_metadata(&response["metadata"], "sourcify");
+    Ok(SourceCode::new(info, files))
+}
+
+/// Parse a `{path: {content}}` map of source files
+fn parse_source_map(sources: &Value) -> Result<BTreeMap<String, String>> {
+    let sources = sources
+        .as_object()
+        .ok_or_else(|| anyhow!("Source files are not an object"))?;
+    Ok(sources
+        .iter()
+        .filter_map(|(path, file)| Some((path.clone(), file["content"].as_str()?.to_string())))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_explorer_source() {
+        let standard_json = json!({
+            "language": "Solidity",
+            "sources": {
+                "src/Token.sol": { "content": "contract Token {}" },
+                "lib/oz/ERC20.sol": { "content": "contract ERC20 {}" }
+            }
+        });
+        let response = json!({
+            "status": "1",
+            "message": "OK",
+            "result": [{
+                "SourceCode": format!("{{{}}}", standard_json),
+                "ContractName": "Token",
+                "CompilerVersion": "v0.8.24+commit.e11b9ed9",
+                "OptimizationUsed": "1",
+                "Runs": "200",
+                "EVMVersion": "Default",
+                "LicenseType": "MIT"
+            }]
+        });
+
+        let source = parse_explorer_source(&response, "Etherscan").unwrap();
+        assert_eq!(source.info.contract_name.as_deref(), Some("Token"));
+        assert_eq!(source.info.optimizer_enabled, Some(true));
+        assert_eq!(source.info.optimizer_runs, Some(200));
+        assert_eq!(source.info.evm_version, None);
+        assert_eq!(source.info.license.as_deref(), Some("MIT"));
+        assert_eq!(source.info.provider, "Etherscan");
+        assert_eq!(source.info.files, ["lib/oz/ERC20.sol", "src/Token.sol"]);
+        assert_eq!(source.file("Token.sol").unwrap().0, "src/Token.sol");
+        assert!(source.file("Missing.sol").is_err());
+
+        // A flattened single file is named after the contract
+        let mut flat = response.clone();
+        flat["result"][0]["SourceCode"] = json!("pragma solidity ^0.8.0;\ncontract Token {}");
+        let source = parse_explorer_source(&flat, "api.polygonscan.com").unwrap();
+        assert_eq!(source.info.files, ["Token.sol"]);
+        assert_eq!(source.info.provider, "api.polygonscan.com");
+
+        let mut unverified = response;
+        unverified["result"][0]["SourceCode"] = json!("");
+        assert!(parse_explorer_source(&unverified, "Etherscan").is_err());
+    }
+
+    #[test]
+    fn test_source_info_from_metadata() {
+        let metadata = json!({
+            "compiler": { "version": "0.8.24+commit.e11b9ed9" },
+            "settings": {
+                "compilationTarget": { "src/Vault.sol": "Vault" },
+                "evmVersion": "cancun",
+                "optimizer": { "enabled": false, "runs": 200 }
+            },
+            "sources": { "src/Vault.sol": { "license": "BUSL-1.1" } }
+        });
+
+        let info = SourceInfo::from_metadata(&metadata, "sourcify");
+        assert_eq!(info.contract_name.as_deref(), Some("Vault"));
+        assert_eq!(info.evm_version.as_deref(), Some("cancun"));
+        assert_eq!(info.optimizer_enabled, Some(false));
+        assert_eq!(info.license.as_deref(), Some("BUSL-1.1"));
+        assert_eq!(info.files, ["src/Vault.sol"]);
+    }
+}
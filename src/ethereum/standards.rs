@@ -0,0 +1,227 @@
+use alloy::{
+    primitives::{keccak256, Address, Bytes, Selector},
+    providers::{Provider, RootProvider},
+    rpc::types::TransactionRequest,
+    transports::http::{Client, Http},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// `supportsInterface(bytes4)`
+const SUPPORTS_INTERFACE_SELECTOR: [u8; 4] = [0x01, 0xff, 0xc9, 0xa7];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Standard {
+    Erc165,
+    Erc20,
+    Erc721,
+    Erc1155,
+    Erc2981,
+    Erc4626,
+    Ownable,
+    AccessControl,
+    Pausable,
+}
+
+/// How a standard was detected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Detection {
+    /// The contract reports the interface through `supportsInterface`
+    Erc165,
+    /// The ABI or bytecode has every function of the standard
+    Selectors,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectedStandard {
+    pub standard: Standard,
+    pub detected_by: Detection,
+}
+
+/// Standards recognized by their functions; those with an ERC-165 interface ID, the XOR of
+/// the function selectors, are probed with `supportsInterface` first
+const STANDARDS: &[(Standard, bool, &[&str])] = &[
+    (
+        Standard::Erc20,
+        false,
+        &[
+            "totalSupply()",
+            "balanceOf(address)",
+            "transfer(address,uint256)",
+            "transferFrom(address,address,uint256)",
+            "approve(address,uint256)",
+            "allowance(address,address)",
+        ],
+    ),
+    (
+        Standard::Erc721,
+        true,
+        &[
+            "balanceOf(address)",
+            "ownerOf(uint256)",
+            "safeTransferFrom(address,address,uint256,bytes)",
+            "safeTransferFrom(address,address,uint256)",
+            "transferFrom(address,address,uint256)",
+            "approve(address,uint256)",
+            "setApprovalForAll(address,bool)",
+            "getApproved(uint256)",
+            "isApprovedForAll(address,address)",
+        ],
+    ),
+    (
+        Standard::Erc1155,
+        true,
+        &[
+            "safeTransferFrom(address,address,uint256,uint256,bytes)",
+            "safeBatchTransferFrom(address,address,uint256[],uint256[],bytes)",
+            "balanceOf(address,uint256)",
+            "balanceOfBatch(address[],uint256[])",
+            "setApprovalForAll(address,bool)",
+            "isApprovedForAll(address,address)",
+        ],
+    ),
+    (Standard::Erc2981, true, &["royaltyInfo(uint256,uint256)"]),
+    (
+        Standard::Erc4626,
+        false,
+        &[
+            "asset()",
+            "totalAssets()",
+            "convertToShares(uint256)",
+            "convertToAssets(uint256)",
+            "maxDeposit(address)",
+            "previewDeposit(uint256)",
+            "deposit(uint256,address)",
+            "maxMint(address)",
+            "previewMint(uint256)",
+            "mint(uint256,address)",
+            "maxWithdraw(address)",
+            "previewWithdraw(uint256)",
+            "withdraw(uint256,address,address)",
+            "maxRedeem(address)",
+            "previewRedeem(uint256)",
+            "redeem(uint256,address,address)",
+        ],
+    ),
+    (
+        Standard::Ownable,
+        false,
+        &["owner()", "transferOwnership(address)"],
+    ),
+    (
+        Standard::AccessControl,
+        true,
+        &[
+            "hasRole(bytes32,address)",
+            "getRoleAdmin(bytes32)",
+            "grantRole(bytes32,address)",
+            "revokeRole(bytes32,address)",
+            "renounceRole(bytes32,address)",
+        ],
+    ),
+    (Standard::Pausable, false, &["paused()"]),
+];
+
+fn selector(signature: &str) -> Selector {
+    Selector::from_slice(&keccak256(signature)[..4])
+}
+
+/// ERC-165 interface ID: the XOR of the interface's function selectors
+fn interface_id(signatures: &[&str]) -> [u8; 4] {
+    signatures.iter().fold([0; 4], |mut id, signature| {
+        for (byte, other) in id.iter_mut().zip(selector(signature)) {
+            *byte ^= other;
+        }
+        id
+    })
+}
+
+/// Classify a contract by ERC-165 probes, then by the selectors in its ABI or bytecode
+pub async fn detect_standards(
+    provider: &RootProvider<Http<Client>>,
+    address: Address,
+    selectors: &HashSet<Selector>,
+) -> Vec<DetectedStandard> {
+    let mut detected = Vec::new();
+
+    // ERC-165 requires `true` for its own ID and `false` for 0xffffffff
+    let (erc165, invalid) = tokio::join!(
+        supports_interface(provider, address, SUPPORTS_INTERFACE_SELECTOR),
+        supports_interface(provider, address, [0xff; 4]),
+    );
+    let erc165 = erc165 == Some(true) && invalid == Some(false);
+    if erc165 {
+        detected.push(DetectedStandard {
+            standard: Standard::Erc165,
+            detected_by: Detection::Erc165,
+        });
+    }
+
+    for (standard, has_interface_id, signatures) in STANDARDS {
+        let detected_by = if erc165
+            && *has_interface_id
+            && supports_interface(provider, address, interface_id(signatures)).await == Some(true)
+        {
+            Detection::Erc165
+        } else if signatures.iter().all(|s| selectors.contains(&selector(s))) {
+            Detection::Selectors
+        } else {
+            continue;
+        };
+        detected.push(DetectedStandard {
+            standard: *standard,
+            detected_by,
+        });
+    }
+    detected
+}
+
+/// Call `supportsInterface`, returning `None` when the call fails or returns garbage
+async fn supports_interface(
+    provider: &RootProvider<Http<Client>>,
+    address: Address,
+    interface_id: [u8; 4],
+) -> Option<bool> {
+    let mut input = SUPPORTS_INTERFACE_SELECTOR.to_vec();
+    input.extend_from_slice(&interface_id);
+    input.resize(4 + 32, 0);
+
+    let request = TransactionRequest::default()
+        .to(address)
+        .input(Bytes::from(input).into());
+    let output = provider.call(&request).await.ok()?;
+    match output.len() {
+        32 if output[..31].iter().all(|b| *b == 0) && output[31] <= 1 => Some(output[31] == 1),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interface_ids() {
+        let id = |standard: Standard| {
+            let (_, _, signatures) = STANDARDS.iter().find(|(s, _, _)| *s == standard).unwrap();
+            interface_id(signatures)
+        };
+        assert_eq!(id(Standard::Erc721), [0x80, 0xac, 0x58, 0xcd]);
+        assert_eq!(id(Standard::Erc1155), [0xd9, 0xb6, 0x7a, 0x26]);
+        assert_eq!(id(Standard::Erc2981), [0x2a, 0x55, 0x20, 0x5a]);
+        assert_eq!(id(Standard::AccessControl), [0x79, 0x65, 0xdb, 0x0b]);
+        assert_eq!(
+            interface_id(&["supportsInterface(bytes4)"]),
+            SUPPORTS_INTERFACE_SELECTOR
+        );
+
+        // ERC-20 and ERC-4626 define no interface ID, so they are only matched by selectors
+        for standard in [Standard::Erc20, Standard::Erc4626] {
+            assert!(STANDARDS
+                .iter()
+                .any(|(s, probed, _)| *s == standard && !probed));
+        }
+    }
+}
@@ -0,0 +1,620 @@
+use alloy::{
+    eips::BlockId,
+    primitives::{keccak256, Address, B256, I256, U256},
+    providers::{Provider, RootProvider},
+    transports::http::{Client, Http},
+};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+
+/// Elements decoded when a whole array is read
+const MAX_ARRAY_ELEMENTS: usize = 64;
+/// Bytes decoded from a long string or bytes value
+const MAX_BYTES_LENGTH: usize = 8 * 1024;
+
+/// Solidity `storageLayout` compiler output
+#[derive(Debug, Clone, Deserialize)]
+pub struct StorageLayout {
+    storage: Vec<StorageEntry>,
+    #[serde(default)]
+    types: Option<HashMap<String, StorageType>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct StorageEntry {
+    label: String,
+    slot: String,
+    offset: usize,
+    #[serde(rename = "type")]
+    type_id: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct StorageType {
+    encoding: String,
+    label: String,
+    #[serde(rename = "numberOfBytes")]
+    number_of_bytes: String,
+    key: Option<String>,
+    value: Option<String>,
+    base: Option<String>,
+    members: Option<Vec<StorageEntry>>,
+}
+
+/// Where a value lives: its slot, its byte offset from the right of the slot, and its type
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub slot: U256,
+    pub offset: usize,
+    pub type_id: String,
+}
+
+/// One step of a variable path: `.member` or `[key]`
+#[derive(Debug, Clone, PartialEq)]
+enum Accessor {
+    Member(String),
+    Index(String),
+}
+
+impl StorageLayout {
+    pub fn from_value(value: &Value) -> Result<Self> {
+        serde_json::from_value(value.clone()).map_err(|e| anyhow!("Invalid storage layout: {}", e))
+    }
+
+    fn storage_type(&self, type_id: &str) -> Result<&StorageType> {
+        self.types
+            .as_ref()
+            .and_then(|types| types.get(type_id))
+            .ok_or_else(|| anyhow!("Storage layout has no type '{}'", type_id))
+    }
+
+    /// Slot of a top-level state variable
+    pub fn variable_slot(&self, label: &str) -> Option<U256> {
+        let entry = self.storage.iter().find(|entry| entry.label == label)?;
+        parse_slot(&entry.slot).ok()
+    }
+
+    /// Label of a type, e.g. `mapping(address => uint256)`
+    pub fn type_label(&self, type_id: &str) -> String {
+        self.storage_type(type_id)
+            .map(|t| t.label.clone())
+            .unwrap_or_else(|_| type_id.to_string())
+    }
+
+    /// Locate a variable path such as `totalSupply`, `balances[0xabc...]`,
+    /// `allowances[0xabc...][0xdef...]`, `positions[3].owner` or `config.fee`.
+    /// Dynamic array indexes need the array length, so they are bounds-checked on chain.
+    pub async fn locate(&self, path: &str, reader: &mut SlotReader<'_>) -> Result<Location> {
+        let (name, accessors) = parse_path(path)?;
+        let entry = self
+            .storage
+            .iter()
+            .find(|entry| entry.label == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = self.storage.iter().map(|e| e.label.as_str()).collect();
+                anyhow!(
+                    "No state variable '{}'. Variables: {}",
+                    name,
+                    names.join(", ")
+                )
+            })?;
+
+        let mut location = Location {
+            slot: parse_slot(&entry.slot)?,
+            offset: entry.offset,
+            type_id: entry.type_id.clone(),
+        };
+        for accessor in accessors {
+            location = self.access(location, &accessor, reader).await?;
+        }
+        Ok(location)
+    }
+
+    async fn access(
+        &self,
+        location: Location,
+        accessor: &Accessor,
+        reader: &mut SlotReader<'_>,
+    ) -> Result<Location> {
+        let storage_type = self.storage_type(&location.type_id)?;
+        match (accessor, storage_type.encoding.as_str()) {
+            (Accessor::Index(key), "mapping") => {
+                let key_type =
+                    self.storage_type(storage_type.key.as_deref().unwrap_or_default())?;
+                let mut preimage = encode_mapping_key(key_type, key)?;
+                preimage.extend_from_slice(&location.slot.to_be_bytes::<32>());
+                Ok(Location {
+                    slot: keccak256(preimage).into(),
+                    offset: 0,
+                    type_id: storage_type.value.clone().unwrap_or_default(),
+                })
+            }
+            (Accessor::Index(index), "dynamic_array") => {
+                let index = parse_index(index)?;
+                let length = reader.read(location.slot).await?;
+                if U256::from(index) >= U256::from_be_bytes(length.0) {
+                    return Err(anyhow!(
+                        "Index {} is out of bounds for {} (length {})",
+                        index,
+                        storage_type.label,
+                        U256::from_be_bytes(length.0)
+                    ));
+                }
+                let start = keccak256(location.slot.to_be_bytes::<32>()).into();
+                self.element(storage_type, start, index)
+            }
+            (Accessor::Index(index), "inplace") if storage_type.base.is_some() => {
+                let index = parse_index(index)?;
+                let length = static_array_length(&storage_type.label)?;
+                if index >= length {
+                    return Err(anyhow!(
+                        "Index {} is out of bounds for {}",
+                        index,
+                        storage_type.label
+                    ));
+                }
+                self.element(storage_type, location.slot, index)
+            }
+            (Accessor::Member(name), _) if storage_type.members.is_some() => {
+                let members = storage_type.members.as_deref().unwrap_or_default();
+                let member = members
+                    .iter()
+                    .find(|member| member.label == *name)
+                    .ok_or_else(|| anyhow!("{} has no member '{}'", storage_type.label, name))?;
+                Ok(Location {
+                    slot: location.slot + parse_slot(&member.slot)?,
+                    offset: member.offset,
+                    type_id: member.type_id.clone(),
+                })
+            }
+            (Accessor::Index(_), _) => Err(anyhow!("{} cannot be indexed", storage_type.label)),
+            (Accessor::Member(_), _) => Err(anyhow!("{} has no members", storage_type.label)),
+        }
+    }
+
+    /// Location of an array element. Elements of 16 bytes or less are packed into slots.
+    fn element(&self, array: &StorageType, start: U256, index: usize) -> Result<Location> {
+        let base = array
+            .base
+            .clone()
+            .ok_or_else(|| anyhow!("{} has no element type", array.label))?;
+        let size = self.storage_type(&base)?.size()?;
+        if size <= 16 {
+            let per_slot = 32 / size;
+            Ok(Location {
+                slot: start + U256::from(index / per_slot),
+                offset: (index % per_slot) * size,
+                type_id: base,
+            })
+        } else {
+            Ok(Location {
+                slot: start + U256::from(index * size.div_ceil(32)),
+                offset: 0,
+                type_id: base,
+            })
+        }
+    }
+
+    /// Read and decode the value at a location. Structs decode to objects and arrays to lists
+    /// (the first elements only, for long arrays); mappings need a key, so a mapping inside a
+    /// struct or array is shown as a placeholder.
+    pub fn decode<'a>(
+        &'a self,
+        location: Location,
+        reader: &'a mut SlotReader<'_>,
+    ) -> Pin<Box<dyn Future<Output = Result<Value>> + Send + 'a>> {
+        Box::pin(async move {
+            let storage_type = self.storage_type(&location.type_id)?;
+            match storage_type.encoding.as_str() {
+                "mapping" => Err(anyhow!(
+                    "{} needs a key, e.g. variable[key]",
+                    storage_type.label
+                )),
+                "bytes" => {
+                    let bytes = read_bytes(reader, location.slot).await?;
+                    Ok(if storage_type.label == "string" {
+                        Value::from(String::from_utf8_lossy(&bytes).into_owned())
+                    } else {
+                        Value::from(format!("0x{}", hex::encode(bytes)))
+                    })
+                }
+                "dynamic_array" => {
+                    let length = U256::from_be_bytes(reader.read(location.slot).await?.0);
+                    let length = usize::try_from(length).unwrap_or(usize::MAX);
+                    let start = keccak256(location.slot.to_be_bytes::<32>()).into();
+                    self.decode_elements(storage_type, start, length, reader)
+                        .await
+                }
+                "inplace" if storage_type.members.is_some() => {
+                    let mut object = serde_json::Map::new();
+                    for member in storage_type.members.as_deref().unwrap_or_default() {
+                        let member_location = Location {
+                            slot: location.slot + parse_slot(&member.slot)?,
+                            offset: member.offset,
+                            type_id: member.type_id.clone(),
+                        };
+                        object.insert(
+                            member.label.clone(),
+                            self.decode_nested(member_location, reader).await?,
+                        );
+                    }
+                    Ok(Value::Object(object))
+                }
+                "inplace" if storage_type.base.is_some() => {
+                    let length = static_array_length(&storage_type.label)?;
+                    self.decode_elements(storage_type, location.slot, length, reader)
+                        .await
+                }
+                "inplace" => {
+                    let word = reader.read(location.slot).await?;
+                    decode_value(
+                        &storage_type.label,
+                        &word,
+                        location.offset,
+                        storage_type.size()?,
+                    )
+                }
+                other => Err(anyhow!("Unsupported storage encoding '{}'", other)),
+            }
+        })
+    }
+
+    /// Decode a struct member or array element, which may be a mapping that can't be listed
+    async fn decode_nested(
+        &self,
+        location: Location,
+        reader: &mut SlotReader<'_>,
+    ) -> Result<Value> {
+        let storage_type = self.storage_type(&location.type_id)?;
+        if storage_type.encoding == "mapping" {
+            return Ok(serde_json::json!({
+                "type": storage_type.label,
+                "note": "needs a key",
+            }));
+        }
+        self.decode(location, reader).await
+    }
+
+    async fn decode_elements(
+        &self,
+        array: &StorageType,
+        start: U256,
+        length: usize,
+        reader: &mut SlotReader<'_>,
+    ) -> Result<Value> {
+        let mut elements = Vec::new();
+        for index in 0..length.min(MAX_ARRAY_ELEMENTS) {
+            let location = self.element(array, start, index)?;
+            elements.push(self.decode_nested(location, reader).await?);
+        }
+        if length <= MAX_ARRAY_ELEMENTS {
+            return Ok(Value::Array(elements));
+        }
+        Ok(serde_json::json!({
+            "length": length,
+            "first_elements": elements,
+        }))
+    }
+}
+
+impl StorageType {
+    fn size(&self) -> Result<usize> {
+        self.number_of_bytes
+            .parse()
+            .map_err(|_| anyhow!("Invalid size '{}' of {}", self.number_of_bytes, self.label))
+    }
+}
+
+/// Reads storage slots of one contract at one block, reading each slot once
+pub struct SlotReader<'a> {
+    provider: &'a RootProvider<Http<Client>>,
+    address: Address,
+    block: BlockId,
+    slots: HashMap<U256, B256>,
+}
+
+impl<'a> SlotReader<'a> {
+    pub fn new(provider: &'a RootProvider<Http<Client>>, address: Address, block: BlockId) -> Self {
+        Self {
+            provider,
+            address,
+            block,
+            slots: HashMap::new(),
+        }
+    }
+
+    pub async fn read(&mut self, slot: U256) -> Result<B256> {
+        if let Some(word) = self.slots.get(&slot) {
+            return Ok(*word);
+        }
+        let value = self
+            .provider
+            .get_storage_at(self.address, slot)
+            .block_id(self.block)
+            .await
+            .map_err(|e| anyhow!("Failed to read storage slot {:#x}: {}", slot, e))?;
+        let word = B256::from(value);
+        self.slots.insert(slot, word);
+        Ok(word)
+    }
+}
+
+/// Read a `string` or `bytes` value: short values (under 32 bytes) sit in the slot with
+/// `length * 2` in the lowest byte; long ones store `length * 2 + 1` and the data at
+/// `keccak256(slot)`
+async fn read_bytes(reader: &mut SlotReader<'_>, slot: U256) -> Result<Vec<u8>> {
+    let word = reader.read(slot).await?;
+    if word[31] & 1 == 0 {
+        let length = (word[31] / 2) as usize;
+        return Ok(word[..length.min(31)].to_vec());
+    }
+
+    let length = (U256::from_be_bytes(word.0) - U256::from(1)) / U256::from(2);
+    let length = usize::try_from(length)
+        .unwrap_or(usize::MAX)
+        .min(MAX_BYTES_LENGTH);
+    let start: U256 = keccak256(slot.to_be_bytes::<32>()).into();
+    let mut bytes = Vec::with_capacity(length);
+    for i in 0..length.div_ceil(32) {
+        bytes.extend_from_slice(reader.read(start + U256::from(i)).await?.as_slice());
+    }
+    bytes.truncate(length);
+    Ok(bytes)
+}
+
+/// Decode a value type packed into a slot at `offset` bytes from the right
+fn decode_value(label: &str, word: &B256, offset: usize, size: usize) -> Result<Value> {
+    if offset + size > 32 {
+        return Err(anyhow!("{} does not fit in its slot", label));
+    }
+    let bytes = &word[32 - offset - size..32 - offset];
+    let mut padded = [0u8; 32];
+    padded[32 - size..].copy_from_slice(bytes);
+    let unsigned = U256::from_be_bytes(padded);
+
+    Ok(if label == "bool" {
+        Value::from(!unsigned.is_zero())
+    } else if label.starts_with("address") || label.starts_with("contract ") {
+        Value::from(format!("{:?}", Address::from_slice(&bytes[size - 20..])))
+    } else if label.starts_with("uint") || label.starts_with("enum ") {
+        Value::from(unsigned.to_string())
+    } else if label.starts_with("int") {
+        // Sign-extend from the value's own width
+        if bytes[0] & 0x80 != 0 {
+            padded[..32 - size].fill(0xff);
+        }
+        Value::from(I256::from_be_bytes(padded).to_string())
+    } else {
+        Value::from(format!("0x{}", hex::encode(bytes)))
+    })
+}
+
+/// Encode a mapping key the way Solidity hashes it: value types padded to 32 bytes, strings
+/// and bytes as their raw contents
+fn encode_mapping_key(key_type: &StorageType, key: &str) -> Result<Vec<u8>> {
+    let label = key_type.label.as_str();
+    let invalid = |e: String| anyhow!("Invalid {} key '{}': {}", label, key, e);
+
+    if key_type.encoding == "bytes" {
+        return Ok(if label == "string" {
+            key.as_bytes().to_vec()
+        } else {
+            hex::decode(key.trim_start_matches("0x")).map_err(|e| invalid(e.to_string()))?
+        });
+    }
+
+    let word: [u8; 32] = if label == "bool" {
+        let value = key.parse::<bool>().map_err(|e| invalid(e.to_string()))?;
+        U256::from(value as u8).to_be_bytes()
+    } else if label.starts_with("address") || label.starts_with("contract ") {
+        Address::from_str(key)
+            .map_err(|e| invalid(e.to_string()))?
+            .into_word()
+            .0
+    } else if label.starts_with("uint") || label.starts_with("enum ") {
+        U256::from_str(key)
+            .map_err(|e| invalid(e.to_string()))?
+            .to_be_bytes()
+    } else if label.starts_with("int") {
+        I256::from_dec_str(key)
+            .map_err(|e| invalid(e.to_string()))?
+            .to_be_bytes()
+    } else if label.starts_with("bytes") {
+        let bytes =
+            hex::decode(key.trim_start_matches("0x")).map_err(|e| invalid(e.to_string()))?;
+        if bytes.len() > 32 {
+            return Err(invalid("longer than 32 bytes".to_string()));
+        }
+        let mut word = [0u8; 32];
+        word[..bytes.len()].copy_from_slice(&bytes);
+        word
+    } else {
+        return Err(anyhow!("Unsupported mapping key type {}", label));
+    };
+    Ok(word.to_vec())
+}
+
+/// Split `a.b[c][d].e` into the variable name and its accessors
+fn parse_path(path: &str) -> Result<(String, Vec<Accessor>)> {
+    let path = path.trim();
+    let name_end = path.find(['.', '[']).unwrap_or(path.len());
+    let name = &path[..name_end];
+    if name.is_empty() {
+        return Err(anyhow!("Invalid variable path '{}'", path));
+    }
+
+    let mut accessors = Vec::new();
+    let mut rest = &path[name_end..];
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let end = after
+                .find(']')
+                .ok_or_else(|| anyhow!("Unclosed '[' in variable path '{}'", path))?;
+            let key = after[..end].trim().trim_matches(|c| c == '"' || c == '\'');
+            accessors.push(Accessor::Index(key.to_string()));
+            rest = &after[end + 1..];
+        } else if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            if end == 0 {
+                return Err(anyhow!("Empty member name in variable path '{}'", path));
+            }
+            accessors.push(Accessor::Member(after[..end].to_string()));
+            rest = &after[end..];
+        } else {
+            return Err(anyhow!("Invalid variable path '{}'", path));
+        }
+    }
+    Ok((name.to_string(), accessors))
+}
+
+/// Parse a slot given as a decimal or `0x` hex number
+pub fn parse_slot(slot: &str) -> Result<U256> {
+    U256::from_str(slot.trim()).map_err(|e| anyhow!("Invalid storage slot '{}': {}", slot, e))
+}
+
+fn parse_index(index: &str) -> Result<usize> {
+    index
+        .parse()
+        .map_err(|_| anyhow!("Invalid array index '{}'", index))
+}
+
+/// Length of a static array from its label, e.g. `uint256[3]`
+fn static_array_length(label: &str) -> Result<usize> {
+    label
+        .rsplit_once('[')
+        .and_then(|(_, length)| length.strip_suffix(']'))
+        .and_then(|length| length.parse().ok())
+        .ok_or_else(|| anyhow!("Cannot tell the length of {}", label))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::b256;
+    use serde_json::json;
+
+    fn layout() -> StorageLayout {
+        StorageLayout::from_value(&json!({
+            "storage": [
+                { "label": "owner", "slot": "0", "offset": 0, "type": "t_address" },
+                { "label": "paused", "slot": "0", "offset": 20, "type": "t_bool" },
+                { "label": "balances", "slot": "1", "offset": 0,
+                  "type": "t_mapping(t_address,t_uint256)" },
+                { "label": "fees", "slot": "2", "offset": 0, "type": "t_array(t_uint64)dyn_storage" },
+                { "label": "config", "slot": "3", "offset": 0, "type": "t_struct(Config)1_storage" }
+            ],
+            "types": {
+                "t_address": { "encoding": "inplace", "label": "address", "numberOfBytes": "20" },
+                "t_bool": { "encoding": "inplace", "label": "bool", "numberOfBytes": "1" },
+                "t_int24": { "encoding": "inplace", "label": "int24", "numberOfBytes": "3" },
+                "t_uint64": { "encoding": "inplace", "label": "uint64", "numberOfBytes": "8" },
+                "t_uint256": { "encoding": "inplace", "label": "uint256", "numberOfBytes": "32" },
+                "t_mapping(t_address,t_uint256)": {
+                    "encoding": "mapping", "label": "mapping(address => uint256)",
+                    "numberOfBytes": "32", "key": "t_address", "value": "t_uint256"
+                },
+                "t_array(t_uint64)dyn_storage": {
+                    "encoding": "dynamic_array", "label": "uint64[]",
+                    "numberOfBytes": "32", "base": "t_uint64"
+                },
+                "t_struct(Config)1_storage": {
+                    "encoding": "inplace", "label": "struct Vault.Config", "numberOfBytes": "96",
+                    "members": [
+                        { "label": "fee", "slot": "0", "offset": 0, "type": "t_uint256" },
+                        { "label": "tick", "slot": "1", "offset": 0, "type": "t_int24" },
+                        { "label": "limits", "slot": "2", "offset": 0,
+                          "type": "t_mapping(t_address,t_uint256)" }
+                    ]
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_path() {
+        let (name, accessors) = parse_path("positions[3].owner[\"key\"]").unwrap();
+        assert_eq!(name, "positions");
+        assert_eq!(
+            accessors,
+            [
+                Accessor::Index("3".to_string()),
+                Accessor::Member("owner".to_string()),
+                Accessor::Index("key".to_string())
+            ]
+        );
+        assert!(parse_path("balances[0x1").is_err());
+        assert!(parse_path(".x").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_locate_without_reads() {
+        let layout = layout();
+        // The reader is never used for mappings, static offsets and struct members
+        let provider = RootProvider::new_http("http://127.0.0.1:1".parse().unwrap());
+        let mut reader = SlotReader::new(&provider, Address::ZERO, BlockId::latest());
+
+        let paused = layout.locate("paused", &mut reader).await.unwrap();
+        assert_eq!((paused.slot, paused.offset), (U256::ZERO, 20));
+
+        // keccak256(pad32(key) ++ pad32(slot)), as Solidity computes it
+        let holder = "0x000000000000000000000000000000000000dEaD";
+        let balance = layout
+            .locate(&format!("balances[{}]", holder), &mut reader)
+            .await
+            .unwrap();
+        let mut preimage = Address::from_str(holder).unwrap().into_word().to_vec();
+        preimage.extend_from_slice(&U256::from(1).to_be_bytes::<32>());
+        assert_eq!(balance.slot, U256::from_be_bytes(keccak256(preimage).0));
+        assert_eq!(balance.type_id, "t_uint256");
+
+        let tick = layout.locate("config.tick", &mut reader).await.unwrap();
+        assert_eq!(tick.slot, U256::from(4));
+        assert!(layout.locate("config.missing", &mut reader).await.is_err());
+        assert!(layout.locate("missing", &mut reader).await.is_err());
+
+        // Four uint64 elements share a slot
+        let fees = layout.storage_type("t_array(t_uint64)dyn_storage").unwrap();
+        let element = layout.element(fees, U256::from(100), 5).unwrap();
+        assert_eq!((element.slot, element.offset), (U256::from(101), 8));
+    }
+
+    #[tokio::test]
+    async fn test_decode_struct_with_mapping() {
+        let layout = layout();
+        let provider = RootProvider::new_http("http://127.0.0.1:1".parse().unwrap());
+        let mut reader = SlotReader::new(&provider, Address::ZERO, BlockId::latest());
+        reader
+            .slots
+            .insert(U256::from(3), B256::from(U256::from(500)));
+        reader.slots.insert(U256::from(4), B256::with_last_byte(7));
+
+        // The mapping member takes no read; only a mapping asked for directly needs a key
+        let config = layout.locate("config", &mut reader).await.unwrap();
+        assert_eq!(
+            layout.decode(config, &mut reader).await.unwrap(),
+            json!({
+                "fee": "500",
+                "tick": "7",
+                "limits": { "type": "mapping(address => uint256)", "note": "needs a key" }
+            })
+        );
+        let balances = layout.locate("balances", &mut reader).await.unwrap();
+        let error = layout.decode(balances, &mut reader).await.unwrap_err();
+        assert!(error.to_string().contains("needs a key"));
+    }
+
+    #[test]
+    fn test_decode_value() {
+        let word = b256!("0000000000000000000000fffffe000000000000000000000000000000000001");
+        assert_eq!(decode_value("uint8", &word, 0, 1).unwrap(), json!("1"));
+        assert_eq!(decode_value("int24", &word, 18, 3).unwrap(), json!("-2"));
+        assert_eq!(decode_value("bool", &word, 0, 1).unwrap(), json!(true));
+        assert!(decode_value("uint256", &word, 8, 32).is_err());
+    }
+}
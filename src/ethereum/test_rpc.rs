@@ -0,0 +1,102 @@
+use alloy::{
+    providers::{ProviderBuilder, RootProvider},
+    transports::http::{Client, Http},
+};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+/// Hash of the block returned by [`block`]
+pub const BLOCK_HASH: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+/// Number of the block returned by [`block`]
+pub const BLOCK_NUMBER: u64 = 1234;
+
+pub type Handler = fn(&str, &Value) -> std::result::Result<Value, String>;
+
+/// Provider for a local JSON-RPC server that answers each request with `handler`
+pub async fn rpc_provider(handler: Handler) -> RootProvider<Http<Client>> {
+    ProviderBuilder::new().on_http(rpc_url(handler).await.parse().unwrap())
+}
+
+/// URL of a local JSON-RPC server that answers each request with `handler`
+pub async fn rpc_url(handler: Handler) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve_rpc(stream, handler));
+        }
+    });
+    url
+}
+
+async fn serve_rpc(stream: tokio::net::TcpStream, handler: Handler) {
+    let mut stream = BufReader::new(stream);
+    loop {
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+                return;
+            }
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+
+        let mut body = vec![0; content_length];
+        stream.read_exact(&mut body).await.unwrap();
+        let request: Value = serde_json::from_slice(&body).unwrap();
+        let response = match handler(request["method"].as_str().unwrap(), &request["params"]) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+            Err(message) => json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "error": { "code": -32601, "message": message }
+            }),
+        }
+        .to_string();
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            response.len()
+        );
+        let stream = stream.get_mut();
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream.write_all(response.as_bytes()).await.unwrap();
+    }
+}
+
+/// Block number of a hex quantity parameter
+pub fn block_number(value: &Value) -> u64 {
+    u64::from_str_radix(value.as_str().unwrap().trim_start_matches("0x"), 16).unwrap()
+}
+
+/// Block 1234 of a chain, holding the given transactions
+pub fn block(transactions: Value) -> Value {
+    let zero = format!("0x{}", "0".repeat(64));
+    json!({
+        "hash": BLOCK_HASH,
+        "parentHash": zero,
+        "sha3Uncles": zero,
+        "miner": format!("0x{}", "0".repeat(40)),
+        "stateRoot": zero,
+        "transactionsRoot": zero,
+        "receiptsRoot": zero,
+        "logsBloom": format!("0x{}", "0".repeat(512)),
+        "difficulty": "0x0",
+        "number": format!("{:#x}", BLOCK_NUMBER),
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0x0",
+        "timestamp": "0x0",
+        "extraData": "0x",
+        "mixHash": zero,
+        "nonce": "0x0000000000000000",
+        "uncles": [],
+        "transactions": transactions
+    })
+}
@@ -0,0 +1,320 @@
+use crate::ethereum::utils;
+use alloy::{
+    dyn_abi::{DynSolValue, JsonAbiExt},
+    json_abi::JsonAbi,
+    primitives::{Address, Bytes, Selector, U256, U64},
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// A frame of Geth `callTracer` output
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    #[serde(rename = "type")]
+    call_type: String,
+    from: Address,
+    to: Option<Address>,
+    value: Option<U256>,
+    #[serde(default)]
+    gas_used: U64,
+    #[serde(default)]
+    input: Bytes,
+    output: Option<Bytes>,
+    error: Option<String>,
+    #[serde(default)]
+    calls: Vec<CallFrame>,
+}
+
+/// A call in the decoded call tree
+#[derive(Debug, Clone, Serialize)]
+pub struct CallNode {
+    /// CALL, STATICCALL, DELEGATECALL, CREATE, ...
+    pub call_type: String,
+    pub from: Address,
+    pub to: Option<Address>,
+    /// Wei sent with the call, when any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+    /// Signature of the called function, when the target's ABI has it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+    pub gas_used: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Decoded revert data of a failed call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<CallNode>,
+}
+
+/// The call where a revert originated
+#[derive(Debug, Clone, Serialize)]
+pub struct RevertPoint {
+    /// Indexes into `calls` leading from the top-level call to the reverting one
+    pub path: Vec<usize>,
+    pub depth: usize,
+    pub to: Option<Address>,
+    pub function: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CallTrace {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_point: Option<RevertPoint>,
+    pub root: CallNode,
+}
+
+impl CallFrame {
+    pub fn from_value(value: &Value) -> Result<Self> {
+        serde_json::from_value(value.clone()).map_err(|e| anyhow!("Invalid call trace: {}", e))
+    }
+
+    /// Every address called in the trace, in order of first appearance
+    pub fn targets(&self) -> Vec<Address> {
+        let mut targets = Vec::new();
+        self.collect_targets(&mut targets);
+        targets
+    }
+
+    fn collect_targets(&self, targets: &mut Vec<Address>) {
+        if let Some(to) = self.to {
+            if !targets.contains(&to) {
+                targets.push(to);
+            }
+        }
+        for call in &self.calls {
+            call.collect_targets(targets);
+        }
+    }
+
+    /// Path to the frame a revert came from. A failed call is blamed on its last failed
+    /// subcall that returned the same data, since that revert bubbled up; a call that fails
+    /// after catching a subcall's revert is blamed itself.
+    fn revert_path(&self) -> Option<Vec<usize>> {
+        self.error.as_ref()?;
+        let mut path = Vec::new();
+        let mut frame = self;
+        while let Some((index, call)) = frame
+            .calls
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, call)| call.error.is_some() && call.output == frame.output)
+        {
+            path.push(index);
+            frame = call;
+        }
+        Some(path)
+    }
+
+    fn selector(&self) -> Option<Selector> {
+        let is_create = self.call_type.starts_with("CREATE");
+        (!is_create && self.input.len() >= 4).then(|| Selector::from_slice(&self.input[..4]))
+    }
+}
+
+impl CallTrace {
+    /// Decode a call tree with the ABIs of the called contracts
+    pub fn new(frame: &CallFrame, abis: &HashMap<Address, JsonAbi>) -> Self {
+        let calls = decode_frame(frame, abis);
+        let revert_point = frame.revert_path().map(|path| {
+            let node = path.iter().fold(&calls, |node, index| &node.calls[*index]);
+            RevertPoint {
+                depth: path.len(),
+                to: node.to,
+                function: node.function.clone(),
+                reason: node.revert_reason.clone().or_else(|| node.error.clone()),
+                path,
+            }
+        });
+        Self {
+            revert_point,
+            root: calls,
+        }
+    }
+}
+
+fn decode_frame(frame: &CallFrame, abis: &HashMap<Address, JsonAbi>) -> CallNode {
+    let abi = frame.to.and_then(|to| abis.get(&to));
+    let selector = frame.selector();
+    let function = selector.and_then(|selector| {
+        abi?.functions()
+            .find(|function| function.selector() == selector)
+            .map(|function| function.signature())
+    });
+    let revert_reason = match (&frame.error, &frame.output) {
+        (Some(_), Some(output)) if !output.is_empty() => Some(decode_revert(output, abi)),
+        _ => None,
+    };
+
+    CallNode {
+        call_type: frame.call_type.clone(),
+        from: frame.from,
+        to: frame.to,
+        value: frame
+            .value
+            .filter(|value| !value.is_zero())
+            .map(|value| value.to_string()),
+        selector: selector.map(|selector| selector.to_string()),
+        function,
+        gas_used: frame.gas_used.to(),
+        error: frame.error.clone(),
+        revert_reason,
+        calls: frame
+            .calls
+            .iter()
+            .map(|call| decode_frame(call, abis))
+            .collect(),
+    }
+}
+
+/// Decode revert data as a custom error of the reverting contract's ABI, or as a standard
+/// `Error(string)` or `Panic(uint256)`
+pub fn decode_revert(data: &[u8], abi: Option<&JsonAbi>) -> String {
+    let custom = data.get(..4).zip(abi).and_then(|(selector, abi)| {
+        let error = abi.errors().find(|error| error.selector() == selector)?;
+        let values = error.abi_decode_input(&data[4..], false).ok()?;
+        let values: Vec<String> = values.iter().map(format_value).collect();
+        Some(format!("{}({})", error.name, values.join(", ")))
+    });
+    match custom {
+        Some(error) => format!("Execution reverted: {}", error),
+        None => utils::revert_message(data),
+    }
+}
+
+/// Format an error argument the way it would be written in Solidity
+fn format_value(value: &DynSolValue) -> String {
+    match value {
+        DynSolValue::Address(address) => address.to_checksum(None),
+        DynSolValue::Bool(value) => value.to_string(),
+        DynSolValue::Int(value, _) => value.to_string(),
+        DynSolValue::Uint(value, _) => value.to_string(),
+        DynSolValue::String(value) => format!("{:?}", value),
+        DynSolValue::Bytes(bytes) => format!("0x{}", hex::encode(bytes)),
+        DynSolValue::FixedBytes(word, size) => format!("0x{}", hex::encode(&word[..*size])),
+        DynSolValue::Array(values) | DynSolValue::FixedArray(values) => {
+            let values: Vec<String> = values.iter().map(format_value).collect();
+            format!("[{}]", values.join(", "))
+        }
+        DynSolValue::Tuple(values) | DynSolValue::CustomStruct { tuple: values, .. } => {
+            let values: Vec<String> = values.iter().map(format_value).collect();
+            format!("({})", values.join(", "))
+        }
+        DynSolValue::Function(function) => format!("0x{}", hex::encode(function)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::json_abi::Function;
+    use serde_json::json;
+
+    const ROUTER: &str = "0xe592427a0aece92de3edee1f18e0157c05861564";
+    const POOL: &str = "0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640";
+    const TOKEN: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+    #[test]
+    fn test_revert_point() {
+        // Error(string) "STF", returned by the token and bubbled up through the pool and router
+        let revert = Function::parse("function Error(string)")
+            .unwrap()
+            .abi_encode_input(&[DynSolValue::String("STF".to_string())])
+            .unwrap();
+        let revert = format!("0x{}", hex::encode(revert));
+        let frame = CallFrame::from_value(&json!({
+            "type": "CALL",
+            "from": "0x0000000000000000000000000000000000000001",
+            "to": ROUTER,
+            "value": "0xde0b6b3a7640000",
+            "gasUsed": "0x1d4c0",
+            "input": "0x414bf389",
+            "output": revert,
+            "error": "execution reverted",
+            "calls": [
+                {
+                    "type": "STATICCALL",
+                    "from": ROUTER,
+                    "to": TOKEN,
+                    "gasUsed": "0x9c4",
+                    "input": "0x70a08231",
+                    "output": "0x00"
+                },
+                {
+                    "type": "CALL",
+                    "from": ROUTER,
+                    "to": POOL,
+                    "gasUsed": "0x7530",
+                    "input": "0x128acb08",
+                    "output": revert,
+                    "error": "execution reverted",
+                    "calls": [{
+                        "type": "CALL",
+                        "from": POOL,
+                        "to": TOKEN,
+                        "gasUsed": "0x2710",
+                        "input": "0x23b872dd",
+                        "output": revert,
+                        "error": "execution reverted"
+                    }]
+                }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(frame.targets().len(), 3);
+
+        let mut abis = HashMap::new();
+        let mut abi = JsonAbi::new();
+        let transfer_from =
+            Function::parse("function transferFrom(address,address,uint256) returns (bool)")
+                .unwrap();
+        abi.functions
+            .insert(transfer_from.name.clone(), vec![transfer_from]);
+        abis.insert(TOKEN.parse().unwrap(), abi);
+
+        let trace = CallTrace::new(&frame, &abis);
+        let point = trace.revert_point.unwrap();
+        assert_eq!(point.path, [1, 0]);
+        assert_eq!(point.to, TOKEN.parse().ok());
+        assert_eq!(
+            point.function.as_deref(),
+            Some("transferFrom(address,address,uint256)")
+        );
+        assert_eq!(point.reason.as_deref(), Some("Execution reverted: STF"));
+        assert_eq!(trace.root.value.as_deref(), Some("1000000000000000000"));
+        assert_eq!(trace.root.gas_used, 120_000);
+        assert!(trace.root.calls[0].revert_reason.is_none());
+    }
+
+    #[test]
+    fn test_custom_error() {
+        let mut abi: JsonAbi = serde_json::from_value(json!([{
+            "type": "error",
+            "name": "InsufficientBalance",
+            "inputs": [
+                { "name": "available", "type": "uint256" },
+                { "name": "required", "type": "uint256" }
+            ]
+        }]))
+        .unwrap();
+        let error = abi.errors().next().unwrap().clone();
+        let mut data = error.selector().to_vec();
+        data.extend_from_slice(&U256::from(5).to_be_bytes::<32>());
+        data.extend_from_slice(&U256::from(7).to_be_bytes::<32>());
+        assert_eq!(
+            decode_revert(&data, Some(&abi)),
+            "Execution reverted: InsufficientBalance(5, 7)"
+        );
+
+        abi.errors.clear();
+        assert!(decode_revert(&data, Some(&abi)).starts_with("Execution reverted with data 0x"));
+    }
+}
@@ -1,6 +1,40 @@
-use alloy::primitives::Address;
+use alloy::{
+    eips::{BlockId, BlockNumberOrTag},
+    primitives::{Address, B256},
+};
 use anyhow::{anyhow, Result};
+use serde_json::Value;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// How long a failed lookup is remembered before it is tried again
+pub const FAILED_LOOKUP_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Result of a lookup kept for reuse. Successes are kept for good, failures only for
+/// [`FAILED_LOOKUP_TTL`] since they may be transient.
+#[derive(Debug, Clone)]
+pub struct CachedLookup<T> {
+    result: std::result::Result<T, String>,
+    cached_at: Instant,
+}
+
+impl<T: Clone> CachedLookup<T> {
+    pub fn new(result: &Result<T>) -> Self {
+        Self {
+            result: result.as_ref().cloned().map_err(|e| e.to_string()),
+            cached_at: Instant::now(),
+        }
+    }
+
+    /// The cached result, or `None` once a failure has expired
+    pub fn get(&self) -> Option<Result<T>> {
+        match &self.result {
+            Ok(value) => Some(Ok(value.clone())),
+            Err(_) if self.cached_at.elapsed() >= FAILED_LOOKUP_TTL => None,
+            Err(e) => Some(Err(anyhow!("{}", e))),
+        }
+    }
+}
 
 /// Validates and normalizes an Ethereum address
 pub fn validate_address(address: &str) -> Result<Address> {
@@ -55,26 +89,74 @@ pub fn validate_network(network: &str, available_networks: &[String]) -> Result<
     Ok(())
 }
 
-/// Validates function name
+/// Validates function name.
+///
+/// Accepts a bare name (`transfer`), a full signature
+/// (`safeTransferFrom(address,address,uint256)`) or a 4-byte selector (`0xa9059cbb`).
 pub fn validate_function_name(function_name: &str) -> Result<()> {
+    let function_name = function_name.trim();
+
     if function_name.is_empty() {
         return Err(anyhow!("Function name cannot be empty"));
     }
 
-    // Check for valid Solidity identifier
-    if !function_name.chars().next().unwrap().is_ascii_alphabetic()
-        && !function_name.starts_with('_')
+    if let Some(selector) = function_name
+        .strip_prefix("0x")
+        .or_else(|| function_name.strip_prefix("0X"))
     {
+        if selector.len() != 8 || !selector.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow!(
+                "Invalid function selector: '{}'. Selectors must be 0x followed by 8 hex characters",
+                function_name
+            ));
+        }
+        return Ok(());
+    }
+
+    // For a signature, validate the name part and the overall shape of the parameter list,
+    // which may be followed by modifiers and outputs
+    let name = match function_name.find('(') {
+        Some(open) => {
+            let params = &function_name[open..];
+            if !params.ends_with(')') || params.matches('(').count() != params.matches(')').count()
+            {
+                return Err(anyhow!(
+                    "Invalid function signature: '{}'. Unbalanced parentheses",
+                    function_name
+                ));
+            }
+
+            if !params.chars().all(|c| {
+                c.is_ascii_alphanumeric() || matches!(c, '_' | '(' | ')' | ',' | '[' | ']' | ' ')
+            }) {
+                return Err(anyhow!(
+                    "Invalid function signature: '{}'. Contains invalid characters",
+                    function_name
+                ));
+            }
+
+            let name = function_name[..open].trim();
+            name.strip_prefix("function ").unwrap_or(name).trim()
+        }
+        None => function_name,
+    };
+
+    if name.is_empty() {
+        return Err(anyhow!(
+            "Invalid function signature: '{}'. Missing function name",
+            function_name
+        ));
+    }
+
+    // Check for valid Solidity identifier
+    if !name.chars().next().unwrap().is_ascii_alphabetic() && !name.starts_with('_') {
         return Err(anyhow!(
             "Invalid function name: '{}'. Function names must start with a letter or underscore",
             function_name
         ));
     }
 
-    if !function_name
-        .chars()
-        .all(|c| c.is_ascii_alphanumeric() || c == '_')
-    {
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
         return Err(anyhow!(
             "Invalid function name: '{}'. Function names can only contain letters, numbers, and underscores",
             function_name
@@ -119,6 +201,107 @@ pub fn validate_block_number(block: Option<u64>) -> Result<u64> {
     }
 }
 
+/// Parses a block range bound: a block number (JSON number, decimal or 0x-prefixed hex string)
+/// or one of the `latest`, `finalized`, `safe` and `earliest` tags
+pub fn parse_block_bound(value: &Value) -> Result<BlockNumberOrTag> {
+    let block = match value {
+        Value::Number(n) => {
+            let number = n.as_u64().ok_or_else(|| {
+                anyhow!(
+                    "Invalid block number: {}. Block numbers must be non-negative integers",
+                    n
+                )
+            })?;
+            BlockNumberOrTag::Number(validate_block_number(Some(number))?)
+        }
+        Value::String(s) => match s.trim().to_lowercase().as_str() {
+            "latest" => BlockNumberOrTag::Latest,
+            "finalized" => BlockNumberOrTag::Finalized,
+            "safe" => BlockNumberOrTag::Safe,
+            "earliest" => BlockNumberOrTag::Earliest,
+            "pending" => {
+                return Err(anyhow!(
+                    "The 'pending' block tag cannot be used as a range bound"
+                ))
+            }
+            number => {
+                let parsed = match number.strip_prefix("0x") {
+                    Some(hex_digits) => u64::from_str_radix(hex_digits, 16),
+                    None => number.parse::<u64>(),
+                }
+                .map_err(|_| {
+                    anyhow!(
+                        "Invalid block: '{}'. Use a block number or one of latest, finalized, safe, earliest",
+                        s
+                    )
+                })?;
+                BlockNumberOrTag::Number(validate_block_number(Some(parsed))?)
+            }
+        },
+        _ => {
+            return Err(anyhow!(
+                "Block must be a number or a string, got: {}",
+                value
+            ))
+        }
+    };
+
+    Ok(block)
+}
+
+/// Parses the block a call runs against: a block hash, the `pending` tag, or anything
+/// [`parse_block_bound`] accepts
+pub fn parse_block_id(value: &Value) -> Result<BlockId> {
+    if let Value::String(s) = value {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("pending") {
+            return Ok(BlockId::pending());
+        }
+        if s.len() == 66 && s.starts_with("0x") {
+            let hash =
+                B256::from_str(s).map_err(|e| anyhow!("Invalid block hash '{}': {}", s, e))?;
+            return Ok(BlockId::hash(hash));
+        }
+    }
+    Ok(parse_block_bound(value)?.into())
+}
+
+/// Checks whether an eth_getLogs error means the block range or result set was too large
+/// and the request should be retried over a smaller range
+pub fn is_log_range_error(error: &str) -> bool {
+    let error = error.to_lowercase();
+    // Throttling also says "too many" or "exceeded"; splitting the range would only add load.
+    // 429 is matched as a whole number so block numbers that contain it still count.
+    let throttled = [
+        "rate limit",
+        "too many requests",
+        "capacity",
+        "compute units",
+    ]
+    .iter()
+    .any(|pattern| error.contains(pattern))
+        || error
+            .split(|c: char| !c.is_ascii_digit())
+            .any(|number| number == "429");
+    if throttled {
+        return false;
+    }
+
+    [
+        "block range",
+        "range too large",
+        "range is too large",
+        "too many",
+        "more than",
+        "exceed",
+        "response size",
+        "query timeout",
+        "-32005",
+    ]
+    .iter()
+    .any(|pattern| error.contains(pattern))
+}
+
 /// Creates user-friendly error messages for common RPC errors
 pub fn interpret_rpc_error(error: &str) -> String {
     if error.contains("execution reverted") {
@@ -146,6 +329,8 @@ pub fn interpret_rpc_error(error: &str) -> String {
             .to_string()
     } else if error.contains("rate limit") {
         "Rate limit error: Too many requests to the RPC endpoint. Try again in a few moments or use a different endpoint.".to_string()
+    } else if error.contains("missing trie node") || error.contains("historical state") {
+        "RPC error: The endpoint does not have the state of that block. Historical blocks need an archive node.".to_string()
     } else if error.contains("method not found") {
         "RPC error: The requested method is not supported by this RPC endpoint. Try using a different endpoint.".to_string()
     } else {
@@ -153,17 +338,34 @@ pub fn interpret_rpc_error(error: &str) -> String {
     }
 }
 
+/// Describe revert data: an `Error(string)` reason, a `Panic(uint256)` code, or the raw data
+pub fn revert_message(data: &[u8]) -> String {
+    if data.is_empty() {
+        return "Execution reverted without a reason".to_string();
+    }
+    match alloy::sol_types::decode_revert_reason(data) {
+        Some(reason) => format!(
+            "Execution reverted: {}",
+            reason.strip_prefix("revert: ").unwrap_or(&reason)
+        ),
+        None => format!("Execution reverted with data 0x{}", hex::encode(data)),
+    }
+}
+
 /// Creates user-friendly error messages for ABI-related errors
 pub fn interpret_abi_error(error: &str, contract_address: &str) -> String {
     if error.contains("404") || error.contains("not found") {
         format!(
-            "Contract verification not found: The contract at {} is not verified on Etherscan. Verified contracts are required for automatic ABI resolution.",
+            "Contract verification not found: No configured ABI provider has an ABI for the contract at {}. Verify it on Etherscan or Sourcify, or register an ABI manually.",
             contract_address
         )
     } else if error.contains("rate limit") || error.contains("429") {
         "API rate limit: Too many requests to Etherscan API. Try again in a few moments or provide your own ETHERSCAN_API_KEY.".to_string()
-    } else if error.contains("invalid API key") || error.contains("403") {
-        "API authentication error: Invalid Etherscan API key. Check your ETHERSCAN_API_KEY environment variable.".to_string()
+    } else if error.contains("invalid API key")
+        || error.contains("Invalid API Key")
+        || error.contains("403")
+    {
+        "API authentication error: Missing or invalid explorer API key. Check your ETHERSCAN_API_KEY environment variable or the network's explorer_api_key.".to_string()
     } else if error.contains("network") || error.contains("connection") {
         "Network error: Cannot connect to Etherscan API. Check your internet connection."
             .to_string()
@@ -212,5 +414,151 @@ mod tests {
         assert!(validate_function_name("").is_err());
         assert!(validate_function_name("123invalid").is_err());
         assert!(validate_function_name("invalid-name").is_err());
+
+        // Full signatures and selectors
+        assert!(validate_function_name("safeTransferFrom(address,address,uint256)").is_ok());
+        assert!(validate_function_name("swap((address,uint24)[], bytes32[2])").is_ok());
+        assert!(validate_function_name("0xa9059cbb").is_ok());
+        assert!(validate_function_name("balanceOf(address)(uint256)").is_ok());
+        assert!(validate_function_name(
+            "function transfer(address to, uint256 amt) external returns (bool)"
+        )
+        .is_ok());
+
+        assert!(validate_function_name("transfer(address,uint256").is_err());
+        assert!(validate_function_name("(address)").is_err());
+        assert!(validate_function_name("transfer(address;uint256)").is_err());
+        assert!(validate_function_name("0xa9059c").is_err());
+    }
+
+    #[test]
+    fn test_parse_block_bound() {
+        use serde_json::json;
+
+        assert_eq!(
+            parse_block_bound(&json!(100)).unwrap(),
+            BlockNumberOrTag::Number(100)
+        );
+        assert_eq!(
+            parse_block_bound(&json!("0x64")).unwrap(),
+            BlockNumberOrTag::Number(100)
+        );
+        assert_eq!(
+            parse_block_bound(&json!("100")).unwrap(),
+            BlockNumberOrTag::Number(100)
+        );
+        assert_eq!(
+            parse_block_bound(&json!("Finalized")).unwrap(),
+            BlockNumberOrTag::Finalized
+        );
+        assert_eq!(
+            parse_block_bound(&json!("latest")).unwrap(),
+            BlockNumberOrTag::Latest
+        );
+
+        assert!(parse_block_bound(&json!("pending")).is_err());
+        assert!(parse_block_bound(&json!(-1)).is_err());
+        assert!(parse_block_bound(&json!("soon")).is_err());
+        assert!(parse_block_bound(&json!(u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_parse_block_id() {
+        use serde_json::json;
+
+        let hash = "0x88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6";
+        assert_eq!(
+            parse_block_id(&json!(hash)).unwrap(),
+            BlockId::hash(B256::from_str(hash).unwrap())
+        );
+        assert_eq!(
+            parse_block_id(&json!("pending")).unwrap(),
+            BlockId::pending()
+        );
+        assert_eq!(parse_block_id(&json!("safe")).unwrap(), BlockId::safe());
+        assert_eq!(
+            parse_block_id(&json!(17_000_000)).unwrap(),
+            BlockId::number(17_000_000)
+        );
+        assert!(parse_block_id(&json!(
+            "0xzz96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6"
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn test_cached_lookup() {
+        let found = CachedLookup::new(&Ok(42));
+        assert_eq!(found.get().unwrap().unwrap(), 42);
+
+        let failed = CachedLookup::<u64>::new(&Err(anyhow!("not verified")));
+        assert_eq!(
+            failed.get().unwrap().unwrap_err().to_string(),
+            "not verified"
+        );
+
+        // Failures are looked up again once the TTL passes; successes are kept
+        let expired = Instant::now().checked_sub(FAILED_LOOKUP_TTL).unwrap();
+        let failed = CachedLookup {
+            cached_at: expired,
+            ..failed
+        };
+        assert!(failed.get().is_none());
+        let found = CachedLookup {
+            cached_at: expired,
+            ..found
+        };
+        assert!(found.get().is_some());
+    }
+
+    #[test]
+    fn test_revert_message() {
+        use alloy::{
+            dyn_abi::{DynSolValue, JsonAbiExt},
+            json_abi::Function,
+            primitives::U256,
+        };
+
+        // Error(string) and Panic(uint256) reasons are decoded; anything else is shown raw
+        let error = Function::parse("function Error(string)").unwrap();
+        let data = error
+            .abi_encode_input(&[DynSolValue::String("Not enough".into())])
+            .unwrap();
+        assert_eq!(revert_message(&data), "Execution reverted: Not enough");
+        let panic = Function::parse("function Panic(uint256)").unwrap();
+        let data = panic
+            .abi_encode_input(&[DynSolValue::Uint(U256::from(0x11), 256)])
+            .unwrap();
+        assert!(revert_message(&data).contains("overflow"));
+        assert!(revert_message(&[]).contains("without a reason"));
+        assert_eq!(
+            revert_message(&[0xde, 0xad, 0xbe, 0xef]),
+            "Execution reverted with data 0xdeadbeef"
+        );
+    }
+
+    #[test]
+    fn test_is_log_range_error() {
+        assert!(is_log_range_error(
+            "server returned an error response: error code -32005: query returned more than 10000 results"
+        ));
+        assert!(is_log_range_error(
+            "Log response size exceeded. You can make eth_getLogs requests with up to a 2K block range"
+        ));
+        assert!(!is_log_range_error("error code 429: rate limit exceeded"));
+        assert!(!is_log_range_error(
+            "HTTP status client error (429) for url"
+        ));
+        assert!(!is_log_range_error("429 Too Many Requests"));
+        assert!(!is_log_range_error(
+            "Your app has exceeded its compute units per second capacity"
+        ));
+        assert!(!is_log_range_error("too many requests, slow down"));
+        assert!(!is_log_range_error("request exceeds account capacity"));
+        assert!(!is_log_range_error("monthly compute units exhausted"));
+        assert!(is_log_range_error(
+            "block range 14290000-14390000 is too large"
+        ));
+        assert!(!is_log_range_error("connection refused"));
     }
 }
@@ -13,7 +13,10 @@ use tracing::{error, info};
 
 use crate::{
     config::Config,
-    ethereum::{contract::ContractManager, provider::ProviderManager, FunctionCall},
+    ethereum::{
+        contract::ContractManager, provider::ProviderManager, BatchCall, EventFilter,
+        EventPagination, FunctionCall, ViewCall,
+    },
 };
 
 #[derive(Debug, Clone)]
@@ -29,12 +32,71 @@ struct ContractInfoRequest {
     network: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct ContractSourceRequest {
+    address: String,
+    /// Source file path, or just its file name when unambiguous; omit to list the files
+    file: Option<String>,
+    network: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct ReadStorageRequest {
+    address: String,
+    /// Raw slot, decimal or 0x-prefixed hex; give this or variable
+    slot: Option<String>,
+    /// State variable path, e.g. totalSupply, balances[0x...], allowances[0x...][0x...],
+    /// positions[3].owner; needs a storage layout
+    variable: Option<String>,
+    /// Solidity storageLayout output to use instead of the one from Sourcify or artifacts
+    storage_layout: Option<Value>,
+    /// Block number, block hash or tag (latest, pending, safe, finalized); defaults to latest
+    block: Option<Value>,
+    network: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize, JsonSchema)]
 struct ViewFunctionRequest {
     contract_address: String,
     function_name: String,
     parameters: Value,
     network: Option<String>,
+    /// Block number, block hash or tag (latest, pending, safe, finalized); defaults to latest
+    block: Option<Value>,
+    /// State overrides by address, e.g. {"0xholder": {"balance": "0x..."}, "0xtoken":
+    /// {"erc20_balances": {"0xholder": "1000"}}}; see the README for all fields
+    state_overrides: Option<Value>,
+    /// Wrap each decoded return value as {"type": ..., "value": ...}
+    include_types: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct Erc20StorageSlotsRequest {
+    token: String,
+    holder: String,
+    /// Also find the slot of the allowance `holder` gave `spender`
+    spender: Option<String>,
+    network: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct BatchCallEntry {
+    contract_address: String,
+    function_name: String,
+    parameters: Value,
+    /// Report this call's failure in its own result instead of failing the batch; defaults to true
+    allow_failure: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct BatchCallRequest {
+    calls: Vec<BatchCallEntry>,
+    network: Option<String>,
+    /// Wrap each decoded return value as {"type": ..., "value": ...}
+    include_types: Option<bool>,
+    /// Block number, block hash or tag (latest, pending, safe, finalized) every call runs
+    /// against; defaults to latest
+    block: Option<Value>,
 }
 
 #[derive(Debug, Deserialize, Serialize, JsonSchema)]
@@ -45,14 +107,32 @@ struct EstimateGasRequest {
     from: Option<String>,
     value: Option<String>,
     network: Option<String>,
+    /// Block number, block hash or tag (latest, pending, safe, finalized); defaults to latest
+    block: Option<Value>,
+    /// State overrides by address, e.g. {"0xholder": {"balance": "0x..."}, "0xtoken":
+    /// {"erc20_balances": {"0xholder": "1000"}}}; see the README for all fields
+    state_overrides: Option<Value>,
 }
 
 #[derive(Debug, Deserialize, Serialize, JsonSchema)]
 struct ContractEventsRequest {
     contract_address: String,
-    from_block: Option<u64>,
-    to_block: Option<u64>,
+    /// Block number or tag (latest, finalized, safe, earliest); defaults to the contract's
+    /// deployment block, or 0 if it can't be found. Finding it may take an explorer lookup or a
+    /// binary search over archive state, so pass a block to skip that on the first page.
+    from_block: Option<Value>,
+    /// Block number or tag (latest, finalized, safe, earliest); defaults to latest
+    to_block: Option<Value>,
     network: Option<String>,
+    /// Event name or signature, e.g. "Transfer" or "Transfer(address,address,uint256)"
+    event: Option<String>,
+    /// Indexed argument values keyed by name, e.g. {"to": "0x..."}; an array matches any value
+    indexed_args: Option<Value>,
+    /// Maximum number of events to return (default 1000)
+    limit: Option<usize>,
+    /// `next_cursor` from a previous response, to fetch the next page. Only valid with the same
+    /// contract, event and indexed_args
+    cursor: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, JsonSchema)]
@@ -63,6 +143,69 @@ struct SimulateTransactionRequest {
     from: Option<String>,
     value: Option<String>,
     network: Option<String>,
+    /// Block number, block hash or tag (latest, pending, safe, finalized); defaults to latest
+    block: Option<Value>,
+    /// State overrides by address, e.g. {"0xholder": {"balance": "0x..."}, "0xtoken":
+    /// {"erc20_balances": {"0xholder": "1000"}}}; see the README for all fields
+    state_overrides: Option<Value>,
+    /// Wrap each decoded return value as {"type": ..., "value": ...}
+    include_types: Option<bool>,
+    /// Include the call tree from debug_traceCall, with the call that reverted and why
+    trace: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct CreateForkSessionRequest {
+    /// Block number, block hash or tag (latest, safe, finalized); defaults to latest
+    block: Option<Value>,
+    /// Name of a recording saved by save_fork_session, to start from instead of a block
+    recording: Option<String>,
+    /// Only read state from the recording, never from the network (default false)
+    offline: Option<bool>,
+    network: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct ForkTransactRequest {
+    session_id: String,
+    contract_address: String,
+    /// Function to call; empty for a plain ETH transfer
+    function_name: String,
+    parameters: Value,
+    /// Sender to impersonate; defaults to the zero address
+    from: Option<String>,
+    value: Option<String>,
+    gas_limit: Option<u64>,
+    /// Defaults to zero, so that the sender needs no ETH for gas
+    gas_price: Option<String>,
+    /// State overrides written into the session before the transaction runs, in the format of
+    /// simulate_transaction's; see the README
+    state_overrides: Option<Value>,
+    /// Wrap each decoded return value as {"type": ..., "value": ...}
+    include_types: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct ForkCallRequest {
+    session_id: String,
+    contract_address: String,
+    function_name: String,
+    parameters: Value,
+    from: Option<String>,
+    /// Wrap each decoded return value as {"type": ..., "value": ...}
+    include_types: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct SaveForkSessionRequest {
+    session_id: String,
+    /// File name of the recording, e.g. swap.json; it is written to the recordings directory
+    name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct DeleteForkSessionRequest {
+    session_id: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, JsonSchema)]
@@ -77,6 +220,41 @@ struct SendTransactionRequest {
     network: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct RegisterAbiRequest {
+    address: String,
+    /// JSON ABI (array, artifact object or JSON string) or human-readable signatures, e.g.
+    /// ["function balanceOf(address owner) view returns (uint256)"]
+    abi: Value,
+    network: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct ListAbisRequest {
+    /// Only list ABIs for this network
+    network: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct ShowAbiRequest {
+    address: String,
+    network: Option<String>,
+    /// Return human-readable signatures instead of the JSON ABI
+    human_readable: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct DeleteAbiRequest {
+    address: String,
+    network: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct ClearAbiCacheRequest {
+    /// Also delete manually registered ABIs (default false)
+    include_manual: Option<bool>,
+}
+
 impl ContractMcpServer {
     pub fn new(config: Config) -> Result<Self> {
         let provider_manager = ProviderManager::new(config.clone())?;
@@ -121,6 +299,55 @@ impl ContractMcpServer {
         }
     }
 
+    #[tool(
+        description = "Get a source file of a verified contract, or its compiler settings and file list"
+    )]
+    async fn get_contract_source(&self, #[tool(aggr)] request: ContractSourceRequest) -> String {
+        let mut manager = self.contract_manager.lock().await;
+
+        match manager
+            .get_contract_source(
+                &request.address,
+                request.file.as_deref(),
+                request.network.as_deref(),
+            )
+            .await
+        {
+            Ok(source) => serde_json::to_string_pretty(&source)
+                .unwrap_or_else(|_| "Failed to serialize contract source".to_string()),
+            Err(e) => {
+                error!("Failed to get contract source: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+
+    #[tool(
+        description = "Read a contract storage slot, or decode a state variable using the contract's storage layout"
+    )]
+    async fn read_storage(&self, #[tool(aggr)] request: ReadStorageRequest) -> String {
+        let mut manager = self.contract_manager.lock().await;
+
+        match manager
+            .read_storage(
+                &request.address,
+                request.slot.as_deref(),
+                request.variable.as_deref(),
+                request.storage_layout.as_ref(),
+                request.block.as_ref(),
+                request.network.as_deref(),
+            )
+            .await
+        {
+            Ok(value) => serde_json::to_string_pretty(&value)
+                .unwrap_or_else(|_| "Failed to serialize storage value".to_string()),
+            Err(e) => {
+                error!("Failed to read storage: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+
     #[tool(description = "Call a read-only contract function")]
     async fn call_view_function(&self, #[tool(aggr)] request: ViewFunctionRequest) -> String {
         let mut manager = self.contract_manager.lock().await;
@@ -132,6 +359,9 @@ impl ContractMcpServer {
             gas_limit: None,
             gas_price: None,
             value: None,
+            include_types: request.include_types.unwrap_or(false),
+            block: request.block,
+            state_overrides: request.state_overrides,
         };
 
         match manager
@@ -151,6 +381,67 @@ impl ContractMcpServer {
         }
     }
 
+    #[tool(
+        description = "Call many read-only functions, across any contracts, in one RPC request through Multicall3"
+    )]
+    async fn batch_call(&self, #[tool(aggr)] request: BatchCallRequest) -> String {
+        let mut manager = self.contract_manager.lock().await;
+
+        let include_types = request.include_types.unwrap_or(false);
+        let calls: Vec<BatchCall> = request
+            .calls
+            .into_iter()
+            .map(|entry| BatchCall {
+                contract_address: entry.contract_address,
+                call: ViewCall {
+                    function_name: entry.function_name,
+                    parameters: entry.parameters,
+                    include_types,
+                },
+                allow_failure: entry.allow_failure.unwrap_or(true),
+            })
+            .collect();
+
+        match manager
+            .batch_call(&calls, request.block.as_ref(), request.network.as_deref())
+            .await
+        {
+            Ok(results) => serde_json::to_string_pretty(&results)
+                .unwrap_or_else(|_| "Failed to serialize results".to_string()),
+            Err(e) => {
+                error!("Failed to batch calls: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+
+    #[tool(
+        description = "Find the storage slots of an ERC-20 balance and allowance, for use in state overrides"
+    )]
+    async fn get_erc20_storage_slots(
+        &self,
+        #[tool(aggr)] request: Erc20StorageSlotsRequest,
+    ) -> String {
+        let mut manager = self.contract_manager.lock().await;
+
+        match manager
+            .get_erc20_storage_slots(
+                &request.token,
+                &request.holder,
+                request.spender.as_deref(),
+                request.network.as_deref(),
+            )
+            .await
+        {
+            Ok(slots) => serde_json::to_string_pretty(&slots)
+                .unwrap_or_else(|_| "Failed to serialize storage slots".to_string()),
+            Err(e) => {
+                error!("Failed to find ERC-20 storage slots: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+
     #[tool(description = "Estimate gas cost for a contract function call")]
     async fn estimate_gas(&self, #[tool(aggr)] request: EstimateGasRequest) -> String {
         let mut manager = self.contract_manager.lock().await;
@@ -162,6 +453,9 @@ impl ContractMcpServer {
             gas_limit: None,
             gas_price: None,
             value: request.value,
+            include_types: false,
+            block: request.block,
+            state_overrides: request.state_overrides,
         };
 
         match manager
@@ -172,7 +466,12 @@ impl ContractMcpServer {
             )
             .await
         {
-            Ok(gas_estimate) => format!("Estimated gas: {} units", gas_estimate),
+            Ok((gas_estimate, block)) => match block.number {
+                Some(number) => {
+                    format!("Estimated gas: {} units at block {}", gas_estimate, number)
+                }
+                None => format!("Estimated gas: {} units at the pending block", gas_estimate),
+            },
             Err(e) => {
                 error!("Failed to estimate gas: {}", e);
                 format!("Error: {}", e)
@@ -182,13 +481,24 @@ impl ContractMcpServer {
 
     #[tool(description = "Get events emitted by a smart contract")]
     async fn get_contract_events(&self, #[tool(aggr)] request: ContractEventsRequest) -> String {
-        let manager = self.contract_manager.lock().await;
+        let mut manager = self.contract_manager.lock().await;
+
+        let event_filter = EventFilter {
+            event: request.event,
+            indexed_args: request.indexed_args,
+        };
+        let pagination = EventPagination {
+            limit: request.limit,
+            cursor: request.cursor,
+        };
 
         match manager
             .get_contract_events(
                 &request.contract_address,
-                request.from_block,
-                request.to_block,
+                request.from_block.as_ref(),
+                request.to_block.as_ref(),
+                &event_filter,
+                &pagination,
                 request.network.as_deref(),
             )
             .await
@@ -216,12 +526,16 @@ impl ContractMcpServer {
             gas_limit: None,
             gas_price: None,
             value: request.value,
+            include_types: request.include_types.unwrap_or(false),
+            block: request.block,
+            state_overrides: request.state_overrides,
         };
 
         match manager
             .simulate_transaction(
                 &request.contract_address,
                 &function_call,
+                request.trace.unwrap_or(false),
                 request.network.as_deref(),
             )
             .await
@@ -235,6 +549,130 @@ impl ContractMcpServer {
         }
     }
 
+    #[tool(
+        description = "Start an in-process EVM session forked from a block, to apply transactions one after another and query the resulting state"
+    )]
+    async fn create_fork_session(&self, #[tool(aggr)] request: CreateForkSessionRequest) -> String {
+        let mut manager = self.contract_manager.lock().await;
+
+        match manager
+            .create_fork_session(
+                request.block.as_ref(),
+                request.recording.as_deref(),
+                request.offline.unwrap_or(false),
+                request.network.as_deref(),
+            )
+            .await
+        {
+            Ok(info) => serde_json::to_string_pretty(&info)
+                .unwrap_or_else(|_| "Failed to serialize result".to_string()),
+            Err(e) => {
+                error!("Failed to create fork session: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+
+    #[tool(
+        description = "Apply a transaction to a fork session from any sender, without a signature; its changes stay in the session"
+    )]
+    async fn fork_transact(&self, #[tool(aggr)] request: ForkTransactRequest) -> String {
+        let mut manager = self.contract_manager.lock().await;
+
+        let function_call = FunctionCall {
+            function_name: request.function_name,
+            parameters: request.parameters,
+            from: request.from,
+            gas_limit: request.gas_limit,
+            gas_price: request.gas_price,
+            value: request.value,
+            include_types: request.include_types.unwrap_or(false),
+            block: None,
+            state_overrides: request.state_overrides,
+        };
+
+        match manager
+            .fork_transact(
+                &request.session_id,
+                &request.contract_address,
+                &function_call,
+            )
+            .await
+        {
+            Ok(result) => serde_json::to_string_pretty(&result)
+                .unwrap_or_else(|_| "Failed to serialize result".to_string()),
+            Err(e) => {
+                error!("Failed to apply fork transaction: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+
+    #[tool(description = "Call a read-only function on the current state of a fork session")]
+    async fn fork_call(&self, #[tool(aggr)] request: ForkCallRequest) -> String {
+        let mut manager = self.contract_manager.lock().await;
+
+        let function_call = FunctionCall {
+            function_name: request.function_name,
+            parameters: request.parameters,
+            from: request.from,
+            gas_limit: None,
+            gas_price: None,
+            value: None,
+            include_types: request.include_types.unwrap_or(false),
+            block: None,
+            state_overrides: None,
+        };
+
+        match manager
+            .fork_call(
+                &request.session_id,
+                &request.contract_address,
+                &function_call,
+            )
+            .await
+        {
+            Ok(result) => serde_json::to_string_pretty(&result)
+                .unwrap_or_else(|_| "Failed to serialize result".to_string()),
+            Err(e) => {
+                error!("Failed to call on fork session: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+
+    #[tool(
+        description = "Save the state a fork session has read as a named recording, to create the session again from it offline"
+    )]
+    async fn save_fork_session(&self, #[tool(aggr)] request: SaveForkSessionRequest) -> String {
+        let manager = self.contract_manager.lock().await;
+
+        match manager
+            .save_fork_session(&request.session_id, &request.name)
+            .await
+        {
+            Ok(info) => serde_json::to_string_pretty(&info)
+                .unwrap_or_else(|_| "Failed to serialize result".to_string()),
+            Err(e) => {
+                error!("Failed to save fork session: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+
+    #[tool(description = "Discard a fork session and the state it holds")]
+    async fn delete_fork_session(&self, #[tool(aggr)] request: DeleteForkSessionRequest) -> String {
+        let mut manager = self.contract_manager.lock().await;
+
+        match manager.delete_fork_session(&request.session_id) {
+            Ok(()) => format!("Deleted fork session {}", request.session_id),
+            Err(e) => {
+                error!("Failed to delete fork session: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+
     #[tool(description = "Send a transaction to execute a contract function")]
     async fn send_transaction(&self, #[tool(aggr)] request: SendTransactionRequest) -> String {
         // Check if write operations are allowed
@@ -268,6 +706,9 @@ impl ContractMcpServer {
             gas_limit: request.gas_limit,
             gas_price: request.gas_price.clone(),
             value: request.value,
+            include_types: false,
+            block: None,
+            state_overrides: None,
         };
 
         let mut manager = self.contract_manager.lock().await;
@@ -291,13 +732,94 @@ impl ContractMcpServer {
             }
         }
     }
+
+    #[tool(
+        description = "Register an ABI for a contract as JSON or human-readable signatures, e.g. for unverified contracts; registrations persist across restarts"
+    )]
+    async fn register_abi(&self, #[tool(aggr)] request: RegisterAbiRequest) -> String {
+        let mut manager = self.contract_manager.lock().await;
+
+        match manager
+            .register_abi(&request.address, &request.abi, request.network.as_deref())
+            .await
+        {
+            Ok(stored) => serde_json::to_string_pretty(&stored)
+                .unwrap_or_else(|_| "Failed to serialize result".to_string()),
+            Err(e) => {
+                error!("Failed to register ABI: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+
+    #[tool(description = "List stored ABIs: manual registrations and cached fetches")]
+    async fn list_abis(&self, #[tool(aggr)] request: ListAbisRequest) -> String {
+        let manager = self.contract_manager.lock().await;
+
+        let stored = manager.list_abis(request.network.as_deref()).await;
+        serde_json::to_string_pretty(&stored)
+            .unwrap_or_else(|_| "Failed to serialize ABI list".to_string())
+    }
+
+    #[tool(description = "Show the stored ABI for a contract, without fetching it")]
+    async fn show_abi(&self, #[tool(aggr)] request: ShowAbiRequest) -> String {
+        let manager = self.contract_manager.lock().await;
+
+        match manager
+            .show_abi(
+                &request.address,
+                request.network.as_deref(),
+                request.human_readable.unwrap_or(false),
+            )
+            .await
+        {
+            Ok(abi) => serde_json::to_string_pretty(&abi)
+                .unwrap_or_else(|_| "Failed to serialize ABI".to_string()),
+            Err(e) => {
+                error!("Failed to show ABI: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+
+    #[tool(description = "Delete the stored ABI (manual and cached) for a contract")]
+    async fn delete_abi(&self, #[tool(aggr)] request: DeleteAbiRequest) -> String {
+        let mut manager = self.contract_manager.lock().await;
+
+        match manager
+            .delete_abi(&request.address, request.network.as_deref())
+            .await
+        {
+            Ok(()) => format!("Deleted ABI for {}", request.address),
+            Err(e) => {
+                error!("Failed to delete ABI: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
+
+    #[tool(description = "Clear the ABI cache, optionally including manual registrations")]
+    async fn clear_abi_cache(&self, #[tool(aggr)] request: ClearAbiCacheRequest) -> String {
+        let mut manager = self.contract_manager.lock().await;
+
+        match manager
+            .clear_abi_cache(request.include_manual.unwrap_or(false))
+            .await
+        {
+            Ok(()) => "Cleared ABI cache".to_string(),
+            Err(e) => {
+                error!("Failed to clear ABI cache: {}", e);
+                format!("Error: {}", e)
+            }
+        }
+    }
 }
 
 #[tool(tool_box)]
 impl ServerHandler for ContractMcpServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
-            instructions: Some("MCP server for interacting with Ethereum smart contracts using Alloy. Supports contract inspection, function calls, gas estimation, event retrieval, transaction simulation, and contract transaction sending.".into()),
+            instructions: Some("MCP server for interacting with Ethereum smart contracts using Alloy. Supports contract inspection and verified source retrieval, storage reads, function calls (batched through Multicall3), gas estimation, event retrieval, transaction simulation, multi-step simulation on forked EVM sessions, contract transaction sending, and ABI management.".into()),
             capabilities: ServerCapabilities::builder().enable_tools().build(),
             ..Default::default()
         }